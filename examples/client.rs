@@ -1,86 +1,118 @@
 use anyhow::Context;
+use bifrost::client::{BifrostClient, DeliveryReport, load_server_certificates};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     pubkey::Pubkey,
     signature::{Keypair, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, VersionedTransaction},
 };
 use solana_system_interface::instruction as system_instruction;
-use std::{fs, io, thread::sleep, time::Duration};
-use url::Url;
+use std::{env, fs, thread::sleep, time::Duration};
 
 const BIFROST_URL: &str = "https://127.0.0.1:4433";
 const CERT_PATH: &str = "certs/cert.pem";
 const RPC_URL: &str = "https://api.devnet.solana.com";
+/// Environment variable holding the payer keypair path, if `--keypair` isn't given
+const KEYPAIR_ENV: &str = "BIFROST_KEYPAIR";
 
 const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const TRANSACTION_COUNT: usize = 10;
+
+const USAGE: &str = "Usage: cargo run --example client -- [--keypair <path>] [--url <bifrost url>] [--cert <cert path>] [--rpc <rpc url>]";
+
+/// Command line options, each falling back to a local development default.
+struct Args {
+    keypair: Option<String>,
+    url: String,
+    cert: String,
+    rpc: String,
+}
+
+impl Args {
+    fn parse() -> anyhow::Result<Self> {
+        let mut args = Args {
+            keypair: env::var(KEYPAIR_ENV).ok(),
+            url: BIFROST_URL.to_string(),
+            cert: CERT_PATH.to_string(),
+            rpc: RPC_URL.to_string(),
+        };
+
+        let mut argv = env::args().skip(1);
+        while let Some(flag) = argv.next() {
+            let value = argv
+                .next()
+                .with_context(|| format!("Missing value for {}\n{}", flag, USAGE))?;
+            match flag.as_str() {
+                "--keypair" => args.keypair = Some(value),
+                "--url" => args.url = value,
+                "--cert" => args.cert = value,
+                "--rpc" => args.rpc = value,
+                _ => anyhow::bail!("Unknown argument: {}\n{}", flag, USAGE),
+            }
+        }
+
+        Ok(args)
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
 
-    println!("Connecting to Bifrost at {}", BIFROST_URL);
+    let args = Args::parse()?;
+    let payer = load_keypair(args.keypair.as_deref())?;
+    println!("Payer {}", payer.pubkey());
+
+    println!("Connecting to Bifrost at {}", args.url);
 
-    let session = connect_to_bifrost().await?;
+    let certs = load_server_certificates(&args.cert)?;
+    let client = BifrostClient::connect(&args.url, certs).await?;
     println!("Connected to Bifrost successfully");
 
-    let mut success = vec![];
-    for _ in 0..10 {
-        let transaction = create_test_without_airdrop().await?;
+    let rpc_client = RpcClient::new(args.rpc.clone());
+
+    let mut sent = 0;
+    for _ in 0..TRANSACTION_COUNT {
+        let transaction = build_transfer_transaction(&rpc_client, &payer)?;
         println!(
             "Created transaction with signature: {}",
             transaction.signatures[0]
         );
 
-        match send_transaction(&session, transaction.clone()).await {
-            Ok(_) => success.push(true),
-            Err(_) => (),
+        let transaction = VersionedTransaction::from(transaction);
+        match client.send_transaction(&transaction).await {
+            Ok(report) => {
+                print_report(&report);
+                sent += 1;
+            }
+            Err(e) => println!("Failed to send transaction: {:#}", e),
         };
         sleep(Duration::from_millis(1200));
     }
 
     println!(
-        "Successfully sent {} out of 100 transactions",
-        success.len()
+        "Successfully sent {} out of {} transactions",
+        sent, TRANSACTION_COUNT
     );
 
     Ok(())
 }
 
-async fn create_test_without_airdrop() -> anyhow::Result<Transaction> {
-    let rpc_client = RpcClient::new(RPC_URL.to_string());
-
-    let payer = Keypair::from_base58_string(
-        "",
-    );
-
-    println!("Payer {}", payer.pubkey());
-
-    build_transfer_transaction(&rpc_client, &payer)
-}
-
-/// Connects to Bifrost WebTransport server.
-async fn connect_to_bifrost() -> anyhow::Result<web_transport_quinn::Session> {
-    let cert_file = fs::File::open(CERT_PATH)
-        .context(format!("Failed to open certificate file: {}", CERT_PATH))?;
-
-    let mut cert_reader = io::BufReader::new(cert_file);
-    let certs: Vec<_> = rustls_pemfile::certs(&mut cert_reader)
-        .collect::<Result<_, _>>()
-        .context("Failed to parse certificates")?;
-
-    anyhow::ensure!(!certs.is_empty(), "No certificates found in {}", CERT_PATH);
-
-    let client = web_transport_quinn::ClientBuilder::new()
-        .with_server_certificates(certs)
-        .context("Failed to create WebTransport client")?;
-
-    let url = Url::parse(BIFROST_URL).context(format!("Invalid Bifrost URL: {}", BIFROST_URL))?;
-
-    client
-        .connect(url)
-        .await
-        .context("Failed to connect to Bifrost")
+/// Reads the payer from a Solana CLI keypair file (a JSON array of 64 bytes).
+fn load_keypair(path: Option<&str>) -> anyhow::Result<Keypair> {
+    let path = path.with_context(|| {
+        format!(
+            "No payer keypair given. Pass --keypair <path> or set {}, e.g. to a file created with `solana-keygen new`\n{}",
+            KEYPAIR_ENV, USAGE
+        )
+    })?;
+
+    let contents =
+        fs::read_to_string(path).context(format!("Failed to read keypair file: {}", path))?;
+    let bytes: Vec<u8> = serde_json::from_str(&contents)
+        .context(format!("Keypair file is not a JSON byte array: {}", path))?;
+
+    Keypair::try_from(bytes.as_slice()).context(format!("Invalid keypair in {}", path))
 }
 
 /// Creates a test transaction by requesting an airdrop and transferring funds.
@@ -153,37 +185,17 @@ fn build_transfer_transaction(client: &RpcClient, payer: &Keypair) -> anyhow::Re
     Ok(transaction)
 }
 
-/// Sends a transaction to Bifrost via WebTransport.
-async fn send_transaction(
-    session: &web_transport_quinn::Session,
-    transaction: Transaction,
-) -> anyhow::Result<()> {
-    let tx_bytes = bincode::serialize(&transaction).context("Failed to serialize transaction")?;
-
-    println!("Sending transaction ({} bytes)", tx_bytes.len());
-
-    let (mut send, mut recv) = session
-        .open_bi()
-        .await
-        .context("Failed to open bidirectional stream")?;
-
-    send.write_all(&tx_bytes)
-        .await
-        .context("Failed to write transaction data")?;
-
-    send.finish().context("Failed to finish send stream")?;
-
-    let response = recv
-        .read_to_end(1024)
-        .await
-        .context("Failed to read response")?;
-
-    let response_str = String::from_utf8_lossy(&response);
-    println!("Server response: {}", response_str);
-
-    if !response_str.starts_with("OK") {
-        anyhow::bail!("Transaction rejected: {}", response_str);
+/// Prints how the server handled a transaction.
+fn print_report(report: &DeliveryReport) {
+    match report {
+        DeliveryReport::Forwarded {
+            signature: Some(signature),
+            latency: Some(latency),
+        } => println!("Forwarded {} in {} ms", signature, latency.as_millis()),
+        DeliveryReport::Forwarded { .. } => println!("Forwarded"),
+        DeliveryReport::Confirmed { signature, status } => {
+            println!("Forwarded {}, {}", signature, status)
+        }
+        DeliveryReport::DryRun => println!("Resolved leaders, not sent (server in dry-run mode)"),
     }
-
-    Ok(())
 }
@@ -0,0 +1,84 @@
+//! Certificate helpers shared by the server, the TPU client and the binary:
+//! fingerprints and generated self-signed certificates.
+
+use anyhow::{Context, Result};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use std::net::SocketAddr;
+
+/// Validity of generated self-signed certificates. Browsers only accept a
+/// certificate pinned with `serverCertificateHashes` if it is valid for at
+/// most two weeks.
+const SELF_SIGNED_VALIDITY: time::Duration = time::Duration::days(13);
+
+/// SHA-256 fingerprint of `cert` in hex, as browsers take it in
+/// `serverCertificateHashes` and `test.html` reads it from
+/// `certs/localhost.hex`.
+pub fn certificate_hash(cert: &CertificateDer<'_>) -> String {
+    let sha256 = rustls::crypto::ring::cipher_suite::TLS13_AES_128_GCM_SHA256
+        .tls13()
+        .expect("TLS 1.3 cipher suite")
+        .common
+        .hash_provider;
+    sha256
+        .hash(cert)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Names a self-signed certificate for listeners on `addrs` covers:
+/// `localhost`, the loopback addresses and every specific listen address.
+pub fn self_signed_names(addrs: &[SocketAddr]) -> Vec<String> {
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    for addr in addrs {
+        let ip = addr.ip().to_string();
+        if !addr.ip().is_unspecified() && !names.contains(&ip) {
+            names.push(ip);
+        }
+    }
+    names
+}
+
+/// Generates an ECDSA P-256 certificate for `subject_alt_names` signed by its
+/// own key, for local development without certificate files. IP addresses in
+/// `subject_alt_names` are added as IP SANs, anything else as DNS names.
+///
+/// The certificate is valid for 13 days, so browsers accept it when pinned by
+/// its hash, see [`certificate_hash`].
+///
+/// # Errors
+///
+/// Returns an error if a name isn't a valid DNS name or IP address.
+pub fn generate_self_signed(
+    subject_alt_names: &[String],
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let (cert, key_pair) = self_signed(subject_alt_names)?;
+    let private_key = PrivatePkcs8KeyDer::from(key_pair.serialize_der());
+    Ok((vec![cert.der().clone()], private_key.into()))
+}
+
+/// Same as [`generate_self_signed`], as PEM data, e.g. for
+/// [`BifrostServer::from_pem`](crate::server::BifrostServer::from_pem).
+pub fn generate_self_signed_pem(subject_alt_names: &[String]) -> Result<(String, String)> {
+    let (cert, key_pair) = self_signed(subject_alt_names)?;
+    Ok((cert.pem(), key_pair.serialize_pem()))
+}
+
+fn self_signed(subject_alt_names: &[String]) -> Result<(rcgen::Certificate, rcgen::KeyPair)> {
+    let mut params = rcgen::CertificateParams::new(subject_alt_names.to_vec())
+        .context("Invalid subject alternative name")?;
+    // Some leeway for clocks running behind
+    params.not_before = time::OffsetDateTime::now_utc() - time::Duration::hours(1);
+    params.not_after = params.not_before + SELF_SIGNED_VALIDITY;
+
+    let key_pair = rcgen::KeyPair::generate().context("Failed to generate key pair")?;
+    let cert = params
+        .self_signed(&key_pair)
+        .context("Failed to sign certificate")?;
+    Ok((cert, key_pair))
+}
@@ -0,0 +1,264 @@
+//! WebTransport client for submitting transactions to a Bifrost server from
+//! Rust, so applications don't have to implement the stream framing and the
+//! response format themselves.
+//!
+//! ```no_run
+//! use bifrost::client::{BifrostClient, load_server_certificates};
+//! # use solana_sdk::transaction::VersionedTransaction;
+//!
+//! # async fn example(transaction: VersionedTransaction) -> anyhow::Result<()> {
+//! let certs = load_server_certificates("certs/cert.pem")?;
+//! let client = BifrostClient::connect("https://127.0.0.1:4433", certs).await?;
+//!
+//! let report = client.send_transaction(&transaction).await?;
+//! println!("{:?}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::server::ConfirmationStatus;
+use anyhow::{Context, Result, ensure};
+use rustls::pki_types::CertificateDer;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+use url::Url;
+
+/// Longest response read back from the server.
+const MAX_RESPONSE_SIZE: usize = 1024;
+
+/// How the server handled a transaction it accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryReport {
+    /// Delivered to at least one leader. The signature and the server-side
+    /// latency are missing for transactions without a signature.
+    Forwarded {
+        signature: Option<Signature>,
+        latency: Option<Duration>,
+    },
+    /// Delivered, then polled for until it reached `status` or the server
+    /// stopped polling, see
+    /// [`SessionConfig::confirmation`](crate::server::SessionConfig::confirmation).
+    Confirmed {
+        signature: Signature,
+        status: ConfirmationStatus,
+    },
+    /// The server runs in dry-run mode: it resolved the leaders but sent
+    /// nothing, see
+    /// [`TpuClientConfig::dry_run`](crate::tpu_client::TpuClientConfig::dry_run).
+    DryRun,
+}
+
+/// A transaction the server didn't forward. Returned by [`BifrostClient`]
+/// inside its [`anyhow::Error`], from which it can be downcast.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ResponseError {
+    /// The transaction is malformed, e.g. has no fee payer.
+    #[error("Transaction rejected: {0}")]
+    Rejected(String),
+    /// Forwarding failed, see [`code`](Self::code).
+    #[error("Server error: {0}")]
+    Failed(String),
+    /// The server's request budget ran out in the given phase.
+    #[error("Request timed out in phase: {0}")]
+    TimedOut(String),
+    #[error("Unexpected response: {0}")]
+    Unexpected(String),
+}
+
+impl ResponseError {
+    /// Error code the server prefixed a [`Failed`](Self::Failed) message with,
+    /// see [`GatewayError::code`](crate::GatewayError::code), e.g. `saturated`
+    /// when the client should back off.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ResponseError::Failed(message) => {
+                let (code, _) = message.split_once(": ")?;
+                (!code.contains(' ')).then_some(code)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Session with a Bifrost server. Each transaction is sent on its own stream,
+/// so a client can be shared between tasks.
+#[derive(Clone)]
+pub struct BifrostClient {
+    session: web_transport_quinn::Session,
+}
+
+impl fmt::Debug for BifrostClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BifrostClient").finish_non_exhaustive()
+    }
+}
+
+impl BifrostClient {
+    /// Connects to the server at `url`, e.g. `https://127.0.0.1:4433`, trusting
+    /// `certs` as its certificate. Servers with token auth expect the token in
+    /// the URL, e.g. `https://127.0.0.1:4433/?token=secret`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid or the session can't be
+    /// established.
+    pub async fn connect(url: &str, certs: Vec<CertificateDer<'static>>) -> Result<Self> {
+        let url = Url::parse(url).context(format!("Invalid Bifrost URL: {}", url))?;
+
+        let client = web_transport_quinn::ClientBuilder::new()
+            .with_server_certificates(certs)
+            .context("Failed to create WebTransport client")?;
+        let session = client
+            .connect(url)
+            .await
+            .context("Failed to connect to Bifrost")?;
+
+        Ok(Self { session })
+    }
+
+    /// Sends `transaction` and waits for the server's response.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ResponseError`] if the server didn't forward the
+    /// transaction, or an error if the stream failed.
+    pub async fn send_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<DeliveryReport> {
+        let tx_data = bincode::serialize(transaction).context("Failed to serialize transaction")?;
+        self.send_raw(&tx_data).await
+    }
+
+    /// Sends an already serialized transaction, optionally prefixed with the
+    /// target-slot header byte, and waits for the server's response.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`send_transaction`](Self::send_transaction).
+    pub async fn send_raw(&self, tx_data: &[u8]) -> Result<DeliveryReport> {
+        let (mut send, mut recv) = self
+            .session
+            .open_bi()
+            .await
+            .context("Failed to open bidirectional stream")?;
+
+        send.write_all(tx_data)
+            .await
+            .context("Failed to write transaction data")?;
+        send.finish().context("Failed to finish send stream")?;
+
+        let response = recv
+            .read_to_end(MAX_RESPONSE_SIZE)
+            .await
+            .context("Failed to read response")?;
+
+        Ok(parse_response(&String::from_utf8_lossy(&response))?)
+    }
+}
+
+/// Reads the certificates to trust from a PEM file, e.g. the server's
+/// self-signed certificate.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or holds no certificate.
+pub fn load_server_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = fs::read(path).context(format!("Failed to open certificate file: {}", path))?;
+    let certs: Vec<_> = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<Result<_, _>>()
+        .context("Failed to parse certificates")?;
+
+    ensure!(!certs.is_empty(), "No certificates found in {}", path);
+    Ok(certs)
+}
+
+/// Parses the response to a single transaction.
+fn parse_response(response: &str) -> Result<DeliveryReport, ResponseError> {
+    if let Some(reason) = response.strip_prefix("REJECTED: ") {
+        return Err(ResponseError::Rejected(reason.to_string()));
+    }
+    if let Some(message) = response.strip_prefix("ERROR: ") {
+        return Err(ResponseError::Failed(message.to_string()));
+    }
+    if let Some(phase) = response.strip_prefix("TIMEOUT: ") {
+        return Err(ResponseError::TimedOut(phase.to_string()));
+    }
+
+    let unexpected = || ResponseError::Unexpected(response.to_string());
+    let fields: Vec<&str> = response.split_whitespace().collect();
+    match fields.as_slice() {
+        ["OK"] => Ok(DeliveryReport::Forwarded {
+            signature: None,
+            latency: None,
+        }),
+        ["OK", "DRYRUN"] => Ok(DeliveryReport::DryRun),
+        ["OK", signature, detail] => {
+            let signature: Signature = signature.parse().map_err(|_| unexpected())?;
+            if let Ok(latency_ms) = detail.parse() {
+                Ok(DeliveryReport::Forwarded {
+                    signature: Some(signature),
+                    latency: Some(Duration::from_millis(latency_ms)),
+                })
+            } else {
+                let status = detail.parse().map_err(|_| unexpected())?;
+                Ok(DeliveryReport::Confirmed { signature, status })
+            }
+        }
+        _ => Err(unexpected()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_every_response() {
+        let signature = Signature::from([3; 64]);
+
+        assert_eq!(
+            parse_response(&format!("OK {} 12", signature)),
+            Ok(DeliveryReport::Forwarded {
+                signature: Some(signature),
+                latency: Some(Duration::from_millis(12)),
+            })
+        );
+        assert_eq!(
+            parse_response(&format!("OK {} finalized", signature)),
+            Ok(DeliveryReport::Confirmed {
+                signature,
+                status: ConfirmationStatus::Finalized,
+            })
+        );
+        assert_eq!(
+            parse_response("OK"),
+            Ok(DeliveryReport::Forwarded {
+                signature: None,
+                latency: None,
+            })
+        );
+        assert_eq!(parse_response("OK DRYRUN"), Ok(DeliveryReport::DryRun));
+
+        let saturated =
+            parse_response("ERROR: saturated: TPU connection pool saturated").unwrap_err();
+        assert_eq!(saturated.code(), Some("saturated"));
+        let rate_limited = parse_response("ERROR: rate limited").unwrap_err();
+        assert_eq!(rate_limited.code(), None);
+        assert_eq!(
+            parse_response("REJECTED: missing fee payer"),
+            Err(ResponseError::Rejected("missing fee payer".to_string()))
+        );
+        assert_eq!(
+            parse_response("TIMEOUT: forward"),
+            Err(ResponseError::TimedOut("forward".to_string()))
+        );
+        assert!(matches!(
+            parse_response("OK not-a-signature 12"),
+            Err(ResponseError::Unexpected(_))
+        ));
+    }
+}
@@ -1,2 +1,22 @@
-pub const MAX_TRANSACTION_SIZE: usize = 10_000_000;
-pub const DEFAULT_TPU_ADDRESS: &str = "127.0.0.1:8009"; // says 8003 but thats LEGACY, TPU QUIC  is 8009
+/// Largest serialized transaction a validator accepts, the size of a standard
+/// Solana packet (IPv6 MTU minus headers)
+pub const PACKET_DATA_SIZE: usize = 1232;
+/// Default of [`SessionConfig::max_transaction_size`](crate::server::SessionConfig::max_transaction_size),
+/// the most a validator accepts
+pub const MAX_TRANSACTION_SIZE: usize = PACKET_DATA_SIZE;
+/// Largest datagram payload accepted: a single-packet transaction plus the
+/// optional target-slot header byte
+pub const MAX_DATAGRAM_SIZE: usize = PACKET_DATA_SIZE + 1;
+/// Target duration of a slot on mainnet
+pub const SLOT_DURATION: std::time::Duration = std::time::Duration::from_millis(400);
+pub const MAX_BATCH_TRANSACTIONS: usize = 256;
+/// Session path that switches streams to batch framing
+pub const BATCH_PATH: &str = "/batch";
+/// TPU QUIC socket of a local `solana-test-validator`, the default of
+/// [`TpuClientConfig::local_validator`](crate::tpu_client::TpuClientConfig::local_validator).
+/// The validator also advertises 8003, but that is the legacy UDP TPU
+pub const DEFAULT_TPU_ADDRESS: &str = "127.0.0.1:8009";
+/// Address the `bifrost` binary listens on unless given `--bind` or
+/// `BIFROST_BIND_ADDR`
+pub const DEFAULT_BIND_ADDRESS: &str = "[::]:4433";
+pub const POOL_SNAPSHOT_PATH: &str = "bifrost-pool-snapshot.json";
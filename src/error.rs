@@ -1,12 +1,83 @@
+//! Typed errors returned to clients and embedders.
+
 #[derive(Debug, thiserror::Error)]
 pub enum GatewayError {
     #[error("Invalid transaction: {0}")]
     InvalidTransaction(String),
 
+    #[error("transaction too large (max {max} bytes)")]
+    TransactionTooLarge { max: usize },
+
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
 
     #[error("Delivery timeout")]
     DeliveryTimeout,
-    // ... more variants
+
+    /// Too many transactions are being written to leaders at once. Clients
+    /// should back off and retry.
+    #[error("TPU connection pool saturated: {in_flight} streams in flight")]
+    Saturated { in_flight: usize },
+
+    #[error("Server not ready: {0}")]
+    NotReady(String),
+
+    #[error("No leaders known for the target slot")]
+    NoLeaders,
+
+    /// The current slot stopped advancing, so the leaders it resolves to are
+    /// likely wrong.
+    #[error("Leader tracker is stale: no new slot for {age:?}")]
+    StaleTracker { age: std::time::Duration },
+
+    #[error("Stream error: {0}")]
+    Stream(String),
+
+    #[error("Failed to write to QUIC stream: {0}")]
+    Write(#[from] quinn::WriteError),
+
+    #[error("Failed to finish QUIC stream: {0}")]
+    Finish(#[from] quinn::ClosedStream),
+
+    /// The validator refused the stream with STOP_SENDING, e.g. because it is
+    /// throttling this client.
+    #[error("Validator stopped the stream with code {0}")]
+    Stopped(u64),
+
+    #[error("QUIC stream was not acknowledged: {0}")]
+    Unacknowledged(#[from] quinn::StoppedError),
+}
+
+impl GatewayError {
+    /// Returns `true` if another attempt may get past the error: the
+    /// connection failed or the validator didn't acknowledge in time. Refusals
+    /// such as [`Stopped`](Self::Stopped) are not transient.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            GatewayError::ConnectionFailed(_)
+                | GatewayError::DeliveryTimeout
+                | GatewayError::Write(quinn::WriteError::ConnectionLost(_))
+                | GatewayError::Unacknowledged(quinn::StoppedError::ConnectionLost(_))
+        )
+    }
+
+    /// Stable, machine-readable identifier of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            GatewayError::InvalidTransaction(_) => "invalid_transaction",
+            GatewayError::TransactionTooLarge { .. } => "transaction_too_large",
+            GatewayError::ConnectionFailed(_) => "connection_failed",
+            GatewayError::DeliveryTimeout => "delivery_timeout",
+            GatewayError::Saturated { .. } => "saturated",
+            GatewayError::NotReady(_) => "not_ready",
+            GatewayError::NoLeaders => "no_leaders",
+            GatewayError::StaleTracker { .. } => "stale_tracker",
+            GatewayError::Stream(_) => "stream_error",
+            GatewayError::Stopped(_) => "stream_stopped",
+            GatewayError::Write(_) | GatewayError::Finish(_) | GatewayError::Unacknowledged(_) => {
+                "quic_stream_error"
+            }
+        }
+    }
 }
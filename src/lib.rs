@@ -9,28 +9,43 @@
 //!
 //! - WebTransport server for browser connectivity
 //! - Direct TPU connection management
-//! - Transaction forwarding from WebTransport to QUIC/UDP
+//! - Transaction forwarding from WebTransport to QUIC, with an optional
+//!   fallback to the legacy UDP TPU port
+//! - Optional OpenTelemetry span export (`otel` feature)
+//! - Optional Geyser gRPC slot updates (`grpc` feature)
+//! - Optional Rust client for submitting transactions (`client` feature)
 //!
 //! ## Example
 //!
 //! ```no_run
 //! use bifrost::server::BifrostServer;
-//! use std::net::SocketAddr;
 //!
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
-//!     let addr: SocketAddr = "[::]:4433".parse()?;
-//!     let server = BifrostServer::new(addr, "certs/cert.pem", "certs/key.pem");
+//!     let server = BifrostServer::builder()
+//!         .addr("[::]:4433".parse()?)
+//!         .certificate("certs/cert.pem", "certs/key.pem")
+//!         .rpc_url("https://api.mainnet-beta.solana.com")
+//!         .ws_url("wss://api.mainnet-beta.solana.com/")
+//!         .build()?;
 //!     server.run().await?;
 //!     Ok(())
 //! }
 //! ```
 //!
 
+pub mod cert;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod constants;
+pub mod error;
 pub mod server;
+#[cfg(feature = "otel")]
+pub mod telemetry;
 pub mod tpu_client;
+pub mod utils;
 
+pub use error::GatewayError;
 pub use server::BifrostServer;
 pub use tpu_client::TpuConnectionManager;
 
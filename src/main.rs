@@ -1,5 +1,19 @@
-use anyhow::Result;
-use bifrost::server::BifrostServer;
+use anyhow::{Context, Result, anyhow};
+use bifrost::cert::{certificate_hash, generate_self_signed_pem, self_signed_names};
+use bifrost::constants::DEFAULT_BIND_ADDRESS;
+use bifrost::server::{
+    BifrostServer, BlockhashConfig, ConfirmationConfig, SessionConfig, StaticTokens,
+    load_certificates_from_pem,
+};
+use bifrost::tpu_client::{LeaderTrackerConfig, SlotSource, TpuClientConfig, ValidatorPins};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use std::future::Future;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -7,12 +21,370 @@ async fn main() -> Result<()> {
         .install_default()
         .expect("Failed to install rustls crypto provider");
 
-    env_logger::init();
+    // Tag the lines logged while handling a session with its correlation ID
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let timestamp = buf.timestamp();
+            match bifrost::server::correlation_id() {
+                Some(id) => writeln!(
+                    buf,
+                    "[{} {} {} {}] {}",
+                    timestamp,
+                    record.level(),
+                    record.target(),
+                    id,
+                    record.args()
+                ),
+                None => writeln!(
+                    buf,
+                    "[{} {} {}] {}",
+                    timestamp,
+                    record.level(),
+                    record.target(),
+                    record.args()
+                ),
+            }
+        })
+        .init();
 
-    let addr = "[::]:4433".parse()?;
-    let server = BifrostServer::new(addr, "certs/cert.pem", "certs/key.pem");
+    // Export transaction-flow spans when built with `--features otel`
+    #[cfg(feature = "otel")]
+    let tracer_provider = match std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        Ok(endpoint) => Some(bifrost::telemetry::init_otlp(&endpoint)?),
+        Err(_) => None,
+    };
 
-    server.run().await?;
+    // Slot updates from Geyser gRPC, e.g. BIFROST_GRPC_ENDPOINT=https://grpc.example.com:10000
+    let mut leader_tracker_config = LeaderTrackerConfig::default();
+    if let Ok(endpoint) = std::env::var("BIFROST_GRPC_ENDPOINT") {
+        leader_tracker_config.slot_source = SlotSource::Grpc {
+            endpoint,
+            x_token: std::env::var("BIFROST_GRPC_X_TOKEN").ok(),
+        };
+    }
+
+    // Leader schedule cache for faster restarts, e.g. BIFROST_SCHEDULE_CACHE=schedule.bin
+    if let Ok(cache_path) = std::env::var("BIFROST_SCHEDULE_CACHE") {
+        leader_tracker_config.schedule_cache = Some(cache_path.into());
+    }
+
+    // Extra RPC endpoints to fail over to, e.g. BIFROST_FALLBACK_RPC_URLS=https://a,https://b
+    if let Ok(urls) = std::env::var("BIFROST_FALLBACK_RPC_URLS") {
+        leader_tracker_config.fallback_rpc_urls = urls
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+
+    // Forward to a local solana-test-validator instead of the cluster's leaders,
+    // at its default TPU socket with BIFROST_LOCAL_VALIDATOR=1 or another one
+    // with e.g. BIFROST_LOCAL_VALIDATOR=127.0.0.1:9009
+    let mut tpu_config = match std::env::var("BIFROST_LOCAL_VALIDATOR") {
+        Ok(addr) if addr == "1" => TpuClientConfig::local_validator(),
+        Ok(addr) => TpuClientConfig::local_validator_at(
+            addr.parse()
+                .context(format!("Invalid BIFROST_LOCAL_VALIDATOR: {}", addr))?,
+        ),
+        Err(_) => TpuClientConfig::default(),
+    };
+
+    // Staked identity for stake-weighted QoS, e.g. BIFROST_IDENTITY_KEYPAIR=identity.json
+    if let Ok(keypair_path) = std::env::var("BIFROST_IDENTITY_KEYPAIR") {
+        tpu_config.identity = Some(Arc::new(read_keypair(&keypair_path)?));
+    }
+    // Only connect to these validator identities, e.g. BIFROST_PINNED_VALIDATORS=<pubkey>,<pubkey>
+    if let Ok(identities) = std::env::var("BIFROST_PINNED_VALIDATORS") {
+        let identities = identities
+            .split(',')
+            .map(|identity| {
+                identity
+                    .trim()
+                    .parse::<Pubkey>()
+                    .context(format!("Invalid validator identity: {}", identity))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tpu_config.pinned_validators = Some(ValidatorPins::identities(identities));
+    }
+    // Log where transactions would go without sending them, e.g. BIFROST_DRY_RUN=1
+    tpu_config.dry_run = std::env::var("BIFROST_DRY_RUN").is_ok();
+    // Send over UDP to leaders QUIC can't reach in time, e.g. BIFROST_UDP_FALLBACK_MS=200
+    if let Ok(timeout_ms) = std::env::var("BIFROST_UDP_FALLBACK_MS") {
+        let timeout_ms: u64 = timeout_ms
+            .parse()
+            .context(format!("Invalid BIFROST_UDP_FALLBACK_MS: {}", timeout_ms))?;
+        tpu_config.udp_fallback = Some(Duration::from_millis(timeout_ms));
+    }
+    // Streams open on a single leader connection at once, e.g. BIFROST_MAX_STREAMS_PER_CONNECTION=128
+    if let Ok(max_streams) = std::env::var("BIFROST_MAX_STREAMS_PER_CONNECTION") {
+        tpu_config.max_streams_per_connection = max_streams.parse().context(format!(
+            "Invalid BIFROST_MAX_STREAMS_PER_CONNECTION: {}",
+            max_streams
+        ))?;
+    }
+
+    // Answer with the confirmation status instead of the latency, e.g. BIFROST_CONFIRMATIONS=1
+    let mut session_config = SessionConfig::default();
+    if std::env::var("BIFROST_CONFIRMATIONS").is_ok() {
+        session_config.confirmation = Some(ConfirmationConfig::default());
+    }
+    // Drop transactions with an expired blockhash, e.g. BIFROST_BLOCKHASH_CHECK=1
+    if std::env::var("BIFROST_BLOCKHASH_CHECK").is_ok() {
+        session_config.blockhash_check = Some(BlockhashConfig::default());
+    }
+
+    // Private deployment, e.g. BIFROST_AUTH_TOKENS=token-a,token-b
+    let mut auth_tokens = None;
+    if let Ok(tokens) = std::env::var("BIFROST_AUTH_TOKENS") {
+        let tokens: Vec<&str> = tokens
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .collect();
+        if tokens.is_empty() {
+            return Err(anyhow!("BIFROST_AUTH_TOKENS is set but holds no token"));
+        }
+        auth_tokens = Some(StaticTokens::new(tokens));
+    }
+
+    // One listener per address, e.g. --bind 0.0.0.0:4433 --bind [::]:4433
+    let args = Args::parse(
+        std::env::args().skip(1),
+        std::env::var("BIFROST_BIND_ADDR").ok(),
+    )?;
+    let bind_addrs = args.bind_addrs;
+
+    // A generated certificate instead of certs/, shared by every listener, for
+    // a quick local try, e.g. BIFROST_SELF_SIGNED=1 or --self-signed
+    let self_signed = if args.self_signed || std::env::var("BIFROST_SELF_SIGNED").is_ok() {
+        let names = self_signed_names(&bind_addrs);
+        let (cert_pem, key_pem) = generate_self_signed_pem(&names)?;
+        let (cert_chain, _) = load_certificates_from_pem(cert_pem.as_bytes(), key_pem.as_bytes())?;
+        log::warn!(
+            "Using a generated self-signed certificate for {}, SHA-256 fingerprint {}",
+            names.join(", "),
+            certificate_hash(&cert_chain[0])
+        );
+        Some((cert_pem, key_pem))
+    } else {
+        None
+    };
+
+    let listener = |addr| -> Result<BifrostServer> {
+        let mut server = match &self_signed {
+            Some((cert_pem, key_pem)) => BifrostServer::from_pem(
+                addr,
+                cert_pem.as_bytes(),
+                key_pem.as_bytes(),
+                leader_tracker_config.clone(),
+            ),
+            None => BifrostServer::new(
+                addr,
+                "certs/cert.pem",
+                "certs/key.pem",
+                leader_tracker_config.clone(),
+            ),
+        }
+        .with_tpu_config(tpu_config.clone())
+        .with_session_config(session_config.clone());
+        if let Some(tokens) = &auth_tokens {
+            server = server.with_token_auth(tokens.clone());
+        }
+        Ok(server)
+    };
+    let mut server = listener(bind_addrs[0])?;
+
+    // Prometheus endpoint, e.g. BIFROST_METRICS_ADDR=0.0.0.0:9090
+    if let Ok(metrics_addr) = std::env::var("BIFROST_METRICS_ADDR") {
+        server = server.with_prometheus(metrics_addr.parse()?);
+    }
+
+    // Orchestrator probes, e.g. BIFROST_HEALTH_ADDR=0.0.0.0:8080
+    if let Ok(health_addr) = std::env::var("BIFROST_HEALTH_ADDR") {
+        server = server.with_health_check(health_addr.parse()?);
+    }
+
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    if bind_addrs.len() == 1 {
+        server.run_until(ctrl_c).await?;
+    } else {
+        let others = bind_addrs[1..]
+            .iter()
+            .map(|addr| listener(*addr))
+            .collect::<Result<_>>()?;
+        run_listeners(server, others, ctrl_c).await?;
+    }
+
+    #[cfg(feature = "otel")]
+    if let Some(provider) = tracer_provider {
+        provider.shutdown()?;
+    }
 
     Ok(())
 }
+
+/// Command line arguments.
+struct Args {
+    /// Addresses to listen on: those of the `--bind <addr>` arguments, else of
+    /// `BIFROST_BIND_ADDR`, else [`DEFAULT_BIND_ADDRESS`]. Each may list
+    /// several addresses separated by commas.
+    bind_addrs: Vec<SocketAddr>,
+    /// `--self-signed`: serve a generated certificate instead of the one in
+    /// `certs/`.
+    self_signed: bool,
+}
+
+impl Args {
+    /// Parses `args`, without the program name. `bind_env` is the value of
+    /// `BIFROST_BIND_ADDR`, if set.
+    fn parse(args: impl IntoIterator<Item = String>, bind_env: Option<String>) -> Result<Self> {
+        let mut lists = Vec::new();
+        let mut self_signed = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--bind=") {
+                Some(list) => lists.push(list.to_string()),
+                None if arg == "--bind" => {
+                    lists.push(args.next().context("--bind needs an address")?)
+                }
+                None if arg == "--self-signed" => self_signed = true,
+                None => return Err(anyhow!("Unknown argument: {}", arg)),
+            }
+        }
+        if lists.is_empty() {
+            lists.push(bind_env.unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string()));
+        }
+
+        let bind_addrs = lists
+            .iter()
+            .flat_map(|list| list.split(','))
+            .map(str::trim)
+            .filter(|addr| !addr.is_empty())
+            .map(|addr| {
+                addr.parse()
+                    .context(format!("Invalid bind address {}", addr))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if bind_addrs.is_empty() {
+            return Err(anyhow!("No address to listen on"));
+        }
+        Ok(Self {
+            bind_addrs,
+            self_signed,
+        })
+    }
+}
+
+/// Runs `primary` and `others` until `shutdown` completes, all forwarding
+/// through the primary's leader tracker and pool of TPU connections. Only the
+/// primary serves the HTTP endpoints.
+///
+/// A listener failing shuts down the others.
+async fn run_listeners(
+    primary: BifrostServer,
+    others: Vec<BifrostServer>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    // Fail on a bad certificate before the slow cluster startup
+    for server in std::iter::once(&primary).chain(&others) {
+        server.preflight()?;
+    }
+    let forwarding = primary.start_forwarding().await?;
+
+    let mut listeners = JoinSet::new();
+    let mut handles = Vec::new();
+    for server in std::iter::once(primary).chain(others) {
+        let (service, handle) = server.into_service_with(forwarding.clone());
+        listeners.spawn(service);
+        handles.push(handle);
+    }
+
+    let mut result = Ok(());
+    tokio::select! {
+        _ = shutdown => {}
+        Some(joined) = listeners.join_next() => {
+            result = joined.context("Listener panicked").and_then(|served| served);
+        }
+    }
+
+    for handle in &handles {
+        handle.shutdown();
+    }
+    while let Some(joined) = listeners.join_next().await {
+        let served = joined
+            .context("Listener panicked")
+            .and_then(|served| served);
+        if result.is_ok() {
+            result = served;
+        }
+    }
+
+    forwarding.stop();
+    forwarding.tpu_manager.close_all_graceful().await;
+    log::info!("Closed all TPU connections");
+
+    result
+}
+
+/// Reads a keypair file in the JSON byte array format `solana-keygen` writes.
+fn read_keypair(path: &str) -> Result<Keypair> {
+    let json = std::fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+    let bytes: Vec<u8> =
+        serde_json::from_str(&json).context(format!("Failed to parse keypair {}", path))?;
+    Keypair::try_from(bytes.as_slice()).map_err(|e| anyhow!("Invalid keypair {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], bind_env: Option<&str>) -> Result<Args> {
+        Args::parse(
+            args.iter().map(|arg| arg.to_string()),
+            bind_env.map(str::to_string),
+        )
+    }
+
+    #[test]
+    fn test_parses_bind_addrs() {
+        let addr = |addr: &str| addr.parse::<SocketAddr>().unwrap();
+
+        let args = parse(&[], None).unwrap();
+        assert_eq!(args.bind_addrs, vec![addr(DEFAULT_BIND_ADDRESS)]);
+        assert!(!args.self_signed);
+
+        // The environment is only a fallback for missing --bind arguments
+        let args = parse(&[], Some("0.0.0.0:4433, [::]:4433")).unwrap();
+        assert_eq!(
+            args.bind_addrs,
+            vec![addr("0.0.0.0:4433"), addr("[::]:4433")]
+        );
+        let args = parse(
+            &[
+                "--bind",
+                "127.0.0.1:1",
+                "--bind=127.0.0.1:2,[::1]:3",
+                "--self-signed",
+            ],
+            Some("0.0.0.0:4433"),
+        )
+        .unwrap();
+        assert_eq!(
+            args.bind_addrs,
+            vec![addr("127.0.0.1:1"), addr("127.0.0.1:2"), addr("[::1]:3")]
+        );
+        assert!(args.self_signed);
+
+        assert!(parse(&["--bind"], None).is_err());
+        assert!(parse(&["--bind", "localhost"], None).is_err());
+        assert!(parse(&["--bind=,"], None).is_err());
+        assert!(parse(&["--port", "4433"], None).is_err());
+    }
+}
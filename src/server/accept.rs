@@ -0,0 +1,120 @@
+//! Classification of WebTransport session accept failures.
+
+use quinn::ConnectionError;
+use std::error::Error;
+use std::fmt;
+use web_transport_quinn::SettingsError;
+
+/// Why a WebTransport session could not be accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptFailure {
+    /// The client negotiated an HTTP/3 or WebTransport version the server
+    /// does not support.
+    VersionMismatch,
+    /// The connection dropped or timed out during the handshake.
+    ConnectionLost,
+    /// Any other handshake failure.
+    Other,
+}
+
+impl fmt::Display for AcceptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            AcceptFailure::VersionMismatch => "unsupported WebTransport protocol version",
+            AcceptFailure::ConnectionLost => "connection lost during handshake",
+            AcceptFailure::Other => "handshake failed",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Classifies an error returned while accepting a session.
+///
+/// The source chain is walked for the errors of the QUIC and WebTransport
+/// layers, and the first one that tells decides: a QUIC version mismatch or a
+/// client without WebTransport support is a version mismatch, a connection
+/// closed, reset or timed out by the peer is lost.
+pub fn classify_accept_error(err: &(dyn Error + 'static)) -> AcceptFailure {
+    let mut current = Some(err);
+    while let Some(e) = current {
+        if let Some(failure) = classify(e) {
+            return failure;
+        }
+        current = e.source();
+    }
+    AcceptFailure::Other
+}
+
+/// Classifies `err` alone, or `None` if it doesn't tell.
+fn classify(err: &(dyn Error + 'static)) -> Option<AcceptFailure> {
+    if let Some(e) = err.downcast_ref::<ConnectionError>() {
+        return Some(match e {
+            ConnectionError::VersionMismatch => AcceptFailure::VersionMismatch,
+            ConnectionError::ConnectionClosed(_)
+            | ConnectionError::ApplicationClosed(_)
+            | ConnectionError::Reset
+            | ConnectionError::TimedOut => AcceptFailure::ConnectionLost,
+            _ => AcceptFailure::Other,
+        });
+    }
+    match err.downcast_ref::<SettingsError>() {
+        Some(SettingsError::WebTransportUnsupported) => Some(AcceptFailure::VersionMismatch),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Wrapped {
+        message: &'static str,
+        source: Option<Box<dyn Error + 'static>>,
+    }
+
+    impl fmt::Display for Wrapped {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.message)
+        }
+    }
+
+    impl Error for Wrapped {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.source.as_deref()
+        }
+    }
+
+    fn error(message: &'static str, source: Option<Box<dyn Error + 'static>>) -> Wrapped {
+        Wrapped { message, source }
+    }
+
+    #[test]
+    fn test_classifies_by_error_variant() {
+        // What a draft-only client produces: the failure is only named in the source
+        let err = error(
+            "failed to exchange h3 settings",
+            Some(Box::new(SettingsError::WebTransportUnsupported)),
+        );
+        assert_eq!(classify_accept_error(&err), AcceptFailure::VersionMismatch);
+        assert_eq!(
+            classify_accept_error(&ConnectionError::VersionMismatch),
+            AcceptFailure::VersionMismatch
+        );
+
+        let err = quinn::WriteError::ConnectionLost(ConnectionError::TimedOut);
+        assert_eq!(classify_accept_error(&err), AcceptFailure::ConnectionLost);
+        assert_eq!(
+            classify_accept_error(&ConnectionError::Reset),
+            AcceptFailure::ConnectionLost
+        );
+
+        // Messages alone don't tell
+        let err = error("settings stream closed, version draft-02", None);
+        assert_eq!(classify_accept_error(&err), AcceptFailure::Other);
+        assert_eq!(
+            classify_accept_error(&ConnectionError::LocallyClosed),
+            AcceptFailure::Other
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Bearer-token authentication of WebTransport sessions.
+//!
+//! Clients pass their token in the `token` query parameter of the session URL,
+//! e.g. `https://bifrost.example.com:4433/?token=secret`. Sessions without a
+//! token the configured [`TokenValidator`] accepts are refused with
+//! `401 Unauthorized` before any stream is read.
+
+use std::collections::HashSet;
+use url::Url;
+
+/// Query parameter of the session URL carrying the bearer token.
+pub const TOKEN_QUERY_PARAM: &str = "token";
+
+/// Decides whether a bearer token grants access.
+pub trait TokenValidator: Send + Sync {
+    fn is_valid(&self, token: &str) -> bool;
+}
+
+impl<F> TokenValidator for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn is_valid(&self, token: &str) -> bool {
+        self(token)
+    }
+}
+
+/// Accepts any token from a fixed set.
+#[derive(Debug, Clone, Default)]
+pub struct StaticTokens {
+    tokens: HashSet<String>,
+}
+
+impl StaticTokens {
+    pub fn new<I, T>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            tokens: tokens.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TokenValidator for StaticTokens {
+    fn is_valid(&self, token: &str) -> bool {
+        self.tokens.contains(token)
+    }
+}
+
+/// Returns the bearer token carried by a session URL, if any.
+pub fn request_token(url: &Url) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == TOKEN_QUERY_PARAM)
+        .map(|(_, token)| token.into_owned())
+}
+
+/// Returns `true` if `url` carries a token `validator` accepts.
+pub fn is_authorized(validator: &dyn TokenValidator, url: &Url) -> bool {
+    request_token(url).is_some_and(|token| validator.is_valid(&token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_in_query_is_validated() {
+        let validator = StaticTokens::new(["secret"]);
+        let url = |s: &str| Url::parse(s).unwrap();
+
+        assert!(is_authorized(
+            &validator,
+            &url("https://127.0.0.1:4433/?token=secret")
+        ));
+        assert!(is_authorized(
+            &validator,
+            &url("https://127.0.0.1:4433/batch?foo=1&token=secret")
+        ));
+
+        assert!(!is_authorized(
+            &validator,
+            &url("https://127.0.0.1:4433/?token=guess")
+        ));
+        assert!(!is_authorized(&validator, &url("https://127.0.0.1:4433/")));
+    }
+}
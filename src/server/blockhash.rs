@@ -0,0 +1,180 @@
+//! Optional rejection of transactions whose blockhash expired, which
+//! validators would drop anyway, before they take up TPU bandwidth.
+
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Most blockhashes remembered. Polling adds at most one per refresh
+/// interval, so this only bounds configurations with a very long TTL.
+const MAX_SEEN_BLOCKHASHES: usize = 1024;
+
+/// Settings of the blockhash check, see
+/// [`SessionConfig::blockhash_check`](super::SessionConfig::blockhash_check).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockhashConfig {
+    /// Pause between consecutive `getLatestBlockhash` calls.
+    pub refresh_interval: Duration,
+    /// Time a blockhash is trusted to be valid after it was first polled as
+    /// the latest one. It is remembered as expired for as long again.
+    pub ttl: Duration,
+}
+
+impl Default for BlockhashConfig {
+    /// Polls once per second and trusts a blockhash for a minute, about the
+    /// 150 blocks it is valid for.
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(1),
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Recent blockhashes of the cluster, as seen by the leader tracker's RPC
+/// node.
+///
+/// Transactions are checked against the blockhashes polled as the latest one
+/// alone, without an RPC call of their own. A blockhash is trusted for
+/// [`BlockhashConfig::ttl`] after it was first polled and rejected for as long
+/// again. Blockhashes the poll never saw pass: they may be newer than the last
+/// poll, or older than the cache.
+pub struct BlockhashCache {
+    rpc_client: RpcClient,
+    config: BlockhashConfig,
+    /// Polled blockhashes, with the time each was first seen
+    seen: Mutex<HashMap<Hash, Instant>>,
+}
+
+impl BlockhashCache {
+    pub fn new(rpc_url: &str, config: BlockhashConfig) -> Self {
+        let rpc_client =
+            RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::processed());
+        Self::from_client(rpc_client, config)
+    }
+
+    pub(crate) fn from_client(rpc_client: RpcClient, config: BlockhashConfig) -> Self {
+        Self {
+            rpc_client,
+            config,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Polls for the latest blockhash forever. Failed polls are logged and
+    /// retried on the next tick.
+    pub async fn run(&self) {
+        loop {
+            match self.rpc_client.get_latest_blockhash().await {
+                Ok(blockhash) => self.record(blockhash),
+                Err(e) => warn!("Failed to poll latest blockhash: {}", e),
+            }
+            tokio::time::sleep(self.config.refresh_interval).await;
+        }
+    }
+
+    /// Returns `false` if `blockhash` was polled longer than the TTL ago.
+    ///
+    /// Since the RPC node's view lags or leads the leaders' by a few slots, a
+    /// blockhash at the edge of its validity can be rejected while a leader
+    /// would still accept it, or pass while it already expired.
+    pub fn is_recent(&self, blockhash: &Hash) -> bool {
+        let Some(seen) = self.seen.lock().unwrap().get(blockhash).copied() else {
+            return true;
+        };
+        let recent = seen.elapsed() < self.config.ttl;
+        if !recent {
+            debug!("Blockhash {} expired", blockhash);
+        }
+        recent
+    }
+
+    /// Remembers `blockhash` as polled now, unless it was seen before, and
+    /// forgets blockhashes past twice the TTL.
+    pub(crate) fn record(&self, blockhash: Hash) {
+        let now = Instant::now();
+        let forget_after = self.config.ttl * 2;
+
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, first_seen| now.duration_since(*first_seen) < forget_after);
+        if seen.len() >= MAX_SEEN_BLOCKHASHES
+            && !seen.contains_key(&blockhash)
+            && let Some(oldest) = seen
+                .iter()
+                .min_by_key(|(_, first_seen)| **first_seen)
+                .map(|(hash, _)| *hash)
+        {
+            seen.remove(&oldest);
+        }
+        seen.entry(blockhash).or_insert(now);
+    }
+}
+
+impl fmt::Debug for BlockhashCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockhashCache")
+            .field("rpc_url", &self.rpc_client.url())
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_blockhashes_expire_after_ttl() {
+        // The mock sender answers nothing on "fails", so no RPC call could pass
+        let cache = BlockhashCache::from_client(
+            RpcClient::new_mock("fails".to_string()),
+            BlockhashConfig::default(),
+        );
+        let polled = Hash::new_from_array([1; 32]);
+        let unknown = Hash::new_from_array([2; 32]);
+        assert!(cache.is_recent(&unknown));
+
+        cache.record(polled);
+        assert!(cache.is_recent(&polled));
+
+        // Polling it again doesn't extend its validity
+        tokio::time::advance(Duration::from_secs(30)).await;
+        cache.record(polled);
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(!cache.is_recent(&polled));
+        assert!(cache.is_recent(&unknown));
+
+        // Expired ones are forgotten after another TTL
+        tokio::time::advance(Duration::from_secs(60)).await;
+        cache.record(Hash::new_from_array([3; 32]));
+        assert!(!cache.seen.lock().unwrap().contains_key(&polled));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_seen_blockhashes_are_capped() {
+        let cache = BlockhashCache::from_client(
+            RpcClient::new_mock("fails".to_string()),
+            BlockhashConfig::default(),
+        );
+        let hash = |i: usize| {
+            let mut bytes = [0; 32];
+            bytes[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            Hash::new_from_array(bytes)
+        };
+        for i in 0..=MAX_SEEN_BLOCKHASHES {
+            cache.record(hash(i));
+            tokio::time::advance(Duration::from_millis(1)).await;
+        }
+
+        // The oldest one made room
+        let seen = cache.seen.lock().unwrap();
+        assert_eq!(seen.len(), MAX_SEEN_BLOCKHASHES);
+        assert!(!seen.contains_key(&hash(0)));
+    }
+}
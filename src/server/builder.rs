@@ -0,0 +1,310 @@
+//! Fluent construction of a [`BifrostServer`].
+
+use crate::server::cert::CertificateSource;
+use crate::server::{
+    BifrostServer, BlockhashConfig, ConfirmationConfig, PreconnectConfig, SessionConfig,
+    SessionRateLimit, TokenValidator,
+};
+use crate::tpu_client::{LeaderTrackerConfig, SlotSource, TpuClientConfig};
+use crate::utils::metrics::MetricsPushConfig;
+use anyhow::{Context, Result};
+use solana_sdk::signature::Keypair;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder for [`BifrostServer`], see [`BifrostServer::builder`].
+///
+/// The listen address and the TLS certificate are required. Everything else
+/// falls back to the same defaults as [`BifrostServer::new`].
+///
+/// ```no_run
+/// use bifrost::server::BifrostServer;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let server = BifrostServer::builder()
+///     .addr("[::]:4433".parse()?)
+///     .certificate("certs/cert.pem", "certs/key.pem")
+///     .rpc_url("https://api.mainnet-beta.solana.com")
+///     .ws_url("wss://api.mainnet-beta.solana.com/")
+///     .fanout(2)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct BifrostServerBuilder {
+    addr: Option<SocketAddr>,
+    certificate: Option<CertificateSource>,
+    leader_tracker_config: LeaderTrackerConfig,
+    tpu_config: TpuClientConfig,
+    session_config: SessionConfig,
+    preconnect: PreconnectConfig,
+    shutdown_timeout: Option<Duration>,
+    metrics_push: Option<MetricsPushConfig>,
+    prometheus_addr: Option<SocketAddr>,
+    health_addr: Option<SocketAddr>,
+    result_cache_capacity: Option<usize>,
+    token_validator: Option<Arc<dyn TokenValidator>>,
+}
+
+impl BifrostServerBuilder {
+    /// Socket address to listen on. Required.
+    pub fn addr(mut self, addr: SocketAddr) -> Self {
+        self.addr = Some(addr);
+        self
+    }
+
+    /// Paths to the TLS certificate chain and private key. Required, unless
+    /// given as PEM data with [`certificate_pem`](Self::certificate_pem).
+    pub fn certificate(mut self, cert_path: &str, key_path: &str) -> Self {
+        self.certificate = Some(CertificateSource::Files {
+            cert_path: cert_path.to_string(),
+            key_path: key_path.to_string(),
+        });
+        self
+    }
+
+    /// TLS certificate chain and private key as PEM data, instead of files.
+    pub fn certificate_pem(mut self, cert_pem: &[u8], key_pem: &[u8]) -> Self {
+        self.certificate = Some(CertificateSource::Pem {
+            cert_pem: cert_pem.to_vec(),
+            key_pem: key_pem.to_vec(),
+        });
+        self
+    }
+
+    /// JSON-RPC endpoint of the cluster. Defaults to devnet.
+    pub fn rpc_url(mut self, rpc_url: &str) -> Self {
+        self.leader_tracker_config.rpc_url = rpc_url.to_string();
+        self
+    }
+
+    /// Further JSON-RPC endpoints to rotate over and fail over to, see
+    /// [`LeaderTrackerConfig::fallback_rpc_urls`].
+    pub fn fallback_rpc_urls(mut self, urls: &[&str]) -> Self {
+        self.leader_tracker_config.fallback_rpc_urls =
+            urls.iter().map(|url| url.to_string()).collect();
+        self
+    }
+
+    /// WebSocket endpoint of the cluster. Defaults to devnet.
+    pub fn ws_url(mut self, ws_url: &str) -> Self {
+        self.leader_tracker_config.ws_url = ws_url.to_string();
+        self
+    }
+
+    /// Where slot updates come from, see [`SlotSource`]. Defaults to the
+    /// WebSocket endpoint.
+    pub fn slot_source(mut self, slot_source: SlotSource) -> Self {
+        self.leader_tracker_config.slot_source = slot_source;
+        self
+    }
+
+    /// File the leader schedules are cached in across restarts, see
+    /// [`LeaderTrackerConfig::schedule_cache`].
+    pub fn schedule_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.leader_tracker_config.schedule_cache = Some(path.into());
+        self
+    }
+
+    /// Replaces all cluster endpoints.
+    pub fn leader_tracker_config(mut self, config: LeaderTrackerConfig) -> Self {
+        self.leader_tracker_config = config;
+        self
+    }
+
+    /// Number of distinct upcoming leaders each transaction is sent to, see
+    /// [`TpuClientConfig::fanout`].
+    pub fn fanout(mut self, fanout: usize) -> Self {
+        self.tpu_config.fanout = Some(fanout);
+        self
+    }
+
+    /// Time after which unused leader connections are closed, see
+    /// [`TpuClientConfig::idle_ttl`].
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.tpu_config.idle_ttl = idle_timeout;
+        self
+    }
+
+    /// End-to-end budget of a single transaction, see
+    /// [`SessionConfig::request_timeout`].
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.session_config.request_timeout = request_timeout;
+        self
+    }
+
+    /// Identity validators see connections from, so forwarded transactions
+    /// are prioritized by its stake, see [`TpuClientConfig::identity`].
+    pub fn identity(mut self, identity: Keypair) -> Self {
+        self.tpu_config.identity = Some(Arc::new(identity));
+        self
+    }
+
+    /// Transactions a single session may submit, see
+    /// [`SessionConfig::rate_limit`].
+    pub fn rate_limit(mut self, rate_limit: SessionRateLimit) -> Self {
+        self.session_config.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Sessions a single client IP may have open at once, see
+    /// [`SessionConfig::max_sessions_per_ip`].
+    pub fn max_sessions_per_ip(mut self, max_sessions: usize) -> Self {
+        self.session_config.max_sessions_per_ip = Some(max_sessions);
+        self
+    }
+
+    /// Resolves and logs the leaders of each transaction without sending it,
+    /// see [`TpuClientConfig::dry_run`].
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.tpu_config.dry_run = dry_run;
+        self
+    }
+
+    /// Polls for the status of forwarded transactions before answering, see
+    /// [`SessionConfig::confirmation`].
+    pub fn confirmation(mut self, confirmation: ConfirmationConfig) -> Self {
+        self.session_config.confirmation = Some(confirmation);
+        self
+    }
+
+    /// Rejects transactions whose blockhash expired instead of forwarding
+    /// them, see [`SessionConfig::blockhash_check`].
+    pub fn blockhash_check(mut self, blockhash_check: BlockhashConfig) -> Self {
+        self.session_config.blockhash_check = Some(blockhash_check);
+        self
+    }
+
+    /// Replaces the whole TPU connection manager configuration.
+    pub fn tpu_config(mut self, tpu_config: TpuClientConfig) -> Self {
+        self.tpu_config = tpu_config;
+        self
+    }
+
+    /// Replaces the whole per-session configuration.
+    pub fn session_config(mut self, session_config: SessionConfig) -> Self {
+        self.session_config = session_config;
+        self
+    }
+
+    /// How far ahead and how often connections to upcoming leaders are warmed.
+    pub fn preconnect(mut self, preconnect: PreconnectConfig) -> Self {
+        self.preconnect = preconnect;
+        self
+    }
+
+    /// Time in-flight sessions get to finish on shutdown.
+    pub fn shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(shutdown_timeout);
+        self
+    }
+
+    /// Periodically pushes metrics to a StatsD or InfluxDB UDP endpoint.
+    pub fn metrics_push(mut self, config: MetricsPushConfig) -> Self {
+        self.metrics_push = Some(config);
+        self
+    }
+
+    /// Serves Prometheus metrics on `GET /metrics` and the leader tracker's
+    /// status on `GET /status` at `addr`. Disabled unless set.
+    pub fn prometheus(mut self, addr: SocketAddr) -> Self {
+        self.prometheus_addr = Some(addr);
+        self
+    }
+
+    /// Serves liveness and readiness probes on `GET /healthz` and
+    /// `GET /readyz` at `addr`, see [`BifrostServer::with_health_check`].
+    pub fn health_check(mut self, addr: SocketAddr) -> Self {
+        self.health_addr = Some(addr);
+        self
+    }
+
+    /// Forwarding results kept for queries and
+    /// [`BifrostServer::recent_forwards`], see
+    /// [`BifrostServer::with_result_cache_capacity`].
+    pub fn result_cache_capacity(mut self, capacity: usize) -> Self {
+        self.result_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Requires every session to carry a bearer token `validator` accepts.
+    pub fn token_auth(mut self, validator: impl TokenValidator + 'static) -> Self {
+        self.token_validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Builds the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address or the certificate is missing.
+    pub fn build(self) -> Result<BifrostServer> {
+        let addr = self.addr.context("Listen address is required")?;
+        let certificate = self.certificate.context("TLS certificate is required")?;
+
+        let mut server =
+            BifrostServer::from_certificate(addr, certificate, self.leader_tracker_config)
+                .with_tpu_config(self.tpu_config)
+                .with_session_config(self.session_config)
+                .with_preconnect_config(self.preconnect);
+
+        if let Some(shutdown_timeout) = self.shutdown_timeout {
+            server = server.with_shutdown_timeout(shutdown_timeout);
+        }
+        if let Some(metrics_push) = self.metrics_push {
+            server = server.with_metrics_push(metrics_push);
+        }
+        if let Some(prometheus_addr) = self.prometheus_addr {
+            server = server.with_prometheus(prometheus_addr);
+        }
+        if let Some(health_addr) = self.health_addr {
+            server = server.with_health_check(health_addr);
+        }
+        if let Some(capacity) = self.result_cache_capacity {
+            server = server.with_result_cache_capacity(capacity);
+        }
+        server.token_validator = self.token_validator;
+
+        Ok(server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_requires_addr_and_certificate() {
+        let addr: SocketAddr = "127.0.0.1:4433".parse().unwrap();
+
+        assert!(BifrostServer::builder().addr(addr).build().is_err());
+        assert!(
+            BifrostServer::builder()
+                .certificate("certs/cert.pem", "certs/key.pem")
+                .build()
+                .is_err()
+        );
+
+        let server = BifrostServer::builder()
+            .addr(addr)
+            .certificate("certs/cert.pem", "certs/key.pem")
+            .rpc_url("http://127.0.0.1:8899")
+            .fanout(3)
+            .idle_timeout(Duration::from_secs(60))
+            .dry_run(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(server.addr, addr);
+        assert_eq!(
+            server.leader_tracker_config.rpc_url,
+            "http://127.0.0.1:8899"
+        );
+        assert_eq!(server.tpu_config.fanout, Some(3));
+        assert_eq!(server.tpu_config.idle_ttl, Duration::from_secs(60));
+        assert!(server.tpu_config.dry_run);
+    }
+}
@@ -0,0 +1,168 @@
+//! Per-session tunables for the WebTransport server.
+
+use crate::constants::{MAX_TRANSACTION_SIZE, PACKET_DATA_SIZE};
+use crate::server::{BlockhashConfig, ConfirmationConfig};
+use anyhow::{Result, ensure};
+use std::time::Duration;
+use url::Url;
+
+/// Minimum rate a client must keep up while a stream is being read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadRateFloor {
+    /// Bytes per second the average read rate may not drop below.
+    pub bytes_per_sec: u64,
+    /// Time from the start of the read before the floor is enforced.
+    pub grace: Duration,
+}
+
+/// Transactions a single session may submit, enforced with a token bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionRateLimit {
+    /// Sustained transactions per second.
+    pub per_second: f64,
+    /// Transactions that may be submitted at once after an idle period.
+    pub burst: u32,
+    /// Close the session once it exceeded the limit this many times. `None`
+    /// keeps throttling without closing.
+    pub max_violations: Option<u32>,
+}
+
+impl Default for SessionRateLimit {
+    /// Permissive enough for any honest client: 1000 transactions per second,
+    /// a full batch at once, never closing the session.
+    fn default() -> Self {
+        Self {
+            per_second: 1000.0,
+            burst: 1000,
+            max_violations: None,
+        }
+    }
+}
+
+/// How far ahead and how often the server warms connections to upcoming leaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreconnectConfig {
+    /// Slots ahead of the current one whose leaders are connected to.
+    pub lookahead_slots: u64,
+    /// Pause between consecutive warming passes.
+    pub interval: Duration,
+    /// Upper bound of the random time added to each pause, so instances
+    /// started together don't warm the same leaders in lockstep.
+    pub jitter: Duration,
+}
+
+impl Default for PreconnectConfig {
+    /// The next 10 leader windows of 4 slots, every 2 to 2.5 seconds.
+    fn default() -> Self {
+        Self {
+            lookahead_slots: 10 * 4,
+            interval: Duration::from_secs(2),
+            jitter: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Configuration applied to every WebTransport session.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// End-to-end budget for a single transaction, from the start of the
+    /// stream read until the response has been written.
+    pub request_timeout: Duration,
+    /// Largest serialized transaction accepted, excluding the optional target
+    /// header. Larger ones are answered with `ERROR: transaction_too_large`.
+    ///
+    /// Validators read each transaction from its own QUIC stream but only
+    /// accept what fits in a single packet, [`PACKET_DATA_SIZE`] bytes at the
+    /// IPv6 minimum MTU. A higher limit could never be forwarded, so it can
+    /// only be lowered, e.g. for clusters of custom validators with a smaller
+    /// packet size. Streams are read up to this limit, times the batch size on
+    /// batch sessions.
+    pub max_transaction_size: usize,
+    /// Reject transactions without a fee payer or any signature before
+    /// forwarding. This is a structural check only, signatures are not verified.
+    pub validate_structure: bool,
+    /// Verify transaction signatures before forwarding. Turn off when the
+    /// incoming traffic has already been verified upstream.
+    pub verify_signatures: bool,
+    /// Second Bifrost that receives a copy of every payload, typically a new
+    /// version running in dry-run. Its responses are ignored.
+    pub shadow_endpoint: Option<Url>,
+    /// Abort reads that trickle in slower than this, to defend against
+    /// slow-loris clients. `None` relies on `request_timeout` alone.
+    pub min_read_rate: Option<ReadRateFloor>,
+    /// Throttle sessions submitting transactions faster than this. Excess
+    /// transactions are answered with `ERROR: rate limited`. `None` disables
+    /// the limit.
+    pub rate_limit: Option<SessionRateLimit>,
+    /// Sessions a single client IP may have open at once. Further sessions
+    /// from that IP are closed right after the handshake. `None` disables the
+    /// limit.
+    pub max_sessions_per_ip: Option<usize>,
+    /// Poll the RPC node of the leader tracker for the status of every
+    /// forwarded transaction and answer `OK <signature> <status>` instead of
+    /// `OK <signature> <latency_ms>`. Costs one or more RPC calls per
+    /// transaction, so `None` by default.
+    pub confirmation: Option<ConfirmationConfig>,
+    /// Answer transactions whose recent blockhash expired with
+    /// `ERROR: blockhash expired` instead of forwarding them, checked against
+    /// the blockhashes polled from the RPC node of the leader tracker. A blockhash
+    /// near the end of its validity can be rejected although a leader would
+    /// still have taken it, so `None` by default.
+    pub blockhash_check: Option<BlockhashConfig>,
+}
+
+impl SessionConfig {
+    /// Checks that the configuration can be served.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `max_transaction_size` is zero or over
+    /// [`PACKET_DATA_SIZE`].
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            (1..=PACKET_DATA_SIZE).contains(&self.max_transaction_size),
+            "max_transaction_size must be between 1 and {} bytes, the Solana packet size, got {}",
+            PACKET_DATA_SIZE,
+            self.max_transaction_size
+        );
+        Ok(())
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(5),
+            max_transaction_size: MAX_TRANSACTION_SIZE,
+            validate_structure: true,
+            verify_signatures: true,
+            shadow_endpoint: None,
+            min_read_rate: Some(ReadRateFloor {
+                bytes_per_sec: 1024,
+                grace: Duration::from_secs(1),
+            }),
+            rate_limit: Some(SessionRateLimit::default()),
+            max_sessions_per_ip: Some(16),
+            confirmation: None,
+            blockhash_check: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_transaction_size_is_bounded_by_packet_size() {
+        assert!(SessionConfig::default().validate().is_ok());
+
+        for max_transaction_size in [0, PACKET_DATA_SIZE + 1, 10_000_000] {
+            let config = SessionConfig {
+                max_transaction_size,
+                ..SessionConfig::default()
+            };
+            assert!(config.validate().is_err());
+        }
+    }
+}
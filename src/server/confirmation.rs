@@ -0,0 +1,275 @@
+//! Optional polling of signature statuses, for clients that want to know a
+//! forwarded transaction landed rather than that a leader received it.
+
+use anyhow::anyhow;
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::{TransactionConfirmationStatus, TransactionStatus};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{Instant, timeout, timeout_at};
+
+/// Most signatures a `getSignatureStatuses` call may ask for.
+const MAX_SIGNATURES_PER_CALL: usize = 256;
+
+/// How far a forwarded transaction got, as reported by the RPC node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfirmationStatus {
+    /// The RPC node hasn't seen the transaction in a block before the deadline.
+    Unconfirmed,
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl From<TransactionConfirmationStatus> for ConfirmationStatus {
+    fn from(status: TransactionConfirmationStatus) -> Self {
+        match status {
+            TransactionConfirmationStatus::Processed => ConfirmationStatus::Processed,
+            TransactionConfirmationStatus::Confirmed => ConfirmationStatus::Confirmed,
+            TransactionConfirmationStatus::Finalized => ConfirmationStatus::Finalized,
+        }
+    }
+}
+
+impl fmt::Display for ConfirmationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConfirmationStatus::Unconfirmed => "unconfirmed",
+            ConfirmationStatus::Processed => "processed",
+            ConfirmationStatus::Confirmed => "confirmed",
+            ConfirmationStatus::Finalized => "finalized",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ConfirmationStatus {
+    type Err = anyhow::Error;
+
+    /// Parses the names [`Display`](fmt::Display) writes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unconfirmed" => Ok(ConfirmationStatus::Unconfirmed),
+            "processed" => Ok(ConfirmationStatus::Processed),
+            "confirmed" => Ok(ConfirmationStatus::Confirmed),
+            "finalized" => Ok(ConfirmationStatus::Finalized),
+            _ => Err(anyhow!("Unknown confirmation status: {}", s)),
+        }
+    }
+}
+
+/// Settings of confirmation polling, see [`SessionConfig::confirmation`](super::SessionConfig::confirmation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmationConfig {
+    /// Status at which polling stops early.
+    pub commitment: ConfirmationStatus,
+    /// Time after forwarding a transaction is polled for at most. Polling also
+    /// stops in time to answer within the request budget, so raise
+    /// [`SessionConfig::request_timeout`](super::SessionConfig::request_timeout)
+    /// along with it.
+    pub timeout: Duration,
+    /// Pause between polls. Each poll asks for the status of every pending
+    /// transaction at once.
+    pub poll_interval: Duration,
+}
+
+impl Default for ConfirmationConfig {
+    /// Waits up to 4 seconds for the transaction to be confirmed, polling
+    /// about once per slot.
+    fn default() -> Self {
+        Self {
+            commitment: ConfirmationStatus::Confirmed,
+            timeout: Duration::from_secs(4),
+            poll_interval: Duration::from_millis(400),
+        }
+    }
+}
+
+/// Polls the cluster's RPC node for the status of forwarded transactions.
+///
+/// Transactions being waited for are polled together, with one
+/// `getSignatureStatuses` call per [`ConfirmationConfig::poll_interval`]
+/// however many sessions wait, see [`run`](Self::run).
+pub struct ConfirmationTracker {
+    rpc_client: RpcClient,
+    config: ConfirmationConfig,
+    /// Furthest status seen of each transaction being waited for
+    pending: Mutex<HashMap<Signature, watch::Sender<ConfirmationStatus>>>,
+}
+
+impl ConfirmationTracker {
+    pub fn new(rpc_url: &str, config: ConfirmationConfig) -> Self {
+        Self::from_client(RpcClient::new(rpc_url.to_string()), config)
+    }
+
+    pub(crate) fn from_client(rpc_client: RpcClient, config: ConfirmationConfig) -> Self {
+        Self {
+            rpc_client,
+            config,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Polls for the status of the transactions being waited for forever.
+    /// Failed polls are logged and retried on the next tick.
+    pub async fn run(&self) {
+        loop {
+            self.poll().await;
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Waits for `signature` to reach the configured commitment, until the
+    /// configured timeout passes or `deadline` is reached, whichever comes
+    /// first. Returns the furthest status seen.
+    ///
+    /// Statuses are only polled while [`run`](Self::run) runs. A transaction
+    /// that landed counts, whether or not it executed successfully.
+    pub async fn wait(&self, signature: &Signature, deadline: Instant) -> ConfirmationStatus {
+        let deadline = deadline.min(Instant::now() + self.config.timeout);
+        let mut status = self
+            .pending
+            .lock()
+            .unwrap()
+            .entry(*signature)
+            .or_insert_with(|| watch::channel(ConfirmationStatus::Unconfirmed).0)
+            .subscribe();
+
+        let commitment = self.config.commitment;
+        let _ = timeout_at(deadline, status.wait_for(|status| *status >= commitment)).await;
+        let status = *status.borrow();
+
+        debug!("Transaction {} is {}", signature, status);
+        status
+    }
+
+    /// Polls the status of every transaction being waited for, in as few
+    /// calls as the RPC node takes, and forgets those no longer waited for.
+    /// Returns the number of transactions polled.
+    pub(crate) async fn poll(&self) -> usize {
+        let signatures = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.retain(|_, status| status.receiver_count() > 0);
+            pending.keys().copied().collect::<Vec<_>>()
+        };
+
+        for chunk in signatures.chunks(MAX_SIGNATURES_PER_CALL) {
+            let polled = self.rpc_client.get_signature_statuses(chunk);
+            let response = match timeout(self.config.timeout, polled).await {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => {
+                    warn!(
+                        "Failed to poll status of {} transaction(s): {}",
+                        chunk.len(),
+                        e
+                    );
+                    continue;
+                }
+                Err(_) => {
+                    warn!("Timed out polling status of {} transaction(s)", chunk.len());
+                    continue;
+                }
+            };
+
+            let pending = self.pending.lock().unwrap();
+            for (signature, polled) in chunk.iter().zip(&response.value) {
+                if let (Some(polled), Some(status)) = (polled, pending.get(signature)) {
+                    let polled = confirmation_status(polled);
+                    status.send_if_modified(|status| {
+                        let advanced = polled > *status;
+                        *status = (*status).max(polled);
+                        advanced
+                    });
+                }
+            }
+        }
+        signatures.len()
+    }
+}
+
+impl fmt::Debug for ConfirmationTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfirmationTracker")
+            .field("rpc_url", &self.rpc_client.url())
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Status of a transaction the RPC node knows about. Nodes that don't report
+/// a confirmation status are assumed to have processed it.
+fn confirmation_status(status: &TransactionStatus) -> ConfirmationStatus {
+    status
+        .confirmation_status
+        .clone()
+        .map_or(ConfirmationStatus::Processed, ConfirmationStatus::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[tokio::test]
+    async fn test_polls_pending_transactions_together() {
+        // The mock sender reports every transaction as finalized on "succeeds"
+        let tracker = ConfirmationTracker::from_client(
+            RpcClient::new_mock("succeeds".to_string()),
+            ConfirmationConfig::default(),
+        );
+        let first = Keypair::new().sign_message(b"first");
+        let second = Keypair::new().sign_message(b"second");
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let (first_status, second_status, polled) = tokio::join!(
+            tracker.wait(&first, deadline),
+            tracker.wait(&second, deadline),
+            async {
+                // Once both waits are pending
+                tokio::task::yield_now().await;
+                tracker.poll().await
+            }
+        );
+        assert_eq!(polled, 2);
+        assert_eq!(first_status, ConfirmationStatus::Finalized);
+        assert_eq!(second_status, ConfirmationStatus::Finalized);
+
+        // Nobody waits for them anymore
+        assert_eq!(tracker.poll().await, 0);
+    }
+
+    #[test]
+    fn test_confirmation_status_orders_by_commitment() {
+        let status = |confirmation_status| TransactionStatus {
+            slot: 1,
+            confirmations: None,
+            status: Ok(()),
+            err: None,
+            confirmation_status,
+        };
+
+        assert_eq!(
+            confirmation_status(&status(None)),
+            ConfirmationStatus::Processed
+        );
+        assert_eq!(
+            confirmation_status(&status(Some(TransactionConfirmationStatus::Finalized))),
+            ConfirmationStatus::Finalized
+        );
+
+        assert!(ConfirmationStatus::Unconfirmed < ConfirmationStatus::Processed);
+        assert!(ConfirmationStatus::Confirmed < ConfirmationStatus::Finalized);
+        assert_eq!(ConfirmationStatus::Confirmed.to_string(), "confirmed");
+        assert_eq!(
+            "processed".parse::<ConfirmationStatus>().unwrap(),
+            ConfirmationStatus::Processed
+        );
+        assert!("landed".parse::<ConfirmationStatus>().is_err());
+    }
+}
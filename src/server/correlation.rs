@@ -0,0 +1,144 @@
+//! Identifiers that tie together every log line and span of one submission.
+//!
+//! Each accepted session gets a [`SessionId`], and each stream or datagram in
+//! it a sequence number. While a session is handled, [`correlation_id`]
+//! returns `<session>/<stream>` for whatever the session task logs, including
+//! the TPU connection manager's lines for the transaction being forwarded, so
+//! a log formatter can tag interleaved lines with the submission they belong
+//! to.
+
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+
+tokio::task_local! {
+    static CORRELATION: Correlation;
+}
+
+/// Random identifier of an accepted session, formatted as a version 4 UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionId(u128);
+
+impl SessionId {
+    pub fn new() -> Self {
+        let bits = rand::random::<u128>();
+        // Version 4 in the high nibble of byte 6, variant 1 in the top bits of byte 8
+        let bits = (bits & !(0xf << 76)) | (0x4 << 76);
+        let bits = (bits & !(0x3 << 62)) | (0x2 << 62);
+        Self(bits)
+    }
+}
+
+impl Default for SessionId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            bits >> 96,
+            (bits >> 80) & 0xffff,
+            (bits >> 64) & 0xffff,
+            (bits >> 48) & 0xffff,
+            bits & 0xffff_ffff_ffff
+        )
+    }
+}
+
+/// Session being handled by the current task, and the stream within it.
+struct Correlation {
+    session: SessionId,
+    stream: Cell<Option<u64>>,
+}
+
+/// Runs `future` with everything it logs attributed to `session`.
+pub(crate) async fn in_session<F: Future>(session: SessionId, future: F) -> F::Output {
+    let correlation = Correlation {
+        session,
+        stream: Cell::new(None),
+    };
+    CORRELATION.scope(correlation, future).await
+}
+
+/// Attributes what the current session logs from now on to its `stream`-th
+/// stream or datagram. Streams of a session are handled one at a time.
+pub(crate) fn enter_stream(stream: u64) {
+    let _ = CORRELATION.try_with(|correlation| correlation.stream.set(Some(stream)));
+}
+
+/// Runs `future`, e.g. on a task of its own, with everything it logs
+/// attributed to the current session's `stream`-th stream or datagram,
+/// whatever the session moves on to meanwhile. Outside of a session,
+/// `future` runs as is.
+pub(crate) fn in_stream<F: Future>(stream: u64, future: F) -> impl Future<Output = F::Output> {
+    let session = CORRELATION.try_with(|correlation| correlation.session).ok();
+    async move {
+        match session {
+            Some(session) => {
+                let correlation = Correlation {
+                    session,
+                    stream: Cell::new(Some(stream)),
+                };
+                CORRELATION.scope(correlation, future).await
+            }
+            None => future.await,
+        }
+    }
+}
+
+/// Correlation ID of the submission the current task is handling, as
+/// `<session>/<stream>`, or just `<session>` between streams. `None` outside
+/// of a session.
+pub fn correlation_id() -> Option<String> {
+    CORRELATION
+        .try_with(|correlation| match correlation.stream.get() {
+            Some(stream) => format!("{}/{}", correlation.session, stream),
+            None => correlation.session.to_string(),
+        })
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_correlation_id_follows_session_and_stream() {
+        let session = SessionId::new();
+        let formatted = session.to_string();
+        let groups: Vec<&str> = formatted.split('-').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('4'));
+        assert!(matches!(
+            groups[3].chars().next(),
+            Some('8' | '9' | 'a' | 'b')
+        ));
+        assert_ne!(SessionId::new(), session);
+
+        assert_eq!(correlation_id(), None);
+        in_session(session, async {
+            assert_eq!(correlation_id(), Some(formatted.clone()));
+            enter_stream(1);
+            assert_eq!(correlation_id(), Some(format!("{}/1", formatted)));
+            tokio::task::yield_now().await;
+            enter_stream(2);
+            assert_eq!(correlation_id(), Some(format!("{}/2", formatted)));
+
+            // A stream handled on another task keeps its own ID
+            let other = tokio::spawn(in_stream(3, async { correlation_id() }));
+            assert_eq!(other.await.unwrap(), Some(format!("{}/3", formatted)));
+            assert_eq!(correlation_id(), Some(format!("{}/2", formatted)));
+        })
+        .await;
+        assert_eq!(correlation_id(), None);
+        assert_eq!(in_stream(1, async { correlation_id() }).await, None);
+    }
+}
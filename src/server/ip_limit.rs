@@ -0,0 +1,84 @@
+//! Cap on concurrent sessions per client IP.
+
+use dashmap::DashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Counts the active sessions of each remote IP.
+#[derive(Debug)]
+pub struct IpSessionLimiter {
+    max_per_ip: usize,
+    active: Arc<DashMap<IpAddr, usize>>,
+}
+
+impl IpSessionLimiter {
+    pub fn new(max_per_ip: usize) -> Self {
+        Self {
+            max_per_ip,
+            active: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Registers a session from `ip`, or returns `None` if it already has the
+    /// maximum number of active sessions. The session counts as active until
+    /// the returned permit is dropped.
+    pub fn try_acquire(&self, ip: IpAddr) -> Option<SessionPermit> {
+        let mut count = self.active.entry(ip).or_default();
+        if *count >= self.max_per_ip {
+            return None;
+        }
+        *count += 1;
+
+        Some(SessionPermit {
+            ip,
+            active: self.active.clone(),
+        })
+    }
+
+    /// Number of active sessions from `ip`.
+    pub fn active(&self, ip: IpAddr) -> usize {
+        self.active.get(&ip).map_or(0, |count| *count)
+    }
+}
+
+/// An active session, released when dropped.
+#[derive(Debug)]
+pub struct SessionPermit {
+    ip: IpAddr,
+    active: Arc<DashMap<IpAddr, usize>>,
+}
+
+impl Drop for SessionPermit {
+    fn drop(&mut self) {
+        // Remove IPs without sessions so the map only holds connected clients
+        self.active.remove_if_mut(&self.ip, |_, count| {
+            *count -= 1;
+            *count == 0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_limited_per_ip() {
+        let limiter = IpSessionLimiter::new(2);
+        let client: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let first = limiter.try_acquire(client).unwrap();
+        let _second = limiter.try_acquire(client).unwrap();
+        assert!(limiter.try_acquire(client).is_none());
+        assert!(limiter.try_acquire(other).is_some());
+
+        // A finished session frees its place
+        drop(first);
+        assert_eq!(limiter.active(client), 1);
+        assert!(limiter.try_acquire(client).is_some());
+
+        assert_eq!(limiter.active(other), 0);
+        assert!(limiter.active.get(&other).is_none());
+    }
+}
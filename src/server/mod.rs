@@ -1,23 +1,72 @@
 //! WebTransport server implementation for Bifrost.
 
+mod accept;
+mod auth;
+mod blockhash;
+mod builder;
 mod cert;
+mod config;
+mod confirmation;
+mod correlation;
+mod ip_limit;
+mod rate_limit;
+mod results;
+mod sanitize;
+mod service;
 mod session;
+mod shadow;
 
-pub use cert::load_certificates;
-pub use session::handle_session;
+pub use accept::{AcceptFailure, classify_accept_error};
+pub use auth::{StaticTokens, TOKEN_QUERY_PARAM, TokenValidator, is_authorized, request_token};
+pub use blockhash::{BlockhashCache, BlockhashConfig};
+pub use builder::BifrostServerBuilder;
+pub use cert::{CertificateReloader, load_certificates, load_certificates_from_pem};
+pub use config::{PreconnectConfig, ReadRateFloor, SessionConfig, SessionRateLimit};
+pub use confirmation::{ConfirmationConfig, ConfirmationStatus, ConfirmationTracker};
+pub use correlation::{SessionId, correlation_id};
+pub use ip_limit::{IpSessionLimiter, SessionPermit};
+pub use rate_limit::{Admission, SessionLimiter};
+pub use results::{DEFAULT_RESULT_CACHE_CAPACITY, RecentForward, ResultCache, TransactionResult};
+pub use service::{BifrostService, ForwardingStack, ShutdownHandle};
+pub use session::{
+    Outcome, Phase, RejectReason, SessionServices, deserialize_transaction, handle_session,
+    transaction_id,
+};
+pub use shadow::ShadowForwarder;
 
-use crate::tpu_client::{LeaderTracker, TpuConnectionManager};
+use crate::cert::{certificate_hash, generate_self_signed_pem, self_signed_names};
+use crate::server::cert::{CertificateResolver, CertificateSource};
+use crate::tpu_client::{LeaderTrackerConfig, TpuClientConfig};
+use crate::utils::metrics::{self, HttpResponse, HttpRoute, MetricsPushConfig};
 use anyhow::{Context, Result};
-use log::{debug, error, info};
+use log::{error, info, warn};
+use solana_sdk::signature::Signature;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Time in-flight sessions get to finish once shutdown is requested.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Application error code sessions over the per-IP limit are closed with.
+const TOO_MANY_SESSIONS_CODE: u32 = 429;
 
 /// WebTransport server that accepts connections and forwards transactions to TPU.
 pub struct BifrostServer {
     addr: SocketAddr,
-    cert_path: String,
-    key_path: String,
+    certificate: CertificateSource,
+    certificates: Arc<CertificateResolver>,
+    leader_tracker_config: LeaderTrackerConfig,
+    tpu_config: TpuClientConfig,
+    session_config: SessionConfig,
+    results: Arc<ResultCache>,
+    metrics_push: Option<MetricsPushConfig>,
+    prometheus_addr: Option<SocketAddr>,
+    health_addr: Option<SocketAddr>,
+    shutdown_timeout: Duration,
+    token_validator: Option<Arc<dyn TokenValidator>>,
+    preconnect: PreconnectConfig,
 }
 
 impl BifrostServer {
@@ -28,117 +77,519 @@ impl BifrostServer {
     /// * `addr` - Socket address to bind the server
     /// * `cert_path` - Path to TLS certificate file
     /// * `key_path` - Path to TLS private key file
-    pub fn new(addr: SocketAddr, cert_path: &str, key_path: &str) -> Self {
-        Self {
-            addr,
+    /// * `leader_tracker_config` - Cluster RPC and WebSocket endpoints
+    pub fn new(
+        addr: SocketAddr,
+        cert_path: &str,
+        key_path: &str,
+        leader_tracker_config: LeaderTrackerConfig,
+    ) -> Self {
+        let certificate = CertificateSource::Files {
             cert_path: cert_path.to_string(),
             key_path: key_path.to_string(),
+        };
+        Self::from_certificate(addr, certificate, leader_tracker_config)
+    }
+
+    /// Creates a new Bifrost server instance whose TLS certificate and private
+    /// key are given as PEM data rather than read from disk.
+    pub fn from_pem(
+        addr: SocketAddr,
+        cert_pem: &[u8],
+        key_pem: &[u8],
+        leader_tracker_config: LeaderTrackerConfig,
+    ) -> Self {
+        let certificate = CertificateSource::Pem {
+            cert_pem: cert_pem.to_vec(),
+            key_pem: key_pem.to_vec(),
+        };
+        Self::from_certificate(addr, certificate, leader_tracker_config)
+    }
+
+    /// Replaces the TLS certificate with a freshly generated self-signed one,
+    /// see [`generate_self_signed`](crate::cert::generate_self_signed), so no
+    /// certificate files are needed for local development. It covers
+    /// `localhost`, the loopback addresses and the listen address, if it is a
+    /// specific one.
+    ///
+    /// Its SHA-256 fingerprint is logged, for pinning it in a browser with
+    /// `serverCertificateHashes`. Every call generates a new certificate; to
+    /// share one between listeners, generate it with
+    /// [`generate_self_signed_pem`](crate::cert::generate_self_signed_pem) and
+    /// pass it to [`from_pem`](Self::from_pem).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the certificate cannot be generated.
+    pub fn with_self_signed(mut self) -> Result<Self> {
+        let names = self_signed_names(&[self.addr]);
+        let (cert_pem, key_pem) = generate_self_signed_pem(&names)?;
+        let (cert_chain, _) = load_certificates_from_pem(cert_pem.as_bytes(), key_pem.as_bytes())?;
+        info!(
+            "Generated self-signed certificate for {}, SHA-256 fingerprint {}",
+            names.join(", "),
+            certificate_hash(&cert_chain[0])
+        );
+
+        self.certificate = CertificateSource::Pem {
+            cert_pem: cert_pem.into_bytes(),
+            key_pem: key_pem.into_bytes(),
+        };
+        Ok(self)
+    }
+
+    pub(crate) fn from_certificate(
+        addr: SocketAddr,
+        certificate: CertificateSource,
+        leader_tracker_config: LeaderTrackerConfig,
+    ) -> Self {
+        Self {
+            addr,
+            certificate,
+            certificates: Arc::new(CertificateResolver::default()),
+            leader_tracker_config,
+            tpu_config: TpuClientConfig::default(),
+            session_config: SessionConfig::default(),
+            results: Arc::new(ResultCache::default()),
+            metrics_push: None,
+            prometheus_addr: None,
+            health_addr: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            token_validator: None,
+            preconnect: PreconnectConfig::default(),
         }
     }
 
+    /// Returns a builder, for setting tunables without a long argument list.
+    pub fn builder() -> BifrostServerBuilder {
+        BifrostServerBuilder::default()
+    }
+
+    /// Periodically pushes metrics to a StatsD or InfluxDB UDP endpoint.
+    pub fn with_metrics_push(mut self, config: MetricsPushConfig) -> Self {
+        self.metrics_push = Some(config);
+        self
+    }
+
+    /// Serves Prometheus metrics on `GET /metrics` and the leader tracker's
+    /// status as JSON on `GET /status` at `addr`, a separate port from the
+    /// WebTransport listener.
+    pub fn with_prometheus(mut self, addr: SocketAddr) -> Self {
+        self.prometheus_addr = Some(addr);
+        self
+    }
+
+    /// Serves a liveness probe on `GET /healthz`, always 200 while the
+    /// process runs, and a readiness probe on `GET /readyz` at `addr`. The
+    /// latter answers 200 only once leaders can be resolved, see
+    /// [`TrackerStatus::is_ready`](crate::tpu_client::TrackerStatus::is_ready),
+    /// and 503 with the tracker status otherwise.
+    ///
+    /// May be the same address as [`with_prometheus`](Self::with_prometheus),
+    /// which serves the probes too.
+    pub fn with_health_check(mut self, addr: SocketAddr) -> Self {
+        self.health_addr = Some(addr);
+        self
+    }
+
+    /// Returns the forwarding result for `signature`, if it is still cached.
+    pub fn query_result(&self, signature: &Signature) -> Option<TransactionResult> {
+        self.results.get(signature)
+    }
+
+    /// Returns a handle to the result cache that stays usable after [`run`](Self::run)
+    /// takes ownership of the server.
+    pub fn results(&self) -> Arc<ResultCache> {
+        self.results.clone()
+    }
+
+    /// Returns up to `limit` of the most recent forwards, newest first, with
+    /// the leaders each went to and how each delivery went. Meant for
+    /// debugging; keep a [`results`](Self::results) handle to call
+    /// [`ResultCache::recent_forwards`] while the server runs.
+    pub fn recent_forwards(&self, limit: usize) -> Vec<RecentForward> {
+        self.results.recent_forwards(limit)
+    }
+
+    /// Overrides how many forwarding results are kept, and with them how far
+    /// back [`recent_forwards`](Self::recent_forwards) reaches. Defaults to
+    /// [`DEFAULT_RESULT_CACHE_CAPACITY`].
+    pub fn with_result_cache_capacity(mut self, capacity: usize) -> Self {
+        self.results = Arc::new(ResultCache::new(capacity));
+        self
+    }
+
+    /// Returns a handle for reloading the TLS certificate while the server is
+    /// running, e.g. after the certificate files were renewed. On Unix, the
+    /// server also reloads it on `SIGHUP`.
+    pub fn certificate_reloader(&self) -> CertificateReloader {
+        CertificateReloader::new(self.certificate.clone(), self.certificates.clone())
+    }
+
+    /// Overrides the per-session configuration.
+    pub fn with_session_config(mut self, session_config: SessionConfig) -> Self {
+        self.session_config = session_config;
+        self
+    }
+
+    /// Overrides the TPU connection manager configuration.
+    pub fn with_tpu_config(mut self, tpu_config: TpuClientConfig) -> Self {
+        self.tpu_config = tpu_config;
+        self
+    }
+
+    /// Requires every session to carry a bearer token `validator` accepts, see
+    /// [`TOKEN_QUERY_PARAM`]. Sessions without one are refused.
+    pub fn with_token_auth(mut self, validator: impl TokenValidator + 'static) -> Self {
+        self.token_validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Overrides how far ahead and how often connections to upcoming leaders
+    /// are warmed.
+    pub fn with_preconnect_config(mut self, preconnect: PreconnectConfig) -> Self {
+        self.preconnect = preconnect;
+        self
+    }
+
+    /// Overrides how long [`run_until`](Self::run_until) waits for in-flight
+    /// sessions before aborting them.
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
     /// Starts the WebTransport server and begins accepting connections.
     ///
     /// # Errors
     ///
     /// Returns an error if:
+    /// - The session configuration is invalid
     /// - Certificate loading fails
     /// - TPU manager initialization fails
     /// - Server binding fails
     pub async fn run(self) -> Result<()> {
-        info!("Starting Bifrost on {}", self.addr);
+        self.run_until(std::future::pending()).await
+    }
+
+    /// Like [`run`](Self::run), but shuts down once `shutdown` completes.
+    ///
+    /// On shutdown, new sessions are no longer accepted and in-flight sessions
+    /// get up to the shutdown timeout (see
+    /// [`with_shutdown_timeout`](Self::with_shutdown_timeout)) to finish before
+    /// they are aborted. All TPU connections are then closed.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`run`](Self::run).
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        self.preflight()?;
+        let forwarding = self.start_forwarding().await?;
+
+        // The connections are closed whether serving failed or not
+        let served = self.serve(forwarding.clone(), shutdown).await;
+
+        forwarding.stop();
+        forwarding.tpu_manager.close_all_graceful().await;
+        info!("Closed all TPU connections");
 
-        let (cert_chain, private_key) = load_certificates(&self.cert_path, &self.key_path)
+        served
+    }
+
+    /// Validates the session configuration and loads the certificate, so a
+    /// misconfigured server fails before [`start_forwarding`](Self::start_forwarding)
+    /// contacts the cluster. [`run_until`](Self::run_until) and
+    /// [`into_service_with`](Self::into_service_with) call it themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session configuration is invalid or the
+    /// certificate can't be loaded.
+    pub fn preflight(&self) -> Result<()> {
+        self.session_config
+            .validate()
+            .context("Invalid session config")?;
+        self.certificate_reloader()
+            .reload_certificates()
             .context("Failed to load certificates")?;
+        Ok(())
+    }
 
-        // Initialize the LeaderTracker - NOW RETURNS RESULT
-        let leader_tracker = Arc::new(
-            LeaderTracker::new()
-                .await
-                .context("Failed to initialize LeaderTracker")?,
-        );
+    /// Connects to the cluster with this server's leader tracker, TPU,
+    /// preconnect and session configuration, see [`ForwardingStack::start`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ForwardingStack::start`].
+    pub async fn start_forwarding(&self) -> Result<ForwardingStack> {
+        ForwardingStack::start(
+            self.leader_tracker_config.clone(),
+            self.tpu_config.clone(),
+            self.preconnect,
+            &self.session_config,
+        )
+        .await
+    }
 
-        // Spawn the slot_updates listener as a background task
-        let leader_tracker_clone = leader_tracker.clone();
-        tokio::spawn(async move {
-            if let Err(e) = LeaderTracker::run(leader_tracker_clone).await {
-                error!("Slot updates listener failed: {}", e);
-                // TODO: implement reconnection logic here
-            }
-        });
-
-        // Spawn task to update leader sockets list every minute
-        let leader_tracker_clone = leader_tracker.clone();
-        tokio::spawn(async move {
-            loop {
-                match LeaderTracker::update_leader_sockets(leader_tracker_clone.clone()).await {
-                    Ok(_) => debug!("Leader sockets updated successfully"),
-                    Err(e) => error!("Failed to update leader sockets: {}", e),
-                }
-                tokio::time::sleep(Duration::from_secs(60)).await;
-            }
-        });
+    /// Turns the server into a future a host application can spawn next to
+    /// its own services, and a handle to shut it down with. The future sets
+    /// up its own [`ForwardingStack`], runs like [`run_until`](Self::run_until)
+    /// and completes once the shutdown finished.
+    ///
+    /// ```no_run
+    /// use bifrost::server::BifrostServer;
+    ///
+    /// # async fn example(server: BifrostServer) -> anyhow::Result<()> {
+    /// let (service, shutdown) = server.into_service();
+    /// let bifrost = tokio::spawn(service);
+    ///
+    /// // ... run the host application, then on its shutdown signal:
+    /// shutdown.shutdown();
+    /// bifrost.await??;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_service(self) -> (BifrostService, ShutdownHandle) {
+        let handle = ShutdownHandle::new();
+        let shutdown = handle.requested();
+        (Box::pin(self.run_until(shutdown)), handle)
+    }
 
-        let tpu_manager = Arc::new(
-            TpuConnectionManager::new(leader_tracker.clone())
-                .context("Failed to create TPU manager")?,
-        );
+    /// Like [`into_service`](Self::into_service), but forwards through an
+    /// existing `forwarding` stack, so several listeners can share one leader
+    /// tracker and one pool of TPU connections. The shadow, confirmation
+    /// polling, blockhash check and per-IP session limit are the stack's too,
+    /// set up from the session configuration it was started with.
+    ///
+    /// The connections stay open when the service shuts down; once every
+    /// listener is done, call [`ForwardingStack::stop`] and close them with
+    /// [`TpuConnectionManager::close_all_graceful`](crate::tpu_client::TpuConnectionManager::close_all_graceful).
+    pub fn into_service_with(
+        self,
+        forwarding: ForwardingStack,
+    ) -> (BifrostService, ShutdownHandle) {
+        let handle = ShutdownHandle::new();
+        let shutdown = handle.requested();
+        let service = async move {
+            self.preflight()?;
+            self.serve(forwarding, shutdown).await
+        };
+        (Box::pin(service), handle)
+    }
 
-        // Spawn task to proactively connect to future leaders
-        let manager_clone = tpu_manager.clone();
-        let leader_tracker_clone = leader_tracker.clone();
-        tokio::spawn(async move {
-            loop {
-                debug!("Pre-connecting to future leaders");
-                let leaders = leader_tracker_clone.get_future_leaders(0, 10 * 4).await;
-
-                for (leader_identity, leader_socket, _) in leaders {
-                    let mc = manager_clone.clone();
-                    tokio::spawn(async move {
-                        match mc.get_or_create_connection(&leader_socket).await {
-                            Ok(_) => debug!(
-                                "Pre-connected to leader {} at {}",
-                                leader_identity, leader_socket
-                            ),
-                            Err(e) => debug!("Failed to pre-connect to {}: {}", leader_socket, e),
+    /// Accepts sessions and forwards their transactions through `forwarding`
+    /// until `shutdown` completes, then drains in-flight sessions. The tasks
+    /// serving this listener's HTTP endpoints and metrics pushes end with it.
+    async fn serve(
+        self,
+        forwarding: ForwardingStack,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        info!("Starting Bifrost on {}", self.addr);
+
+        let ForwardingStack {
+            leader_tracker,
+            tpu_manager,
+            ..
+        } = &forwarding;
+        // Aborted when dropped, on return
+        let mut tasks = JoinSet::new();
+
+        if let Some(push_config) = self.metrics_push.clone() {
+            info!(
+                "Pushing {:?} metrics to {} every {:?}",
+                push_config.format, push_config.target, push_config.interval
+            );
+            let manager_clone = tpu_manager.clone();
+            tasks.spawn(async move {
+                let sample = || {
+                    let manager = manager_clone.clone();
+                    async move { manager.metrics_snapshot().await }
+                };
+                if let Err(e) = metrics::run_pusher(push_config, sample).await {
+                    error!("Metrics pusher failed: {}", e);
+                }
+            });
+        }
+
+        let mut http_addrs: Vec<SocketAddr> = self.prometheus_addr.into_iter().collect();
+        if let Some(health_addr) = self.health_addr
+            && !http_addrs.contains(&health_addr)
+        {
+            http_addrs.push(health_addr);
+        }
+        for http_addr in http_addrs {
+            let manager_clone = tpu_manager.clone();
+            let leader_tracker_clone = leader_tracker.clone();
+            tasks.spawn(async move {
+                let render = move |route| {
+                    let manager = manager_clone.clone();
+                    let leader_tracker = leader_tracker_clone.clone();
+                    async move {
+                        match route {
+                            HttpRoute::Metrics => manager.prometheus_metrics().await.into(),
+                            HttpRoute::Status => {
+                                let status = leader_tracker.status().await;
+                                serde_json::to_string(&status).unwrap_or_default().into()
+                            }
+                            HttpRoute::Liveness => "ok\n".to_string().into(),
+                            HttpRoute::Readiness => {
+                                let status = leader_tracker.status().await;
+                                HttpResponse {
+                                    available: status.is_ready(),
+                                    body: serde_json::to_string(&status).unwrap_or_default(),
+                                }
+                            }
                         }
-                    });
+                    }
+                };
+                if let Err(e) = metrics::serve_http(http_addr, render).await {
+                    error!("HTTP endpoint failed: {:#}", e);
                 }
+            });
+        }
 
-                tokio::time::sleep(Duration::from_secs(2)).await;
-            }
-        });
+        // Picked up on SIGHUP
+        forwarding.reload_on_sighup(self.certificate_reloader());
+        let services = forwarding.services.clone();
 
-        let mut server = web_transport_quinn::ServerBuilder::new()
-            .with_addr(self.addr)
-            .with_certificate(cert_chain, private_key)?;
+        let mut server = bind(self.addr, self.certificates.clone())?;
 
         info!("Listening for WebTransport connections on {}", self.addr);
 
+        let mut sessions = JoinSet::new();
+        let ip_limiter = forwarding.ip_limiter.clone();
+        tokio::pin!(shutdown);
+
         // Accept and handle incoming connections
-        while let Some(request) = server.accept().await {
-            info!("Received connection request: {}", request.url());
+        loop {
+            let request = tokio::select! {
+                _ = &mut shutdown => {
+                    info!("Shutdown requested, no longer accepting sessions");
+                    break;
+                }
+                // Reap finished sessions so the set only holds live ones
+                Some(_) = sessions.join_next(), if !sessions.is_empty() => continue,
+                request = server.accept() => match request {
+                    Some(request) => request,
+                    None => break,
+                },
+            };
+            info!("Received connection request for {}", request.url().path());
 
             let tpu = tpu_manager.clone();
-            tokio::spawn(async move {
+            let session_config = self.session_config.clone();
+            let results = self.results.clone();
+            let services = services.clone();
+            let token_validator = self.token_validator.clone();
+            let ip_limiter = ip_limiter.clone();
+            sessions.spawn(async move {
+                if let Some(validator) = token_validator
+                    && !is_authorized(validator.as_ref(), request.url())
+                {
+                    warn!("Refusing unauthenticated session for {}", request.url().path());
+                    if let Err(e) = request.close(http::StatusCode::UNAUTHORIZED).await {
+                        error!("Failed to refuse session: {}", e);
+                    }
+                    return;
+                }
+
                 match request.ok().await {
                     Ok(session) => {
-                        info!("Session accepted from {}", session.remote_address());
-                        if let Err(e) = handle_session(session, tpu).await {
-                            error!("Session error: {}", e);
+                        let ip = session.remote_address().ip();
+                        // Held until the session ends
+                        let _permit = match ip_limiter.map(|limiter| limiter.try_acquire(ip)) {
+                            Some(None) => {
+                                warn!("Refusing session from {}: too many active sessions", ip);
+                                session.close(TOO_MANY_SESSIONS_CODE, b"too many sessions");
+                                return;
+                            }
+                            permit => permit.flatten(),
+                        };
+
+                        let session_id = SessionId::new();
+                        info!(
+                            "Session {} accepted from {}",
+                            session_id,
+                            session.remote_address()
+                        );
+                        let handling = handle_session(
+                            session,
+                            session_id,
+                            tpu,
+                            session_config,
+                            results,
+                            services,
+                        );
+                        if let Err(e) = handling.await {
+                            error!("Session {} error: {}", session_id, e);
                         }
                     }
-                    Err(e) => {
-                        error!("Failed to accept session: {}", e);
-                    }
+                    Err(e) => match classify_accept_error(&e) {
+                        AcceptFailure::VersionMismatch => error!(
+                            "Failed to accept session: client uses an unsupported WebTransport version: {}",
+                            e
+                        ),
+                        failure => error!("Failed to accept session ({}): {}", failure, e),
+                    },
                 }
             });
         }
 
         info!("Server shutting down");
+
+        if !drain_sessions(&mut sessions, self.shutdown_timeout).await {
+            warn!(
+                "Aborted {} session(s) still running after {:?}",
+                sessions.len(),
+                self.shutdown_timeout
+            );
+            sessions.shutdown().await;
+        }
+
         Ok(())
     }
 }
 
+/// Waits for every task in `sessions` to finish, for at most `timeout`.
+/// Returns `false` if some were still running when it expired.
+async fn drain_sessions(sessions: &mut JoinSet<()>, timeout: Duration) -> bool {
+    if !sessions.is_empty() {
+        info!("Waiting for {} in-flight session(s)", sessions.len());
+    }
+
+    let drained = async { while sessions.join_next().await.is_some() {} };
+    tokio::time::timeout(timeout, drained).await.is_ok()
+}
+
+/// Binds the WebTransport server to `addr`, presenting whatever certificate
+/// `certificates` holds at the time of each handshake.
+///
+/// `web_transport_quinn::ServerBuilder::with_certificate` only takes a fixed
+/// certificate chain, so the TLS and QUIC configuration it would create is
+/// built here instead, with a resolver that can be swapped at runtime.
+fn bind(
+    addr: SocketAddr,
+    certificates: Arc<CertificateResolver>,
+) -> Result<web_transport_quinn::Server> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut tls = rustls::ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .context("Failed to configure TLS")?
+        .with_no_client_auth()
+        .with_cert_resolver(certificates);
+    // WebTransport runs over HTTP/3
+    tls.alpn_protocols = vec![b"h3".to_vec()];
+
+    let crypto = quinn::crypto::rustls::QuicServerConfig::try_from(tls)
+        .context("Failed to configure QUIC")?;
+    let config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    let endpoint =
+        quinn::Endpoint::server(config, addr).context(format!("Failed to bind to {}", addr))?;
+
+    Ok(web_transport_quinn::Server::new(endpoint))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,17 +597,70 @@ mod tests {
     #[test]
     fn test_server_creation() {
         let addr = "127.0.0.1:4433".parse().unwrap();
-        let server = BifrostServer::new(addr, "certs/cert.pem", "certs/key.pem");
+        let server = BifrostServer::new(
+            addr,
+            "certs/cert.pem",
+            "certs/key.pem",
+            LeaderTrackerConfig::default(),
+        );
         assert_eq!(server.addr.port(), 4433);
     }
 
+    #[test]
+    fn test_self_signed_server_needs_no_files() {
+        let server = BifrostServer::new(
+            "127.0.0.2:4433".parse().unwrap(),
+            "missing/cert.pem",
+            "missing/key.pem",
+            LeaderTrackerConfig::default(),
+        );
+        assert!(server.certificate_reloader().reload_certificates().is_err());
+
+        let server = server.with_self_signed().unwrap();
+        server.certificate_reloader().reload_certificates().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_certificate_fails_before_rpc() {
+        // Nothing listens here, so reaching the cluster would fail differently
+        let config = LeaderTrackerConfig {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            ws_url: "ws://127.0.0.1:1".to_string(),
+            ..LeaderTrackerConfig::default()
+        };
+        let server = BifrostServer::new(
+            "127.0.0.2:4433".parse().unwrap(),
+            "missing/cert.pem",
+            "missing/key.pem",
+            config,
+        );
+
+        let err = server.run_until(std::future::pending()).await.unwrap_err();
+        assert!(
+            format!("{:#}", err).starts_with("Failed to load certificates"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drain_sessions_waits_then_gives_up() {
+        let mut sessions = JoinSet::new();
+        sessions.spawn(tokio::time::sleep(Duration::from_millis(10)));
+        assert!(drain_sessions(&mut sessions, Duration::from_secs(1)).await);
+        assert!(sessions.is_empty());
+
+        sessions.spawn(std::future::pending());
+        assert!(!drain_sessions(&mut sessions, Duration::from_millis(20)).await);
+        assert_eq!(sessions.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_tpu_client_creation() {
-        use crate::tpu_client::TpuConnectionManager;
+        use crate::tpu_client::{LeaderTracker, TpuConnectionManager};
 
-        // LeaderTracker::new() now returns Result
         let leader_tracker = Arc::new(
-            LeaderTracker::new()
+            LeaderTracker::new(LeaderTrackerConfig::default())
                 .await
                 .expect("Failed to initialize LeaderTracker"),
         );
@@ -0,0 +1,95 @@
+//! Per-session transaction rate limiting.
+
+use crate::server::SessionRateLimit;
+use tokio::time::Instant;
+
+/// Whether a session may submit more transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// Within the limit, forward the transactions.
+    Allowed,
+    /// Over the limit, reply without forwarding.
+    Throttled,
+    /// Over the limit too many times, reply and close the session.
+    Close,
+}
+
+/// Token bucket refilled at `per_second` up to `burst` tokens, one token per
+/// transaction.
+#[derive(Debug)]
+pub struct SessionLimiter {
+    limit: SessionRateLimit,
+    tokens: f64,
+    last_refill: Instant,
+    violations: u32,
+}
+
+impl SessionLimiter {
+    /// Creates a limiter with a full bucket.
+    pub fn new(limit: SessionRateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last_refill: Instant::now(),
+            violations: 0,
+        }
+    }
+
+    /// Takes `count` tokens if available.
+    ///
+    /// A request that doesn't fit takes nothing and counts as a violation,
+    /// so a batch is either forwarded whole or not at all.
+    pub fn admit(&mut self, count: usize) -> Admission {
+        self.admit_at(count, Instant::now())
+    }
+
+    fn admit_at(&mut self, count: usize, now: Instant) -> Admission {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.limit.per_second)
+            .min(self.limit.burst as f64);
+        self.last_refill = now;
+
+        if self.tokens >= count as f64 {
+            self.tokens -= count as f64;
+            return Admission::Allowed;
+        }
+
+        self.violations += 1;
+        match self.limit.max_violations {
+            Some(max) if self.violations >= max => Admission::Close,
+            _ => Admission::Throttled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_streams_over_the_limit_are_throttled() {
+        let mut limiter = SessionLimiter::new(SessionRateLimit {
+            per_second: 10.0,
+            burst: 3,
+            max_violations: Some(3),
+        });
+        let start = limiter.last_refill;
+
+        // A burst is allowed, the stream right after it is not
+        for _ in 0..3 {
+            assert_eq!(limiter.admit_at(1, start), Admission::Allowed);
+        }
+        assert_eq!(limiter.admit_at(1, start), Admission::Throttled);
+
+        // 100ms refills one token
+        let later = start + Duration::from_millis(100);
+        assert_eq!(limiter.admit_at(1, later), Admission::Allowed);
+        assert_eq!(limiter.admit_at(1, later), Admission::Throttled);
+
+        // A batch larger than the bucket never fits, and the third violation
+        // closes the session
+        let much_later = start + Duration::from_secs(10);
+        assert_eq!(limiter.admit_at(4, much_later), Admission::Close);
+    }
+}
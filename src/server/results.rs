@@ -0,0 +1,183 @@
+//! Bounded cache of recent forwarding results, keyed by transaction signature
+//! as returned by [`transaction_id`](crate::server::transaction_id).
+//!
+//! Doubles as a log of recent forwards, so a report of a transaction that
+//! didn't land can be checked against what Bifrost did with it.
+
+use crate::server::Outcome;
+use crate::tpu_client::LeaderDeliveryResult;
+use solana_sdk::signature::Signature;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Number of results kept before the oldest ones are evicted.
+pub const DEFAULT_RESULT_CACHE_CAPACITY: usize = 10_000;
+
+/// What happened to a single transaction submitted through Bifrost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionResult {
+    pub outcome: Outcome,
+    /// Per-leader delivery status, empty if forwarding never reached a leader.
+    pub deliveries: Vec<LeaderDeliveryResult>,
+    pub latency: Duration,
+}
+
+/// A cached result together with when and for which transaction it was
+/// recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentForward {
+    pub signature: Signature,
+    pub recorded_at: SystemTime,
+    pub result: TransactionResult,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    results: HashMap<Signature, (SystemTime, TransactionResult)>,
+    order: VecDeque<Signature>,
+}
+
+/// FIFO-bounded map from signature to [`TransactionResult`].
+#[derive(Debug)]
+pub struct ResultCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Records the result for `signature`, evicting the oldest entry when full.
+    pub fn record(&self, signature: Signature, result: TransactionResult) {
+        let mut entries = self.entries.lock().unwrap();
+
+        let recorded_at = SystemTime::now();
+        if entries
+            .results
+            .insert(signature, (recorded_at, result))
+            .is_none()
+        {
+            entries.order.push_back(signature);
+        }
+
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.results.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns the recorded result for `signature`, if still cached.
+    pub fn get(&self, signature: &Signature) -> Option<TransactionResult> {
+        self.entries
+            .lock()
+            .unwrap()
+            .results
+            .get(signature)
+            .map(|(_, result)| result.clone())
+    }
+
+    /// Returns up to `limit` of the most recently submitted transactions'
+    /// results, newest first.
+    pub fn recent_forwards(&self, limit: usize) -> Vec<RecentForward> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .order
+            .iter()
+            .rev()
+            .take(limit)
+            .filter_map(|signature| {
+                let (recorded_at, result) = entries.results.get(signature)?;
+                Some(RecentForward {
+                    signature: *signature,
+                    recorded_at: *recorded_at,
+                    result: result.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESULT_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> TransactionResult {
+        TransactionResult {
+            outcome: Outcome::Forwarded {
+                signature: None,
+                latency: Duration::from_millis(3),
+            },
+            deliveries: vec![LeaderDeliveryResult {
+                leader_identity: "leader".to_string(),
+                socket: "127.0.0.1:8009".to_string(),
+                slot: 42,
+                priority: 0,
+                udp_fallback: false,
+                retryable: false,
+                dry_run: false,
+                outcome: Ok(Duration::from_millis(1)),
+            }],
+            latency: Duration::from_millis(3),
+        }
+    }
+
+    #[test]
+    fn test_evicts_oldest() {
+        let cache = ResultCache::new(2);
+        let sigs: Vec<Signature> = (0..3u8).map(|i| Signature::from([i; 64])).collect();
+
+        for sig in &sigs {
+            cache.record(*sig, result());
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&sigs[0]), None);
+        assert_eq!(cache.get(&sigs[2]), Some(result()));
+    }
+
+    #[test]
+    fn test_recent_forwards_newest_first() {
+        let cache = ResultCache::new(3);
+        let sigs: Vec<Signature> = (0..4u8).map(|i| Signature::from([i; 64])).collect();
+
+        for sig in &sigs {
+            cache.record(*sig, result());
+        }
+
+        let recent = cache.recent_forwards(2);
+        assert_eq!(
+            recent.iter().map(|r| r.signature).collect::<Vec<_>>(),
+            [sigs[3], sigs[2]]
+        );
+        assert_eq!(recent[0].result, result());
+        assert!(recent[0].recorded_at >= recent[1].recorded_at);
+
+        // Never more than what is still cached
+        assert_eq!(cache.recent_forwards(10).len(), 3);
+    }
+}
@@ -0,0 +1,141 @@
+//! Cheap checks of untrusted transaction bytes before bincode sees them.
+
+/// Wire size of a signature.
+const SIGNATURE_SIZE: usize = 64;
+/// Wire size of an account key, a blockhash or a lookup table address.
+const KEY_SIZE: usize = 32;
+/// Bytes of the legacy message header.
+const MESSAGE_HEADER_SIZE: usize = 3;
+/// Bit set in the first message byte of versioned messages.
+const MESSAGE_VERSION_PREFIX: u8 = 0x80;
+/// Smallest compiled instruction: a program index and two empty vectors.
+const MIN_INSTRUCTION_SIZE: usize = 3;
+/// Smallest address table lookup: an address and two empty vectors.
+const MIN_LOOKUP_SIZE: usize = KEY_SIZE + 2;
+
+/// Checks that every vector in a serialized transaction claims no more
+/// elements than the bytes after its length prefix could hold.
+///
+/// bincode preallocates a `short_vec` for as many elements as its prefix
+/// claims, so a 3 byte payload claiming 65535 signatures would allocate
+/// 4 MiB before failing on the missing bytes. Once this passes, what bincode
+/// allocates is bounded by the payload size. A truncated payload passes, and
+/// bincode reports it.
+///
+/// # Errors
+///
+/// Returns the name of the first vector whose length exceeds the payload.
+pub(crate) fn check_declared_lengths(tx_data: &[u8]) -> Result<(), String> {
+    let mut wire = Wire {
+        data: tx_data,
+        pos: 0,
+    };
+
+    let signatures = wire.vec_len("signatures", SIGNATURE_SIZE)?;
+    wire.skip(signatures * SIGNATURE_SIZE);
+
+    let versioned = wire
+        .data
+        .get(wire.pos)
+        .is_some_and(|byte| byte & MESSAGE_VERSION_PREFIX != 0);
+    wire.skip(usize::from(versioned) + MESSAGE_HEADER_SIZE);
+
+    let account_keys = wire.vec_len("account keys", KEY_SIZE)?;
+    // The keys and the recent blockhash
+    wire.skip((account_keys + 1) * KEY_SIZE);
+
+    let instructions = wire.vec_len("instructions", MIN_INSTRUCTION_SIZE)?;
+    for _ in 0..instructions {
+        // Program index
+        wire.skip(1);
+        wire.skip_bytes("instruction accounts")?;
+        wire.skip_bytes("instruction data")?;
+    }
+
+    if versioned {
+        let lookups = wire.vec_len("address table lookups", MIN_LOOKUP_SIZE)?;
+        for _ in 0..lookups {
+            wire.skip(KEY_SIZE);
+            wire.skip_bytes("writable indexes")?;
+            wire.skip_bytes("readonly indexes")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read position in a serialized transaction. Past the end, every vector
+/// reads as empty.
+struct Wire<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Wire<'_> {
+    /// Reads the compact-u16 length prefix of a vector whose elements take at
+    /// least `min_element_size` bytes each.
+    fn vec_len(&mut self, name: &str, min_element_size: usize) -> Result<usize, String> {
+        let Some(len) = self.compact_u16() else {
+            self.pos = self.data.len();
+            return Ok(0);
+        };
+
+        let remaining = self.data.len().saturating_sub(self.pos);
+        if len * min_element_size > remaining {
+            return Err(format!("{} length {} exceeds payload", name, len));
+        }
+        Ok(len)
+    }
+
+    /// Skips over a vector of bytes.
+    fn skip_bytes(&mut self, name: &str) -> Result<(), String> {
+        let len = self.vec_len(name, 1)?;
+        self.skip(len);
+        Ok(())
+    }
+
+    fn skip(&mut self, bytes: usize) {
+        self.pos = self.pos.saturating_add(bytes);
+    }
+
+    /// Decodes up to 3 bytes of 7-bit groups, least significant first.
+    /// Non-canonical encodings are left for bincode to reject.
+    fn compact_u16(&mut self) -> Option<usize> {
+        let mut value = 0;
+        for shift in [0, 7, 14] {
+            let byte = *self.data.get(self.pos)?;
+            self.pos += 1;
+            value |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_lengths_must_fit_payload() {
+        // 65535 signatures in a 3 byte payload
+        let err = check_declared_lengths(&[0xff, 0xff, 0x03]).unwrap_err();
+        assert_eq!(err, "signatures length 65535 exceeds payload");
+
+        // One signature and an empty legacy message claiming 2 account keys
+        let mut payload = vec![1];
+        payload.extend([0; SIGNATURE_SIZE]);
+        payload.extend([1, 0, 0, 2]);
+        payload.extend([0; KEY_SIZE]);
+        assert_eq!(
+            check_declared_lengths(&payload).unwrap_err(),
+            "account keys length 2 exceeds payload"
+        );
+
+        // Truncated payloads are left to bincode
+        assert!(check_declared_lengths(&[]).is_ok());
+        assert!(check_declared_lengths(&[0, 1, 0]).is_ok());
+    }
+}
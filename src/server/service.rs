@@ -0,0 +1,330 @@
+//! Pieces for embedding Bifrost in a host application: the forwarding stack
+//! shared between listeners, and a shutdown handle for a spawned listener.
+
+use crate::server::{
+    BlockhashCache, CertificateReloader, ConfirmationTracker, IpSessionLimiter, PreconnectConfig,
+    SessionConfig, SessionServices, ShadowForwarder,
+};
+use crate::tpu_client::{
+    LeaderTracker, LeaderTrackerConfig, TpuClientConfig, TpuConnectionManager,
+};
+use crate::utils::jitter;
+use anyhow::{Context, Result};
+use futures_util::future::BoxFuture;
+use log::{debug, error, info};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+/// Accept loop of a [`BifrostServer`](super::BifrostServer) as a spawnable
+/// future, see [`into_service`](super::BifrostServer::into_service).
+pub type BifrostService = BoxFuture<'static, Result<()>>;
+
+/// Leader tracker and TPU connection manager, with the background tasks that
+/// keep them current: slot tracking, leader socket refreshes, connection
+/// reaping and prewarming. It also holds what sessions share across
+/// listeners, the [`SessionServices`] and the per-IP session limit, and
+/// handles `SIGUSR1` and `SIGHUP`.
+///
+/// Clones share the same tracker and connections, so several listeners can
+/// forward through one stack, see
+/// [`BifrostServer::into_service_with`](super::BifrostServer::into_service_with).
+/// The background tasks run until [`stop`](Self::stop) is called or the last
+/// clone is dropped.
+#[derive(Clone)]
+pub struct ForwardingStack {
+    pub leader_tracker: Arc<LeaderTracker>,
+    pub tpu_manager: Arc<TpuConnectionManager>,
+    /// Shadow, confirmation polling and blockhash check of every listener's
+    /// sessions.
+    pub services: SessionServices,
+    /// Active sessions per client IP, counted across listeners.
+    pub ip_limiter: Option<Arc<IpSessionLimiter>>,
+    /// Certificates of the listeners, reloaded on `SIGHUP`
+    certificates: Arc<Mutex<Vec<CertificateReloader>>>,
+    tasks: Arc<Mutex<JoinSet<()>>>,
+}
+
+impl ForwardingStack {
+    /// Connects to the cluster and starts the background tasks, along with
+    /// the services `session_config` enables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the leader tracker or the TPU manager cannot be
+    /// initialized, or the signal handlers cannot be registered.
+    pub async fn start(
+        leader_tracker_config: LeaderTrackerConfig,
+        tpu_config: TpuClientConfig,
+        preconnect: PreconnectConfig,
+        session_config: &SessionConfig,
+    ) -> Result<Self> {
+        let rpc_url = leader_tracker_config.rpc_url.clone();
+        let leader_tracker = Arc::new(
+            LeaderTracker::new(leader_tracker_config)
+                .await
+                .context("Failed to initialize LeaderTracker")?,
+        );
+        let tpu_manager = Arc::new(
+            TpuConnectionManager::with_config(leader_tracker.clone(), tpu_config)
+                .context("Failed to create TPU manager")?,
+        );
+
+        let mut stack = Self::spawn_tasks(leader_tracker, tpu_manager, preconnect);
+        stack.start_session_services(session_config, &rpc_url);
+        #[cfg(unix)]
+        stack.spawn_signal_handlers()?;
+        Ok(stack)
+    }
+
+    /// Starts the background tasks keeping `leader_tracker` and `tpu_manager`
+    /// current.
+    fn spawn_tasks(
+        leader_tracker: Arc<LeaderTracker>,
+        tpu_manager: Arc<TpuConnectionManager>,
+        preconnect: PreconnectConfig,
+    ) -> Self {
+        let mut tasks = JoinSet::new();
+
+        // Spawn the slot_updates listener as a background task
+        tasks.spawn(LeaderTracker::run_supervised(leader_tracker.clone()));
+
+        // Spawn task to update leader sockets list every minute, dropping
+        // connections to leaders that moved
+        let leader_tracker_clone = leader_tracker.clone();
+        let refresh_manager = tpu_manager.clone();
+        tasks.spawn(async move {
+            loop {
+                match LeaderTracker::update_leader_sockets(leader_tracker_clone.clone()).await {
+                    Ok(changes) => {
+                        debug!("Leader sockets updated successfully");
+                        refresh_manager
+                            .invalidate_connections(changes.stale_sockets())
+                            .await;
+                    }
+                    Err(e) => error!("Failed to update leader sockets: {}", e),
+                }
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+        });
+
+        // Spawn task to drop dead and idle connections
+        let manager_clone = tpu_manager.clone();
+        tasks.spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                manager_clone.reap_dead_connections().await;
+            }
+        });
+
+        // Spawn task to proactively connect to future leaders
+        let manager_clone = tpu_manager.clone();
+        tasks.spawn(async move {
+            loop {
+                debug!("Pre-connecting to future leaders");
+                manager_clone.prewarm(preconnect.lookahead_slots).await;
+
+                tokio::time::sleep(preconnect.interval + jitter(preconnect.jitter)).await;
+            }
+        });
+
+        Self {
+            leader_tracker,
+            tpu_manager,
+            services: SessionServices::default(),
+            ip_limiter: None,
+            certificates: Arc::new(Mutex::new(Vec::new())),
+            tasks: Arc::new(Mutex::new(tasks)),
+        }
+    }
+
+    /// Sets up the services and the per-IP session limit `session_config`
+    /// enables, polling `rpc_url` for confirmations and blockhashes.
+    fn start_session_services(&mut self, session_config: &SessionConfig, rpc_url: &str) {
+        let mut tasks = self.tasks.lock().unwrap();
+
+        self.services.shadow = session_config.shadow_endpoint.clone().map(|endpoint| {
+            info!("Mirroring transactions to shadow at {}", endpoint);
+            ShadowForwarder::spawn(endpoint)
+        });
+        self.services.confirmations = session_config.confirmation.map(|config| {
+            info!(
+                "Polling {} for confirmations of forwarded transactions",
+                rpc_url
+            );
+            let confirmations = Arc::new(ConfirmationTracker::new(rpc_url, config));
+            let polling = confirmations.clone();
+            tasks.spawn(async move { polling.run().await });
+            confirmations
+        });
+        self.services.blockhashes = session_config.blockhash_check.map(|config| {
+            info!(
+                "Rejecting transactions with expired blockhashes, as seen by {}",
+                rpc_url
+            );
+            let blockhashes = Arc::new(BlockhashCache::new(rpc_url, config));
+            let polling = blockhashes.clone();
+            tasks.spawn(async move { polling.run().await });
+            blockhashes
+        });
+        self.ip_limiter = session_config
+            .max_sessions_per_ip
+            .map(|max| Arc::new(IpSessionLimiter::new(max)));
+    }
+
+    /// Dumps the connection pool to disk on `SIGUSR1`, for debugging, and
+    /// reloads the listeners' certificates on `SIGHUP`.
+    #[cfg(unix)]
+    fn spawn_signal_handlers(&self) -> Result<()> {
+        use crate::constants::POOL_SNAPSHOT_PATH;
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut tasks = self.tasks.lock().unwrap();
+
+        let mut sigusr1 =
+            signal(SignalKind::user_defined1()).context("Failed to register SIGUSR1")?;
+        let manager_clone = self.tpu_manager.clone();
+        tasks.spawn(async move {
+            while sigusr1.recv().await.is_some() {
+                if let Err(e) = manager_clone
+                    .write_snapshot(std::path::Path::new(POOL_SNAPSHOT_PATH))
+                    .await
+                {
+                    error!("Failed to write pool snapshot: {}", e);
+                }
+            }
+        });
+
+        let mut sighup = signal(SignalKind::hangup()).context("Failed to register SIGHUP")?;
+        let certificates = self.certificates.clone();
+        tasks.spawn(async move {
+            while sighup.recv().await.is_some() {
+                let reloaders = certificates.lock().unwrap().clone();
+                for reloader in reloaders {
+                    match reloader.reload_certificates() {
+                        Ok(()) => info!("Reloaded TLS certificate"),
+                        Err(e) => error!("Failed to reload TLS certificate: {:#}", e),
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Reloads `reloader` along with the other listeners' certificates on
+    /// `SIGHUP`.
+    pub(crate) fn reload_on_sighup(&self, reloader: CertificateReloader) {
+        self.certificates.lock().unwrap().push(reloader);
+    }
+
+    /// Stops the background tasks of every clone, so nothing reconnects to
+    /// leaders anymore. Call it before
+    /// [`TpuConnectionManager::close_all_graceful`] on shutdown.
+    pub fn stop(&self) {
+        self.tasks.lock().unwrap().abort_all();
+    }
+}
+
+/// Stops a spawned [`BifrostService`]: it stops accepting sessions, gives
+/// in-flight ones the shutdown timeout to finish and then completes.
+///
+/// Clones stop the same service. Dropping every handle doesn't stop it.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Requests the shutdown. Returns immediately; await the service to know
+    /// when it is done.
+    pub fn shutdown(&self) {
+        self.sender.send_replace(true);
+    }
+
+    /// Completes once [`shutdown`](Self::shutdown) was called on any clone.
+    pub(crate) fn requested(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut receiver = self.sender.subscribe();
+        async move {
+            // The sender lives in `self`'s clones, and a dropped one means no
+            // shutdown can be requested anymore
+            if receiver.wait_for(|requested| *requested).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tpu_client::mock_tpu::MockTpu;
+    use crate::tpu_client::tracker::schedule_tracking::ScheduleTracker;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_stopped_stack_reopens_no_connections() {
+        let tpu = MockTpu::spawn();
+        let config = LeaderTrackerConfig {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            ws_url: "ws://127.0.0.1:1".to_string(),
+            ..LeaderTrackerConfig::default()
+        };
+        let leader_tracker = Arc::new(LeaderTracker::with_schedule(
+            config,
+            ScheduleTracker::from_schedules(0, 432_000, HashMap::new(), HashMap::new()),
+        ));
+        let tpu_config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            ..TpuClientConfig::default()
+        };
+        let tpu_manager = Arc::new(
+            TpuConnectionManager::with_config(leader_tracker.clone(), tpu_config).unwrap(),
+        );
+        let preconnect = PreconnectConfig {
+            interval: Duration::from_millis(10),
+            jitter: Duration::ZERO,
+            ..PreconnectConfig::default()
+        };
+        let stack = ForwardingStack::spawn_tasks(leader_tracker, tpu_manager, preconnect);
+
+        // Prewarming keeps a connection to the static TPU open
+        let manager = stack.tpu_manager.clone();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while manager.connection_count().await == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("prewarming never connected");
+
+        stack.stop();
+        manager.close_all_graceful().await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(manager.connection_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_handle_reaches_every_clone() {
+        let handle = ShutdownHandle::new();
+        let requested = handle.requested();
+        let clone = handle.clone();
+
+        tokio::spawn(async move { clone.shutdown() });
+        tokio::time::timeout(Duration::from_secs(1), requested)
+            .await
+            .expect("shutdown was not observed");
+
+        // Shutdowns requested before subscribing are seen too
+        tokio::time::timeout(Duration::from_secs(1), handle.requested())
+            .await
+            .expect("earlier shutdown was not observed");
+    }
+}
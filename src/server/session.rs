@@ -1,76 +1,509 @@
-use crate::{constants::MAX_TRANSACTION_SIZE, tpu_client::TpuConnectionManager};
-use anyhow::{Context, Result};
-use log::info;
-use solana_sdk::transaction::Transaction;
+use crate::{
+    constants::{BATCH_PATH, MAX_BATCH_TRANSACTIONS, MAX_DATAGRAM_SIZE, MAX_TRANSACTION_SIZE},
+    error::GatewayError,
+    server::{
+        Admission, BlockhashCache, ConfirmationStatus, ConfirmationTracker, ReadRateFloor,
+        ResultCache, SessionConfig, SessionLimiter, ShadowForwarder, TransactionResult,
+        correlation::{self, SessionId, correlation_id},
+        sanitize::check_declared_lengths,
+    },
+    tpu_client::{LeaderDeliveryResult, TargetSlot, TpuConnectionManager},
+};
+use bincode::Options;
+use futures_util::future::join_all;
+use log::{info, warn};
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::fmt;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task::JoinSet;
+use tokio::time::{Instant, timeout_at};
+use tracing::{Instrument, info_span};
+
+/// Step of request handling, used to report which one exhausted the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Read,
+    Deserialize,
+    Forward,
+    Respond,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::Read => "read",
+            Phase::Deserialize => "deserialize",
+            Phase::Forward => "forward",
+            Phase::Respond => "respond",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Why a transaction was rejected before forwarding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// The message has no account keys, so there is no fee payer.
+    MissingFeePayer,
+    /// The transaction carries no signatures.
+    MissingSignature,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            RejectReason::MissingFeePayer => "no_fee_payer",
+            RejectReason::MissingSignature => "no_signature",
+        };
+        f.write_str(code)
+    }
+}
+
+/// How a submitted transaction was handled. Also written back to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Delivered to at least one leader, `latency` after the stream was read.
+    /// Written back as `OK <signature> <latency_ms>`, or a bare `OK` if the
+    /// transaction carries no signature.
+    Forwarded {
+        signature: Option<Signature>,
+        latency: Duration,
+    },
+    /// Delivered, then polled for until it reached `status` or the polling
+    /// deadline passed, see [`SessionConfig::confirmation`]. Written back as
+    /// `OK <signature> <status>`.
+    Confirmed {
+        signature: Signature,
+        status: ConfirmationStatus,
+    },
+    /// Resolved to its leaders but not sent, see
+    /// [`TpuClientConfig::dry_run`](crate::tpu_client::TpuClientConfig::dry_run).
+    /// Written back as `OK DRYRUN`.
+    DryRun,
+    Rejected(RejectReason),
+    Failed(String),
+    TimedOut(Phase),
+}
+
+impl Outcome {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Outcome::Forwarded {
+                signature: Some(signature),
+                latency,
+            } => format!("OK {} {}", signature, latency.as_millis()).into_bytes(),
+            Outcome::Forwarded {
+                signature: None, ..
+            } => b"OK".to_vec(),
+            Outcome::Confirmed { signature, status } => {
+                format!("OK {} {}", signature, status).into_bytes()
+            }
+            Outcome::DryRun => b"OK DRYRUN".to_vec(),
+            Outcome::Rejected(reason) => format!("REJECTED: {}", reason).into_bytes(),
+            Outcome::Failed(e) => format!("ERROR: {}", e).into_bytes(),
+            Outcome::TimedOut(phase) => format!("TIMEOUT: {}", phase).into_bytes(),
+        }
+    }
+}
+
+/// Optional helpers every session of a server shares, set up from its
+/// [`SessionConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct SessionServices {
+    /// Second Bifrost every payload is mirrored to, see
+    /// [`SessionConfig::shadow_endpoint`].
+    pub shadow: Option<ShadowForwarder>,
+    /// See [`SessionConfig::confirmation`].
+    pub confirmations: Option<Arc<ConfirmationTracker>>,
+    /// See [`SessionConfig::blockhash_check`].
+    pub blockhashes: Option<Arc<BlockhashCache>>,
+}
+
+/// Response to transactions over the session's rate limit.
+const RATE_LIMITED: &str = "rate limited";
+/// Response to transactions whose blockhash expired.
+const BLOCKHASH_EXPIRED: &str = "blockhash expired";
+/// Application error code sessions are closed with after repeated rate limit
+/// violations.
+const RATE_LIMITED_CLOSE_CODE: u32 = 429;
+/// Part of the request budget kept for writing the response once confirmation
+/// polling is done.
+const CONFIRMATION_RESPONSE_RESERVE: Duration = Duration::from_millis(50);
+
+/// Applies the session's rate limit, if any, to `count` transactions.
+fn admit(limiter: &mut Option<SessionLimiter>, count: usize) -> Admission {
+    let admission = limiter
+        .as_mut()
+        .map_or(Admission::Allowed, |limiter| limiter.admit(count));
+    if admission != Admission::Allowed {
+        warn!("Rate limiting {} transaction(s)", count);
+    }
+    admission
+}
+
+/// High bit marking an optional target-slot header byte in front of the
+/// transaction. A bincode transaction can never start with it, since that would
+/// mean at least 128 signatures.
+const TARGET_HEADER_FLAG: u8 = 0x80;
+
+/// Splits the optional target-slot header off a stream's payload.
+///
+/// The header is a single byte `TARGET_HEADER_FLAG | policy`, where policy `0`
+/// is [`TargetSlot::LeadingEdge`] and `1` is [`TargetSlot::Completed`]. Payloads
+/// without the header use the default target.
+fn split_target_header(data: &[u8]) -> Result<(TargetSlot, &[u8]), GatewayError> {
+    match data.split_first() {
+        Some((&header, tx_data)) if header & TARGET_HEADER_FLAG != 0 => {
+            let target = match header & !TARGET_HEADER_FLAG {
+                0 => TargetSlot::LeadingEdge,
+                1 => TargetSlot::Completed,
+                other => {
+                    return Err(GatewayError::InvalidTransaction(format!(
+                        "unknown target slot policy: {}",
+                        other
+                    )));
+                }
+            };
+            Ok((target, tx_data))
+        }
+        _ => Ok((TargetSlot::default(), data)),
+    }
+}
+
+/// Largest stream payload that can carry transactions of at most
+/// `max_transaction_size` bytes: one with its target header, or a full batch
+/// of them with the batch framing.
+fn stream_limit(max_transaction_size: usize, batch: bool) -> usize {
+    let payload = max_transaction_size + 1;
+    if batch {
+        4 + MAX_BATCH_TRANSACTIONS * (4 + payload)
+    } else {
+        payload
+    }
+}
+
+/// Splits a batch payload into its transaction frames.
+///
+/// A batch is a little-endian `u32` frame count followed by that many frames,
+/// each a little-endian `u32` length and that many bytes. A frame carries what
+/// a single-transaction stream would, including the optional target header.
+fn split_batch(data: &[u8]) -> Result<Vec<&[u8]>, GatewayError> {
+    let invalid = |reason: String| GatewayError::InvalidTransaction(format!("batch {}", reason));
+
+    let (count, mut rest) =
+        split_u32(data).ok_or_else(|| invalid("is missing its frame count".to_string()))?;
+    if count > MAX_BATCH_TRANSACTIONS {
+        return Err(invalid(format!(
+            "has {} frames, the limit is {}",
+            count, MAX_BATCH_TRANSACTIONS
+        )));
+    }
+
+    let mut frames = Vec::with_capacity(count);
+    for index in 0..count {
+        let (len, tail) = split_u32(rest)
+            .ok_or_else(|| invalid(format!("frame {} is missing its length", index)))?;
+        if len > tail.len() {
+            return Err(invalid(format!("frame {} is truncated", index)));
+        }
+        let (frame, tail) = tail.split_at(len);
+        frames.push(frame);
+        rest = tail;
+    }
+
+    if !rest.is_empty() {
+        return Err(invalid(format!(
+            "has {} trailing bytes after {} frames",
+            rest.len(),
+            count
+        )));
+    }
+
+    Ok(frames)
+}
+
+/// Splits a little-endian `u32` off the front of `data`.
+fn split_u32(data: &[u8]) -> Option<(usize, &[u8])> {
+    let (bytes, rest) = data.split_first_chunk::<4>()?;
+    Some((u32::from_le_bytes(*bytes) as usize, rest))
+}
+
+/// Cheap structural check for transactions that can never land.
+///
+/// Does not verify signatures, only that a fee payer and at least one
+/// signature are present.
+fn validate_structure(transaction: &VersionedTransaction) -> Result<(), RejectReason> {
+    if transaction.message.static_account_keys().is_empty() {
+        return Err(RejectReason::MissingFeePayer);
+    }
+    if transaction.signatures.is_empty() {
+        return Err(RejectReason::MissingSignature);
+    }
+    Ok(())
+}
+
+/// Identifier a transaction is logged and cached under.
+///
+/// This is the first signature, which belongs to the fee payer and is the ID
+/// the cluster and RPC nodes use for the transaction. Additional signatures of
+/// multi-signer transactions are ignored. Returns `None` for a transaction
+/// without signatures.
+pub fn transaction_id(transaction: &VersionedTransaction) -> Option<Signature> {
+    transaction.signatures.first().copied()
+}
+
+/// Returns `true` if the transaction is signed and every signature verifies.
+fn signatures_verify(transaction: &VersionedTransaction) -> bool {
+    !transaction.signatures.is_empty() && transaction.verify_with_results().iter().all(|ok| *ok)
+}
+
+/// Deserializes a legacy or versioned (v0) transaction.
+///
+/// The versioned format is a superset of the legacy one: a legacy message is
+/// told apart by the missing version prefix, so both parse the same way.
+///
+/// The bytes are untrusted: vector lengths are checked against the payload
+/// first, see [`check_declared_lengths`], and bincode reads at most
+/// [`MAX_TRANSACTION_SIZE`] bytes.
+///
+/// # Errors
+///
+/// Returns [`GatewayError::InvalidTransaction`] if the bytes aren't a
+/// transaction.
+pub fn deserialize_transaction(tx_data: &[u8]) -> Result<VersionedTransaction, GatewayError> {
+    let invalid = |e: &dyn fmt::Display| {
+        GatewayError::InvalidTransaction(format!("failed to deserialize: {}", e))
+    };
+
+    check_declared_lengths(tx_data).map_err(|e| invalid(&e))?;
+    bincode::options()
+        .with_limit(MAX_TRANSACTION_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize(tx_data)
+        .map_err(|e| invalid(&e))
+}
 
 /// Handles an individual WebTransport session.
 ///
 /// Accepts bidirectional streams, reads transaction data, deserializes it,
-/// and forwards to the TPU.
+/// and forwards to the TPU. Each stream may start with a target-slot header
+/// byte, see [`split_target_header`].
+///
+/// Sessions opened on [`BATCH_PATH`] carry several transactions per stream,
+/// see [`split_batch`]. The response then has one outcome line per frame.
+///
+/// Transactions may also arrive as datagrams, which skip stream setup. Each
+/// datagram carries what a single-transaction stream would, is forwarded
+/// alongside the streams and its outcome is sent back as a datagram, see
+/// [`handle_datagram`].
+///
+/// Transactions over [`SessionConfig::rate_limit`] are answered with
+/// `ERROR: rate limited` instead of being forwarded, and the session is closed
+/// once it has exceeded the limit too often.
+///
+/// Everything logged while handling the session is attributed to
+/// `session_id` and the stream at hand, see [`correlation_id`].
 ///
 /// # Arguments
 ///
 /// * `session` - The WebTransport session
+/// * `session_id` - Identifier the session's log lines and spans carry
 /// * `tpu_manager` - Shared TPU connection manager
+/// * `config` - Per-session settings such as the request budget
+/// * `results` - Cache the outcome of each transaction is recorded in
+/// * `services` - Optional shadow, confirmation polling and blockhash check
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - Stream acceptance fails
-/// - Transaction reading fails
-/// - Deserialization fails
-/// - TPU forwarding fails
+/// Returns [`GatewayError::Stream`] if reading a stream or writing the
+/// response fails.
+///
+/// Malformed payloads, oversized ones and forwarding failures are written back
+/// to the client as an outcome instead, and the session stays open.
+#[tracing::instrument(
+    name = "session",
+    skip_all,
+    fields(remote = %session.remote_address(), session_id = %session_id)
+)]
 pub async fn handle_session(
     session: web_transport_quinn::Session,
+    session_id: SessionId,
     tpu_manager: Arc<TpuConnectionManager>,
-) -> Result<()> {
+    config: SessionConfig,
+    results: Arc<ResultCache>,
+    services: SessionServices,
+) -> Result<(), GatewayError> {
+    let serving = serve_session(session, tpu_manager, config, results, services);
+    correlation::in_session(session_id, serving).await
+}
+
+async fn serve_session(
+    session: web_transport_quinn::Session,
+    tpu_manager: Arc<TpuConnectionManager>,
+    config: SessionConfig,
+    results: Arc<ResultCache>,
+    services: SessionServices,
+) -> Result<(), GatewayError> {
     info!("Handling session from {}", session.remote_address());
+    let metrics = tpu_manager.metrics();
+    metrics.record_session();
+
+    let batch = session.url().path() == BATCH_PATH;
+    if batch {
+        info!("Session uses batch framing");
+    }
+
+    let mut limiter = config.rate_limit.map(SessionLimiter::new);
+    let mut streams = 0u64;
+    let close_rate_limited = || {
+        warn!("Closing session after repeated rate limit violations");
+        session.close(RATE_LIMITED_CLOSE_CODE, RATE_LIMITED.as_bytes());
+    };
+
+    let reply_datagram = |outcome: Outcome| {
+        if let Err(e) = session.send_datagram(outcome.to_bytes().into()) {
+            warn!("Failed to send datagram response: {}", e);
+        }
+    };
+
+    // Datagrams are forwarded alongside the streams and answered as each
+    // forward completes. Aborted when dropped, with the session
+    let mut datagram_forwards = JoinSet::new();
+    let mut reading_datagrams = true;
 
     loop {
-        match session.accept_bi().await {
+        let accepted = tokio::select! {
+            accepted = session.accept_bi() => accepted,
+            datagram = session.read_datagram(), if reading_datagrams => {
+                let datagram = match datagram {
+                    Ok(datagram) => datagram,
+                    Err(e) => {
+                        // Streams may still arrive
+                        log::error!("Failed to read datagram, no longer reading datagrams: {}", e);
+                        reading_datagrams = false;
+                        continue;
+                    }
+                };
+                streams += 1;
+                correlation::enter_stream(streams);
+                info!("Datagram received");
+                metrics.record_received(1);
+
+                let admission = admit(&mut limiter, 1);
+                if admission == Admission::Allowed {
+                    let deadline = Instant::now() + config.request_timeout;
+                    let config = config.clone();
+                    let results = results.clone();
+                    let services = services.clone();
+                    let tpu_manager = tpu_manager.clone();
+                    let forward = async move {
+                        let forward = |tx_data, target| {
+                            tpu_manager.send_transaction_targeting(tx_data, target)
+                        };
+                        handle_datagram(&datagram, deadline, &config, &results, &services, forward)
+                            .await
+                    };
+                    datagram_forwards.spawn(correlation::in_stream(streams, forward));
+                    continue;
+                }
+
+                reply_datagram(Outcome::Failed(RATE_LIMITED.to_string()));
+                if admission == Admission::Close {
+                    close_rate_limited();
+                    break;
+                }
+                continue;
+            }
+            Some(forwarded) = datagram_forwards.join_next(), if !datagram_forwards.is_empty() => {
+                match forwarded {
+                    Ok(outcome) => reply_datagram(outcome),
+                    Err(e) => log::error!("Datagram forward failed: {}", e),
+                }
+                continue;
+            }
+        };
+
+        match accepted {
             Ok((mut send, mut recv)) => {
+                streams += 1;
+                correlation::enter_stream(streams);
                 info!("New stream opened");
 
+                // The budget covers everything from here until the response is written
+                let deadline = Instant::now() + config.request_timeout;
+                let mut admission = Admission::Allowed;
+
                 // Read raw transaction data from WebTransport
-                let tx_data = recv
-                    .read_to_end(MAX_TRANSACTION_SIZE)
-                    .await
-                    .context("Failed to read transaction")?;
-
-                info!("Received transaction: {} bytes", tx_data.len());
-
-                // Deserialize at the boundary - fail fast if invalid
-                let transaction: Transaction =
-                    bincode::deserialize(&tx_data).context("Failed to deserialize transaction")?;
-
-                info!(
-                    "Transaction signature: {}, accounts: {}",
-                    transaction
-                        .signatures
-                        .first()
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|| "none".to_string()),
-                    transaction.message.account_keys.len()
-                );
-
-                // Forward the deserialized transaction to TPU
-                match tpu_manager.send_transaction(&tx_data).await {
-                    Ok(confirmation) => {
-                        info!(
-                            "Transaction forwarded successfully (latency: {:?})",
-                            confirmation.latency
-                        );
-                        send.write_all(b"OK").await?;
+                let limit = stream_limit(config.max_transaction_size, batch);
+                let reading = read_payload(&mut recv, limit, config.min_read_rate);
+                let response = match timeout_at(deadline, reading).await {
+                    Ok(Err(e)) => read_error_outcome(e)?.to_bytes(),
+                    Ok(Ok(data)) => {
+                        let forward = |tx_data, target| {
+                            tpu_manager.send_transaction_targeting(tx_data, target)
+                        };
+
+                        if batch {
+                            match split_batch(&data) {
+                                Ok(frames) => {
+                                    metrics.record_received(frames.len());
+                                    admission = admit(&mut limiter, frames.len());
+                                    if admission != Admission::Allowed {
+                                        let throttled = Outcome::Failed(RATE_LIMITED.to_string());
+                                        vec![throttled.to_bytes(); frames.len()].join(&b'\n')
+                                    } else {
+                                        handle_batch(
+                                            frames, deadline, &config, &results, &services, forward,
+                                        )
+                                        .await
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("Rejecting batch: {}", e);
+                                    Outcome::Failed(format!("{}: {}", e.code(), e)).to_bytes()
+                                }
+                            }
+                        } else {
+                            metrics.record_received(1);
+                            admission = admit(&mut limiter, 1);
+                            if admission != Admission::Allowed {
+                                Outcome::Failed(RATE_LIMITED.to_string()).to_bytes()
+                            } else {
+                                handle_payload(
+                                    &data, deadline, &config, &results, &services, forward,
+                                )
+                                .await
+                                .unwrap_or_else(|e| {
+                                    warn!("Failed to handle stream: {}", e);
+                                    Outcome::Failed(format!("{}: {}", e.code(), e))
+                                })
+                                .to_bytes()
+                            }
+                        }
                     }
-                    Err(e) => {
-                        log::error!("Failed to forward transaction: {}", e);
-                        let error_msg = format!("ERROR: {}", e);
-                        send.write_all(error_msg.as_bytes()).await?;
+                    Err(_) => {
+                        warn!("Request budget exceeded in phase: {}", Phase::Read);
+                        Outcome::TimedOut(Phase::Read).to_bytes()
                     }
+                };
+
+                match timeout_at(deadline, send.write_all(&response)).await {
+                    Ok(written) => written.map_err(|e| GatewayError::Stream(e.to_string()))?,
+                    Err(_) => warn!("Request budget exceeded in phase: {}", Phase::Respond),
                 }
 
-                send.finish()?;
+                send.finish()
+                    .map_err(|e| GatewayError::Stream(e.to_string()))?;
+
+                if admission == Admission::Close {
+                    close_rate_limited();
+                    break;
+                }
             }
             Err(e) => {
                 log::error!("Failed to accept stream: {}", e);
@@ -81,3 +514,1259 @@ pub async fn handle_session(
 
     Ok(())
 }
+
+/// Size of each read from the stream.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Reads `reader` to the end, failing if more than `limit` bytes arrive or the
+/// average rate drops below `floor` once its grace period has passed.
+async fn read_payload<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+    floor: Option<ReadRateFloor>,
+) -> Result<Vec<u8>, GatewayError> {
+    let started = Instant::now();
+    let mut data = Vec::new();
+    let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+
+    loop {
+        let read = reader.read(&mut chunk);
+
+        let n = match floor {
+            Some(floor) if floor.bytes_per_sec > 0 => {
+                // Latest time the next chunk may arrive while keeping the average rate
+                let budget =
+                    Duration::from_secs_f64(data.len() as f64 / floor.bytes_per_sec as f64);
+                match timeout_at(started + floor.grace + budget, read).await {
+                    Ok(n) => n,
+                    Err(_) => {
+                        return Err(GatewayError::Stream(format!(
+                            "Read rate dropped below {} bytes/s after {} bytes",
+                            floor.bytes_per_sec,
+                            data.len()
+                        )));
+                    }
+                }
+            }
+            _ => read.await,
+        }
+        .map_err(|e| GatewayError::Stream(format!("failed to read transaction: {}", e)))?;
+
+        if n == 0 {
+            return Ok(data);
+        }
+        if data.len() + n > limit {
+            return Err(GatewayError::TransactionTooLarge { max: limit });
+        }
+        data.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// Turns a read failure the client can be told about into the outcome written
+/// back to it, keeping the session open. Other failures are returned as is.
+fn read_error_outcome(e: GatewayError) -> Result<Outcome, GatewayError> {
+    match e {
+        GatewayError::TransactionTooLarge { .. } => {
+            warn!("Rejecting stream: {}", e);
+            Ok(Outcome::Failed(format!("{}: {}", e.code(), e)))
+        }
+        e => Err(e),
+    }
+}
+
+/// Forwards the frames of a batch concurrently, within one shared `deadline`,
+/// and returns the response: one outcome line per frame, in frame order.
+///
+/// Frames are independent, so one that fails to deserialize is reported as
+/// an `ERROR` line instead of failing the whole batch.
+async fn handle_batch<'a, F, Fut>(
+    frames: Vec<&'a [u8]>,
+    deadline: Instant,
+    config: &SessionConfig,
+    results: &ResultCache,
+    services: &SessionServices,
+    forward: F,
+) -> Vec<u8>
+where
+    F: Fn(&'a [u8], TargetSlot) -> Fut,
+    Fut: Future<Output = Result<Vec<LeaderDeliveryResult>, GatewayError>>,
+{
+    info!("Received batch of {} transaction(s)", frames.len());
+
+    let forward = &forward;
+    let outcomes = join_all(frames.into_iter().map(|frame| async move {
+        handle_payload(frame, deadline, config, results, services, forward)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to handle batch frame: {}", e);
+                Outcome::Failed(format!("{}: {}", e.code(), e))
+            })
+    }))
+    .await;
+
+    outcomes
+        .iter()
+        .map(Outcome::to_bytes)
+        .collect::<Vec<_>>()
+        .join(&b'\n')
+}
+
+/// Handles a transaction submitted as a datagram and returns the outcome to
+/// send back.
+///
+/// Datagrams can't be fragmented, so payloads over [`MAX_DATAGRAM_SIZE`] are
+/// rejected without being forwarded. Failures are reported as an outcome
+/// instead of closing the session, since the client has no stream to learn
+/// about them otherwise.
+async fn handle_datagram<'a, F, Fut>(
+    data: &'a [u8],
+    deadline: Instant,
+    config: &SessionConfig,
+    results: &ResultCache,
+    services: &SessionServices,
+    forward: F,
+) -> Outcome
+where
+    F: FnOnce(&'a [u8], TargetSlot) -> Fut,
+    Fut: Future<Output = Result<Vec<LeaderDeliveryResult>, GatewayError>>,
+{
+    if data.len() > MAX_DATAGRAM_SIZE {
+        let e = GatewayError::TransactionTooLarge {
+            max: MAX_DATAGRAM_SIZE,
+        };
+        warn!("Rejecting datagram of {} bytes: {}", data.len(), e);
+        return Outcome::Failed(format!("{}: {}", e.code(), e));
+    }
+
+    handle_payload(data, deadline, config, results, services, forward)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to handle datagram: {}", e);
+            Outcome::Failed(format!("{}: {}", e.code(), e))
+        })
+}
+
+/// Handles the raw payload of one stream: mirrors it to the shadow if one is
+/// configured, then splits off the target header and forwards the transaction.
+/// With confirmation polling, a forwarded transaction is then polled for until
+/// it lands or the budget runs out.
+async fn handle_payload<'a, F, Fut>(
+    data: &'a [u8],
+    deadline: Instant,
+    config: &SessionConfig,
+    results: &ResultCache,
+    services: &SessionServices,
+    forward: F,
+) -> Result<Outcome, GatewayError>
+where
+    F: FnOnce(&'a [u8], TargetSlot) -> Fut,
+    Fut: Future<Output = Result<Vec<LeaderDeliveryResult>, GatewayError>>,
+{
+    if let Some(shadow) = &services.shadow {
+        shadow.mirror(data);
+    }
+
+    let (target, tx_data) = split_target_header(data)?;
+    if tx_data.len() > config.max_transaction_size {
+        let e = GatewayError::TransactionTooLarge {
+            max: config.max_transaction_size,
+        };
+        warn!("Rejecting transaction of {} bytes: {}", tx_data.len(), e);
+        return Ok(Outcome::Failed(format!("{}: {}", e.code(), e)));
+    }
+    info!(
+        "Received transaction: {} bytes, target: {:?}",
+        tx_data.len(),
+        target
+    );
+
+    let blockhashes = services.blockhashes.as_deref();
+    let outcome =
+        forward_within_budget(tx_data, deadline, config, results, blockhashes, |tx_data| {
+            forward(tx_data, target)
+        })
+        .await?;
+
+    match (outcome, services.confirmations.as_deref()) {
+        (
+            Outcome::Forwarded {
+                signature: Some(signature),
+                ..
+            },
+            Some(confirmations),
+        ) => {
+            let polling_deadline = deadline - CONFIRMATION_RESPONSE_RESERVE;
+            let status = confirmations.wait(&signature, polling_deadline).await;
+            info!("Transaction {} is {}", signature, status);
+            Ok(Outcome::Confirmed { signature, status })
+        }
+        (outcome, _) => Ok(outcome),
+    }
+}
+
+/// Deserializes `tx_data` and forwards it, aborting once `deadline` passes.
+///
+/// Depending on `config`, structurally invalid transactions (see
+/// [`validate_structure`]) and transactions whose signatures don't verify are
+/// rejected without being forwarded, as are transactions `blockhashes` knows
+/// to have expired. The outcome is recorded in `results` under the
+/// transaction's signature.
+#[tracing::instrument(
+    name = "transaction",
+    skip_all,
+    fields(bytes = tx_data.len(), correlation_id = %correlation_id().unwrap_or_default())
+)]
+async fn forward_within_budget<'a, F, Fut>(
+    tx_data: &'a [u8],
+    deadline: Instant,
+    config: &SessionConfig,
+    results: &ResultCache,
+    blockhashes: Option<&BlockhashCache>,
+    forward: F,
+) -> Result<Outcome, GatewayError>
+where
+    F: FnOnce(&'a [u8]) -> Fut,
+    Fut: Future<Output = Result<Vec<LeaderDeliveryResult>, GatewayError>>,
+{
+    let started = Instant::now();
+
+    // Deserialize at the boundary - fail fast if invalid
+    let transaction = info_span!("deserialize").in_scope(|| deserialize_transaction(tx_data))?;
+
+    let signature = transaction_id(&transaction);
+
+    info!(
+        "Transaction signature: {}, version: {:?}, accounts: {}",
+        signature
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "none".to_string()),
+        transaction.version(),
+        transaction.message.static_account_keys().len()
+    );
+
+    let mut result = TransactionResult {
+        outcome: Outcome::TimedOut(Phase::Deserialize),
+        deliveries: Vec::new(),
+        latency: Duration::ZERO,
+    };
+
+    let rejected = if config.validate_structure {
+        validate_structure(&transaction).err()
+    } else {
+        None
+    };
+
+    if let Some(reason) = rejected {
+        warn!("Rejecting transaction: {}", reason);
+        result.outcome = Outcome::Rejected(reason);
+    } else if config.verify_signatures && !signatures_verify(&transaction) {
+        warn!("Rejecting transaction: invalid signature");
+        result.outcome = Outcome::Failed("invalid signature".to_string());
+    } else if let Some(blockhashes) = blockhashes
+        && !blockhashes.is_recent(transaction.message.recent_blockhash())
+    {
+        warn!(
+            "Rejecting transaction: blockhash {} expired",
+            transaction.message.recent_blockhash()
+        );
+        result.outcome = Outcome::Failed(BLOCKHASH_EXPIRED.to_string());
+    } else if Instant::now() >= deadline {
+        warn!("Request budget exceeded in phase: {}", Phase::Deserialize);
+    } else {
+        // Forward the deserialized transaction to TPU
+        let forwarding = forward(tx_data).instrument(info_span!("forward"));
+        result.outcome = match timeout_at(deadline, forwarding).await {
+            Ok(Ok(deliveries)) => {
+                for delivery in &deliveries {
+                    match &delivery.outcome {
+                        Ok(latency) => info!(
+                            "Delivered to {} at {} (latency: {:?})",
+                            delivery.leader_identity, delivery.socket, latency
+                        ),
+                        Err(e) => warn!(
+                            "Failed to deliver to {} at {}: {}",
+                            delivery.leader_identity, delivery.socket, e
+                        ),
+                    }
+                }
+
+                let outcome = if deliveries.iter().any(LeaderDeliveryResult::is_delivered) {
+                    Outcome::Forwarded {
+                        signature,
+                        latency: started.elapsed(),
+                    }
+                } else if !deliveries.is_empty()
+                    && deliveries.iter().all(|delivery| delivery.dry_run)
+                {
+                    Outcome::DryRun
+                } else {
+                    Outcome::Failed(format!(
+                        "Failed to deliver to any of {} leader(s)",
+                        deliveries.len()
+                    ))
+                };
+                result.deliveries = deliveries;
+                outcome
+            }
+            Ok(Err(e)) => {
+                log::error!("Failed to forward transaction: {}", e);
+                Outcome::Failed(format!("{}: {}", e.code(), e))
+            }
+            Err(_) => {
+                warn!("Request budget exceeded in phase: {}", Phase::Forward);
+                Outcome::TimedOut(Phase::Forward)
+            }
+        };
+    }
+
+    result.latency = started.elapsed();
+    let outcome = result.outcome.clone();
+
+    if let Some(signature) = signature {
+        results.record(signature, result);
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::PACKET_DATA_SIZE;
+    use crate::server::{BlockhashConfig, ConfirmationConfig, SessionRateLimit};
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{VersionedMessage, v0};
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::transaction::{Transaction, TransactionVersion};
+
+    fn transaction(signature: Signature) -> Transaction {
+        let mut transaction = Transaction {
+            signatures: vec![signature],
+            ..Transaction::default()
+        };
+        transaction
+            .message
+            .account_keys
+            .push(Pubkey::new_from_array([1; 32]));
+        transaction
+    }
+
+    fn delivery(socket: &str, outcome: Result<Duration, String>) -> LeaderDeliveryResult {
+        LeaderDeliveryResult {
+            leader_identity: format!("leader-{}", socket),
+            socket: socket.to_string(),
+            slot: 1234,
+            priority: 0,
+            udp_fallback: false,
+            retryable: false,
+            dry_run: false,
+            outcome,
+        }
+    }
+
+    /// Session config for tests using placeholder signatures.
+    fn unverified() -> SessionConfig {
+        SessionConfig {
+            verify_signatures: false,
+            ..SessionConfig::default()
+        }
+    }
+
+    fn forwarded() -> Vec<LeaderDeliveryResult> {
+        vec![delivery("127.0.0.1:8009", Ok(Duration::ZERO))]
+    }
+
+    /// Serves a single WebTransport session on a loopback port, forwarding in
+    /// dry run mode, and returns the client's end of it, opened on `path`.
+    async fn open_session(config: SessionConfig, path: &str) -> web_transport_quinn::Session {
+        use crate::server::cert::CertificateResolver;
+        use crate::tpu_client::tracker::schedule_tracking::ScheduleTracker;
+        use crate::tpu_client::{LeaderTracker, LeaderTrackerConfig, TpuClientConfig};
+        use std::collections::HashMap;
+
+        let addr = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (cert_chain, private_key) =
+            crate::cert::generate_self_signed(&["127.0.0.1".to_string()]).unwrap();
+        let certificates = Arc::new(CertificateResolver::default());
+        certificates.set(cert_chain.clone(), private_key).unwrap();
+        let mut server = super::super::bind(addr, certificates).unwrap();
+
+        let leader_tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig {
+                rpc_url: "http://127.0.0.1:1".to_string(),
+                ws_url: "ws://127.0.0.1:1".to_string(),
+                ..LeaderTrackerConfig::default()
+            },
+            ScheduleTracker::from_schedules(0, 432_000, HashMap::new(), HashMap::new()),
+        ));
+        let tpu_config = TpuClientConfig {
+            static_tpu_addresses: vec!["127.0.0.1:8009".parse().unwrap()],
+            dry_run: true,
+            ..TpuClientConfig::default()
+        };
+        let tpu_manager =
+            Arc::new(TpuConnectionManager::with_config(leader_tracker, tpu_config).unwrap());
+
+        tokio::spawn(async move {
+            let session = server.accept().await.unwrap().ok().await.unwrap();
+            let results = Arc::new(ResultCache::default());
+            let services = SessionServices::default();
+            let handling = handle_session(
+                session,
+                SessionId::new(),
+                tpu_manager,
+                config,
+                results,
+                services,
+            );
+            if let Err(e) = handling.await {
+                warn!("Test session failed: {}", e);
+            }
+        });
+
+        let client = web_transport_quinn::ClientBuilder::new()
+            .with_server_certificates(cert_chain)
+            .unwrap();
+        let url = url::Url::parse(&format!("https://{}{}", addr, path)).unwrap();
+        client.connect(url).await.unwrap()
+    }
+
+    /// Sends `payload` on a new stream of `session` and returns the response.
+    async fn request(session: &web_transport_quinn::Session, payload: &[u8]) -> String {
+        let (mut send, mut recv) = session.open_bi().await.unwrap();
+        send.write_all(payload).await.unwrap();
+        send.finish().unwrap();
+        let response = recv.read_to_end(64 * 1024).await.unwrap();
+        String::from_utf8(response).unwrap()
+    }
+
+    #[test]
+    fn test_split_target_header() {
+        let tx_data = bincode::serialize(&Transaction::default()).unwrap();
+
+        let (target, rest) = split_target_header(&tx_data).unwrap();
+        assert_eq!(target, TargetSlot::LeadingEdge);
+        assert_eq!(rest, &tx_data[..]);
+
+        let framed = [&[TARGET_HEADER_FLAG | 1][..], &tx_data].concat();
+        let (target, rest) = split_target_header(&framed).unwrap();
+        assert_eq!(target, TargetSlot::Completed);
+        assert_eq!(rest, &tx_data[..]);
+
+        assert!(split_target_header(&[TARGET_HEADER_FLAG | 9]).is_err());
+    }
+
+    fn batch(frames: &[&[u8]]) -> Vec<u8> {
+        let mut data = (frames.len() as u32).to_le_bytes().to_vec();
+        for frame in frames {
+            data.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            data.extend_from_slice(frame);
+        }
+        data
+    }
+
+    #[test]
+    fn test_split_batch() {
+        let data = batch(&[b"first", b"", b"third"]);
+        let frames = split_batch(&data).unwrap();
+        assert_eq!(frames, vec![&b"first"[..], &b""[..], &b"third"[..]]);
+
+        // Truncated frame, trailing bytes and a missing count are all rejected
+        assert!(split_batch(&data[..data.len() - 1]).is_err());
+        assert!(split_batch(&[data.as_slice(), &[0]].concat()).is_err());
+        assert!(split_batch(&[1, 0]).is_err());
+
+        let too_many = (MAX_BATCH_TRANSACTIONS as u32 + 1).to_le_bytes();
+        assert!(split_batch(&too_many).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_streams_keep_session_open() {
+        let tx_data = bincode::serialize(&transaction(Signature::from([9; 64]))).unwrap();
+
+        let session = open_session(unverified(), "/").await;
+        let unknown_header = [&[TARGET_HEADER_FLAG | 9][..], &tx_data].concat();
+        assert_eq!(
+            request(&session, &unknown_header).await,
+            "ERROR: invalid_transaction: Invalid transaction: unknown target slot policy: 9"
+        );
+        assert_eq!(request(&session, &tx_data).await, "OK DRYRUN");
+
+        let session = open_session(unverified(), BATCH_PATH).await;
+        assert!(
+            request(&session, &[1, 0])
+                .await
+                .starts_with("ERROR: invalid_transaction")
+        );
+        assert_eq!(request(&session, &batch(&[&tx_data])).await, "OK DRYRUN");
+    }
+
+    #[tokio::test]
+    async fn test_datagrams_are_answered_alongside_streams() {
+        let session = open_session(unverified(), "/").await;
+        let tx_data = |byte| bincode::serialize(&transaction(Signature::from([byte; 64]))).unwrap();
+
+        session.send_datagram(tx_data(1).into()).unwrap();
+        assert_eq!(request(&session, &tx_data(2)).await, "OK DRYRUN");
+        let response = session.read_datagram().await.unwrap();
+        assert_eq!(&response[..], b"OK DRYRUN");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_session_is_closed() {
+        let config = SessionConfig {
+            rate_limit: Some(SessionRateLimit {
+                per_second: 0.001,
+                burst: 1,
+                max_violations: Some(3),
+            }),
+            ..unverified()
+        };
+        let session = open_session(config, "/").await;
+        let tx_data = |byte| bincode::serialize(&transaction(Signature::from([byte; 64]))).unwrap();
+
+        assert_eq!(request(&session, &tx_data(1)).await, "OK DRYRUN");
+        for byte in [2, 3] {
+            assert_eq!(
+                request(&session, &tx_data(byte)).await,
+                "ERROR: rate limited"
+            );
+        }
+
+        // The third violation closes the session. Its response may be dropped
+        // with the connection, so it isn't read.
+        let (mut send, _recv) = session.open_bi().await.unwrap();
+        send.write_all(&tx_data(4)).await.unwrap();
+        send.finish().unwrap();
+
+        // WebTransport codes are carried as reserved HTTP/3 error codes
+        let code = u64::from(RATE_LIMITED_CLOSE_CODE);
+        let http3_code = 0x52e4a40fa8db + code + code / 0x1e;
+        let closed = session.closed().await.to_string();
+        assert!(
+            closed.contains(&format!("{} (code {})", RATE_LIMITED, http3_code)),
+            "{}",
+            closed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_each_frame() {
+        let signature = Signature::from([3; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let data = batch(&[&tx_data, &[0xff; 3]]);
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let response = handle_batch(
+            split_batch(&data).unwrap(),
+            deadline,
+            &unverified(),
+            &results,
+            &SessionServices::default(),
+            |_, _| async { Ok(forwarded()) },
+        )
+        .await;
+
+        let response = String::from_utf8(response).unwrap();
+        let lines: Vec<&str> = response.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(&format!("OK {} ", signature)));
+        assert!(lines[1].starts_with("ERROR: invalid_transaction"));
+        assert!(results.get(&signature).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_datagram_forwarded_unless_oversized() {
+        let signature = Signature::from([4; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let outcome = handle_datagram(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            &SessionServices::default(),
+            |_, _| async { Ok(forwarded()) },
+        )
+        .await;
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+
+        let oversized = vec![0; MAX_DATAGRAM_SIZE + 1];
+        let outcome = handle_datagram(
+            &oversized,
+            deadline,
+            &unverified(),
+            &results,
+            &SessionServices::default(),
+            |_, _| async { panic!("oversized datagram must not be forwarded") },
+        )
+        .await;
+        assert!(
+            String::from_utf8(outcome.to_bytes())
+                .unwrap()
+                .starts_with("ERROR: transaction_too_large")
+        );
+
+        // Undecodable datagrams are reported instead of ending the session
+        let outcome = handle_datagram(
+            &[0xff; 3],
+            deadline,
+            &unverified(),
+            &results,
+            &SessionServices::default(),
+            |_, _| async { Ok(forwarded()) },
+        )
+        .await;
+        assert!(matches!(outcome, Outcome::Failed(_)));
+    }
+
+    #[test]
+    fn test_ok_response_carries_signature_and_latency() {
+        let signature = Signature::from([3; 64]);
+        let outcome = Outcome::Forwarded {
+            signature: Some(signature),
+            latency: Duration::from_micros(12_700),
+        };
+        assert_eq!(
+            outcome.to_bytes(),
+            format!("OK {} 12", signature).into_bytes()
+        );
+
+        let confirmed = Outcome::Confirmed {
+            signature,
+            status: ConfirmationStatus::Finalized,
+        };
+        assert_eq!(
+            confirmed.to_bytes(),
+            format!("OK {} finalized", signature).into_bytes()
+        );
+
+        let unsigned = Outcome::Forwarded {
+            signature: None,
+            latency: Duration::from_millis(12),
+        };
+        assert_eq!(unsigned.to_bytes(), b"OK".to_vec());
+        assert_eq!(Outcome::DryRun.to_bytes(), b"OK DRYRUN".to_vec());
+    }
+
+    #[test]
+    fn test_transaction_id() {
+        let single = VersionedTransaction::from(transaction(Signature::from([1; 64])));
+        assert_eq!(transaction_id(&single), Some(Signature::from([1; 64])));
+
+        // The fee payer's signature identifies multi-signer transactions
+        let mut multi = single.clone();
+        multi.signatures.push(Signature::from([2; 64]));
+        assert_eq!(transaction_id(&multi), Some(Signature::from([1; 64])));
+
+        let mut unsigned = single;
+        unsigned.signatures.clear();
+        assert_eq!(transaction_id(&unsigned), None);
+    }
+
+    fn v0_transaction(payer: &Keypair) -> VersionedTransaction {
+        let message = v0::Message::try_compile(&payer.pubkey(), &[], &[], Hash::default()).unwrap();
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer]).unwrap()
+    }
+
+    #[test]
+    fn test_deserializes_legacy_and_v0_transactions() {
+        let payer = Keypair::new();
+
+        let legacy = Transaction::new_signed_with_payer(
+            &[],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let parsed = deserialize_transaction(&bincode::serialize(&legacy).unwrap()).unwrap();
+        assert_eq!(parsed.version(), TransactionVersion::LEGACY);
+        assert_eq!(transaction_id(&parsed), Some(legacy.signatures[0]));
+        assert!(signatures_verify(&parsed));
+
+        let versioned = v0_transaction(&payer);
+        let parsed = deserialize_transaction(&bincode::serialize(&versioned).unwrap()).unwrap();
+        assert_eq!(parsed.version(), TransactionVersion::Number(0));
+        assert_eq!(parsed, versioned);
+        assert!(signatures_verify(&parsed));
+    }
+
+    #[tokio::test]
+    async fn test_forwards_v0_transaction_unchanged() {
+        let payer = Keypair::new();
+        let versioned = v0_transaction(&payer);
+        let tx_data = bincode::serialize(&versioned).unwrap();
+        let expected = tx_data.clone();
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &SessionConfig::default(),
+            &results,
+            None,
+            |forwarded_data| async move {
+                assert_eq!(forwarded_data, &expected[..]);
+                Ok(forwarded())
+            },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            outcome,
+            Outcome::Forwarded { signature: Some(signature), .. } if signature == versioned.signatures[0]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_garbage_payload_is_invalid_transaction() {
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let err = forward_within_budget(
+            &[0xff; 3],
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { Ok(forwarded()) },
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidTransaction(_)));
+        assert_eq!(err.code(), "invalid_transaction");
+    }
+
+    #[tokio::test]
+    async fn test_forward_error_reports_code() {
+        let tx_data = bincode::serialize(&transaction(Signature::default())).unwrap();
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { Err(GatewayError::NoLeaders) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            outcome.to_bytes(),
+            b"ERROR: no_leaders: No leaders known for the target slot".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_forward_exceeds_budget() {
+        let tx_data = bincode::serialize(&transaction(Signature::default())).unwrap();
+        let deadline = Instant::now() + Duration::from_millis(20);
+
+        let results = ResultCache::default();
+
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok(forwarded())
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, Outcome::TimedOut(Phase::Forward));
+        assert_eq!(outcome.to_bytes(), b"TIMEOUT: forward".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_result_queryable_by_signature() {
+        let signature = Signature::from([7; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let results = ResultCache::default();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { Ok(forwarded()) },
+        )
+        .await
+        .unwrap();
+
+        let result = results.get(&signature).expect("result should be cached");
+        assert!(matches!(result.outcome, Outcome::Forwarded { .. }));
+        assert_eq!(result.deliveries, forwarded());
+    }
+
+    #[tokio::test]
+    async fn test_partial_delivery_reports_each_leader() {
+        let signature = Signature::from([9; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let healthy = delivery("10.0.0.1:8009", Ok(Duration::from_millis(2)));
+        let broken = delivery("10.0.0.2:8009", Err("No open connection".to_string()));
+
+        let deliveries = vec![healthy.clone(), broken.clone()];
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async move { Ok(deliveries) },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+        assert_eq!(
+            results.get(&signature).unwrap().deliveries,
+            vec![healthy, broken.clone()]
+        );
+
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async move { Ok(vec![broken]) },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, Outcome::Failed(_)));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_is_not_a_failure() {
+        let signature = Signature::from([10; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let resolved = LeaderDeliveryResult {
+            dry_run: true,
+            ..delivery("10.0.0.1:8009", Err("dry run".to_string()))
+        };
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async move { Ok(vec![resolved]) },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, Outcome::DryRun));
+        assert!(matches!(
+            results.get(&signature).unwrap().outcome,
+            Outcome::DryRun
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_missing_fee_payer() {
+        let signature = Signature::from([3; 64]);
+        let mut no_fee_payer = transaction(signature);
+        no_fee_payer.message.account_keys.clear();
+        let tx_data = bincode::serialize(&no_fee_payer).unwrap();
+        let results = ResultCache::default();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { panic!("rejected transaction must not be forwarded") },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, Outcome::Rejected(RejectReason::MissingFeePayer));
+        assert_eq!(outcome.to_bytes(), b"REJECTED: no_fee_payer".to_vec());
+        assert_eq!(results.get(&signature).unwrap().outcome, outcome);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_missing_signature() {
+        let mut unsigned = transaction(Signature::default());
+        unsigned.signatures.clear();
+        let tx_data = bincode::serialize(&unsigned).unwrap();
+        let results = ResultCache::default();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { panic!("rejected transaction must not be forwarded") },
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, Outcome::Rejected(RejectReason::MissingSignature));
+        assert_eq!(outcome.to_bytes(), b"REJECTED: no_signature".to_vec());
+
+        // Forwarded as before when validation is disabled
+        let unchecked = SessionConfig {
+            validate_structure: false,
+            ..unverified()
+        };
+        let outcome =
+            forward_within_budget(&tx_data, deadline, &unchecked, &results, None, |_| async {
+                Ok(forwarded())
+            })
+            .await
+            .unwrap();
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_verifies_signatures() {
+        let payer = Keypair::new();
+        let signed = Transaction::new_signed_with_payer(
+            &[],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let config = SessionConfig::default();
+
+        let tx_data = bincode::serialize(&signed).unwrap();
+        let outcome =
+            forward_within_budget(&tx_data, deadline, &config, &results, None, |_| async {
+                Ok(forwarded())
+            })
+            .await
+            .unwrap();
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+
+        // Tampering with the signature makes verification fail
+        let mut forged = signed.clone();
+        forged.signatures[0] = Signature::from([7; 64]);
+        let tx_data = bincode::serialize(&forged).unwrap();
+        let outcome =
+            forward_within_budget(&tx_data, deadline, &config, &results, None, |_| async {
+                panic!("unverified transaction must not be forwarded")
+            })
+            .await
+            .unwrap();
+        assert_eq!(outcome.to_bytes(), b"ERROR: invalid signature".to_vec());
+
+        // Pre-verified traffic can skip the check
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { Ok(forwarded()) },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rejects_expired_blockhash() {
+        let config = BlockhashConfig::default();
+        let blockhashes =
+            BlockhashCache::from_client(RpcClient::new_mock("fails".to_string()), config);
+        let results = ResultCache::default();
+
+        let expired = transaction(Signature::from([6; 64]));
+        blockhashes.record(expired.message.recent_blockhash);
+        tokio::time::advance(config.ttl).await;
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let tx_data = bincode::serialize(&expired).unwrap();
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            Some(&blockhashes),
+            |_| async { panic!("expired transaction must not be forwarded") },
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome.to_bytes(), b"ERROR: blockhash expired".to_vec());
+
+        // Recent blockhashes, and ones the poll never saw, are forwarded
+        let mut recent = transaction(Signature::from([7; 64]));
+        recent.message.recent_blockhash = Hash::new_from_array([7; 32]);
+        blockhashes.record(recent.message.recent_blockhash);
+        let mut unknown = transaction(Signature::from([8; 64]));
+        unknown.message.recent_blockhash = Hash::new_from_array([8; 32]);
+        for transaction in [recent, unknown] {
+            let tx_data = bincode::serialize(&transaction).unwrap();
+            let outcome = forward_within_budget(
+                &tx_data,
+                deadline,
+                &unverified(),
+                &results,
+                Some(&blockhashes),
+                |_| async { Ok(forwarded()) },
+            )
+            .await
+            .unwrap();
+            assert!(matches!(outcome, Outcome::Forwarded { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payload_mirrored_to_shadow() {
+        let signature = Signature::from([5; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let payload = [&[TARGET_HEADER_FLAG | 1][..], &tx_data].concat();
+
+        let (queue, mut mirrored) = tokio::sync::mpsc::channel(8);
+        let services = SessionServices {
+            shadow: Some(ShadowForwarder::from_sender(queue)),
+            ..SessionServices::default()
+        };
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let outcome = handle_payload(
+            &payload,
+            deadline,
+            &unverified(),
+            &results,
+            &services,
+            |forwarded_tx, target| {
+                assert_eq!(forwarded_tx, &tx_data[..]);
+                assert_eq!(target, TargetSlot::Completed);
+                async { Ok(forwarded()) }
+            },
+        )
+        .await
+        .unwrap();
+
+        // Primary path is unaffected and the shadow gets the payload as received
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+        assert!(matches!(
+            results.get(&signature).unwrap().outcome,
+            Outcome::Forwarded { .. }
+        ));
+        assert_eq!(mirrored.try_recv().unwrap(), payload);
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_payload_waits_for_confirmation() {
+        let signature = Signature::from([8; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+
+        // The mock sender reports every transaction as finalized on "succeeds"
+        let confirmations = Arc::new(ConfirmationTracker::from_client(
+            RpcClient::new_mock("succeeds".to_string()),
+            ConfirmationConfig::default(),
+        ));
+        let polling = confirmations.clone();
+        let poller = tokio::spawn(async move { polling.run().await });
+        let services = SessionServices {
+            confirmations: Some(confirmations),
+            ..SessionServices::default()
+        };
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(2);
+
+        let outcome = handle_payload(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            &services,
+            |_, _| async { Ok(forwarded()) },
+        )
+        .await
+        .unwrap();
+        poller.abort();
+
+        assert_eq!(
+            outcome,
+            Outcome::Confirmed {
+                signature,
+                status: ConfirmationStatus::Finalized,
+            }
+        );
+        assert_eq!(
+            outcome.to_bytes(),
+            format!("OK {} finalized", signature).into_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trickled_read_is_aborted() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let floor = Some(ReadRateFloor {
+            bytes_per_sec: 1000,
+            grace: Duration::from_millis(50),
+        });
+
+        // One byte every 20ms is ~50 bytes/s, far below the floor
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            for _ in 0..100 {
+                if client.write_all(&[0]).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        let err = read_payload(&mut server, MAX_TRANSACTION_SIZE, floor)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Read rate dropped below"));
+        assert_eq!(err.code(), "stream_error");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_transaction_is_reported() {
+        let payload = vec![0u8; MAX_TRANSACTION_SIZE + 1];
+
+        let err = read_payload(&mut &payload[..], MAX_TRANSACTION_SIZE, None)
+            .await
+            .unwrap_err();
+        let outcome = read_error_outcome(err).unwrap();
+
+        assert_eq!(
+            outcome.to_bytes(),
+            format!(
+                "ERROR: transaction_too_large: transaction too large (max {} bytes)",
+                MAX_TRANSACTION_SIZE
+            )
+            .into_bytes()
+        );
+
+        // Anything else still ends the session
+        assert!(read_error_outcome(GatewayError::Stream("reset".to_string())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_configured_max_transaction_size_applies() {
+        let signature = Signature::from([6; 64]);
+        let tx_data = bincode::serialize(&transaction(signature)).unwrap();
+        let results = ResultCache::default();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let config = SessionConfig {
+            max_transaction_size: tx_data.len() - 1,
+            ..unverified()
+        };
+
+        let outcome = handle_payload(
+            &tx_data,
+            deadline,
+            &config,
+            &results,
+            &SessionServices::default(),
+            |_, _| async { panic!("oversized transaction must not be forwarded") },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Failed(format!(
+                "transaction_too_large: transaction too large (max {} bytes)",
+                tx_data.len() - 1
+            ))
+        );
+
+        // Streams leave room for the target header, and batches for their framing
+        assert_eq!(stream_limit(PACKET_DATA_SIZE, false), PACKET_DATA_SIZE + 1);
+        let frame = [0u8; PACKET_DATA_SIZE + 1];
+        let frames = vec![&frame[..]; MAX_BATCH_TRANSACTIONS];
+        let full_batch = batch(&frames);
+        assert_eq!(full_batch.len(), stream_limit(PACKET_DATA_SIZE, true));
+    }
+
+    #[tokio::test]
+    async fn test_read_payload_within_floor() {
+        let payload = vec![7u8; 10_000];
+        let floor = Some(ReadRateFloor {
+            bytes_per_sec: 1000,
+            grace: Duration::from_millis(50),
+        });
+
+        let mut reader = &payload[..];
+        let data = read_payload(&mut reader, stream_limit(MAX_TRANSACTION_SIZE, true), floor)
+            .await
+            .unwrap();
+        assert_eq!(data, payload);
+
+        let mut reader = &payload[..];
+        assert!(read_payload(&mut reader, 100, floor).await.is_err());
+    }
+
+    #[cfg(feature = "otel")]
+    #[tokio::test]
+    async fn test_forward_emits_spans() {
+        use opentelemetry::trace::TracerProvider as _;
+        use opentelemetry_sdk::trace::{InMemorySpanExporterBuilder, SdkTracerProvider};
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let exporter = InMemorySpanExporterBuilder::new().build();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let tx_data = bincode::serialize(&transaction(Signature::default())).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let results = ResultCache::default();
+        let outcome = forward_within_budget(
+            &tx_data,
+            deadline,
+            &unverified(),
+            &results,
+            None,
+            |_| async { Ok(forwarded()) },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, Outcome::Forwarded { .. }));
+
+        provider.force_flush().unwrap();
+        let names: Vec<String> = exporter
+            .get_finished_spans()
+            .unwrap()
+            .into_iter()
+            .map(|span| span.name.to_string())
+            .collect();
+
+        for expected in ["transaction", "deserialize", "forward"] {
+            assert!(
+                names.iter().any(|n| n == expected),
+                "missing span {}",
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Mirroring of incoming transactions to a second Bifrost instance.
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use tokio::sync::mpsc;
+use url::Url;
+
+/// Payloads queued for the shadow before new copies are dropped.
+const SHADOW_QUEUE_CAPACITY: usize = 1024;
+
+/// Sends a copy of every submitted payload to a shadow Bifrost over
+/// WebTransport, for canarying a new version against live traffic.
+///
+/// Mirroring never blocks or fails the primary path: copies are queued and
+/// sent from a background task, dropped when the queue is full, and the
+/// shadow's responses are ignored.
+#[derive(Debug, Clone)]
+pub struct ShadowForwarder {
+    queue: mpsc::Sender<Vec<u8>>,
+}
+
+impl ShadowForwarder {
+    /// Starts a background task mirroring payloads to `endpoint`.
+    pub fn spawn(endpoint: Url) -> Self {
+        let (queue, rx) = mpsc::channel(SHADOW_QUEUE_CAPACITY);
+        tokio::spawn(run(endpoint, rx));
+        Self { queue }
+    }
+
+    /// Creates a forwarder feeding `queue` directly, without a WebTransport client.
+    #[cfg(test)]
+    pub(crate) fn from_sender(queue: mpsc::Sender<Vec<u8>>) -> Self {
+        Self { queue }
+    }
+
+    /// Queues a copy of `payload` for the shadow.
+    pub fn mirror(&self, payload: &[u8]) {
+        if self.queue.try_send(payload.to_vec()).is_err() {
+            debug!("Shadow queue full or closed, dropping mirrored copy");
+        }
+    }
+}
+
+async fn run(endpoint: Url, mut rx: mpsc::Receiver<Vec<u8>>) {
+    let client = match web_transport_quinn::ClientBuilder::new().with_system_roots() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create shadow WebTransport client: {}", e);
+            return;
+        }
+    };
+
+    let mut session: Option<web_transport_quinn::Session> = None;
+
+    while let Some(payload) = rx.recv().await {
+        if session.is_none() {
+            match client.connect(endpoint.clone()).await {
+                Ok(s) => {
+                    info!("Connected to shadow at {}", endpoint);
+                    session = Some(s);
+                }
+                Err(e) => {
+                    warn!("Failed to connect to shadow at {}: {}", endpoint, e);
+                    continue;
+                }
+            }
+        }
+
+        if let Some(s) = &session
+            && let Err(e) = send(s, &payload).await
+        {
+            warn!("Failed to mirror transaction to shadow: {}", e);
+            // Reconnect on the next payload
+            session = None;
+        }
+    }
+}
+
+/// Writes `payload` on a fresh stream. The shadow's response is not awaited.
+async fn send(session: &web_transport_quinn::Session, payload: &[u8]) -> Result<()> {
+    let (mut send, _recv) = session
+        .open_bi()
+        .await
+        .context("Failed to open shadow stream")?;
+
+    send.write_all(payload)
+        .await
+        .context("Failed to write to shadow")?;
+    send.finish().context("Failed to finish shadow stream")?;
+
+    Ok(())
+}
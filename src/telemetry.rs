@@ -0,0 +1,43 @@
+//! OpenTelemetry export of transaction-flow spans.
+//!
+//! Bifrost emits `tracing` spans for each stage a transaction goes through
+//! (`session` → `transaction` → `deserialize` → `forward` → `send` per leader).
+//! This module installs a subscriber that ships those spans to an OTLP
+//! collector over gRPC.
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Installs a global `tracing` subscriber exporting spans to `endpoint`.
+///
+/// The returned provider must be kept alive for the lifetime of the process and
+/// shut down on exit so buffered spans are flushed.
+///
+/// # Errors
+///
+/// Returns an error if the exporter cannot be built or a global subscriber is
+/// already installed.
+pub fn init_otlp(endpoint: &str) -> Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .context("Failed to build OTLP span exporter")?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("bifrost").build())
+        .build();
+
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("bifrost")));
+
+    tracing::subscriber::set_global_default(subscriber)
+        .context("Failed to install tracing subscriber")?;
+
+    Ok(provider)
+}
@@ -0,0 +1,298 @@
+//! Tunables for the TPU connection manager.
+
+use crate::constants::DEFAULT_TPU_ADDRESS;
+use crate::tpu_client::ValidatorPins;
+use anyhow::{Context, Result, ensure};
+use quinn::{IdleTimeout, TransportConfig, VarInt};
+use solana_sdk::signature::Keypair;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What to do with a transaction that arrives before the leader tracker has
+/// seen its first slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotReadyPolicy {
+    /// Fail immediately with a "not ready" error.
+    Reject,
+    /// Wait up to the given duration for the tracker to become ready.
+    Wait(Duration),
+}
+
+/// Bounds for proactively connecting to upcoming leaders.
+///
+/// Validators admit a limited number of connections per client identity, and
+/// the allowance grows with the identity's stake. `max_warm_connections`
+/// should be set to that allowance so prewarming never opens connections the
+/// validators would refuse or evict, see [`PrewarmConfig::for_allowance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrewarmConfig {
+    /// Upper bound on pooled connections. Once reached, connections to leaders
+    /// further out are dropped in favor of more imminent ones.
+    pub max_warm_connections: usize,
+    /// Handshakes in flight at once while prewarming.
+    pub concurrency: usize,
+    /// Connect to a leader only once its next window is at most this many
+    /// slots away, instead of as soon as it enters the lookahead. Connections
+    /// to leaders that are further out are the first to be evicted. `None`
+    /// warms every leader in the lookahead at once.
+    pub warm_ahead_slots: Option<u64>,
+    /// Upper bound of the random delay before each handshake of a warming
+    /// pass is started, so a pass doesn't hit all upcoming leaders at once.
+    pub connect_stagger: Duration,
+}
+
+impl PrewarmConfig {
+    /// Sizes the warm pool to a connection `allowance`, warming a quarter of it
+    /// concurrently.
+    pub fn for_allowance(allowance: usize) -> Self {
+        Self {
+            max_warm_connections: allowance,
+            concurrency: (allowance / 4).max(1),
+            warm_ahead_slots: None,
+            connect_stagger: Duration::from_millis(50),
+        }
+    }
+}
+
+impl Default for PrewarmConfig {
+    /// Allowance of an unstaked identity: the next 10 leaders.
+    fn default() -> Self {
+        Self::for_allowance(10)
+    }
+}
+
+/// QUIC transport parameters of connections to validators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuicTransportConfig {
+    /// Time without traffic after which a connection is considered dead.
+    pub max_idle_timeout: Duration,
+    /// Interval of keep-alive packets on otherwise idle connections. Must be
+    /// shorter than `max_idle_timeout`, or connections would time out between
+    /// keep-alives.
+    pub keep_alive_interval: Duration,
+    /// Share bandwidth fairly between the streams of a connection. Off sends
+    /// each stream's data in the order it was written, which gets a single
+    /// transaction out sooner.
+    pub send_fairness: bool,
+    /// Unidirectional streams the validator may have open towards us at once.
+    pub max_concurrent_uni_streams: u32,
+}
+
+impl QuicTransportConfig {
+    /// Checks that the parameters can be applied together.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keep-alive interval isn't shorter than the idle
+    /// timeout.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.keep_alive_interval < self.max_idle_timeout,
+            "QUIC keep-alive interval ({:?}) must be shorter than the idle timeout ({:?})",
+            self.keep_alive_interval,
+            self.max_idle_timeout
+        );
+        Ok(())
+    }
+
+    /// Builds the quinn transport configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parameters are invalid, see
+    /// [`validate`](Self::validate).
+    pub fn transport_config(&self) -> Result<TransportConfig> {
+        self.validate()?;
+        let idle_timeout =
+            IdleTimeout::try_from(self.max_idle_timeout).context("QUIC idle timeout too large")?;
+
+        let mut config = TransportConfig::default();
+        config.max_idle_timeout(Some(idle_timeout));
+        config.keep_alive_interval(Some(self.keep_alive_interval));
+        config.send_fairness(self.send_fairness);
+        config.max_concurrent_uni_streams(VarInt::from_u32(self.max_concurrent_uni_streams));
+        Ok(config)
+    }
+}
+
+impl Default for QuicTransportConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_timeout: Duration::from_secs(5),
+            keep_alive_interval: Duration::from_secs(4),
+            send_fairness: false,
+            // quinn's default
+            max_concurrent_uni_streams: 100,
+        }
+    }
+}
+
+/// Configuration for [`TpuConnectionManager`](super::TpuConnectionManager).
+#[derive(Debug, Clone)]
+pub struct TpuClientConfig {
+    /// Number of parallel handshakes raced against a cold leader, each from its
+    /// own source port. The first to complete is kept and the rest are closed.
+    /// `1` disables racing.
+    pub handshake_race: usize,
+    /// Handshake attempts made against a leader before giving up on it.
+    pub connect_attempts: usize,
+    /// Pause between consecutive handshake attempts.
+    pub connect_retry_delay: Duration,
+    /// Seed for tie-breaking between equal-priority leaders. `None` seeds from
+    /// OS entropy; set it in tests to get a reproducible send order.
+    pub selection_seed: Option<u64>,
+    /// Handling of transactions submitted before the current slot is known.
+    pub not_ready_policy: NotReadyPolicy,
+    /// Refuse to connect to loopback or unspecified leader sockets. Leave off
+    /// when forwarding to a local test validator.
+    pub reject_loopback: bool,
+    /// Age after which a pooled connection is closed and re-established on its
+    /// next use, even if it is still healthy. `None` keeps connections until
+    /// they fail or go idle.
+    pub max_connection_lifetime: Option<Duration>,
+    /// Connections unused for longer than this are removed by
+    /// [`reap_dead_connections`](super::TpuConnectionManager::reap_dead_connections).
+    pub idle_ttl: Duration,
+    /// Number of distinct upcoming leaders each transaction is sent to.
+    ///
+    /// A larger fanout survives skipped slots and unhealthy connections, at the
+    /// cost of amplifying every transaction into that many duplicate sends and
+    /// holding connections to more validators. `None` sends to the current
+    /// leader, plus the next one when close to a leader switch.
+    pub fanout: Option<usize>,
+    /// Hard cap on the distinct leaders a single transaction is sent to,
+    /// applied after `fanout`, to bound the bandwidth spent per transaction.
+    pub max_spray_targets: usize,
+    /// Limits for prewarming connections to upcoming leaders.
+    pub prewarm: PrewarmConfig,
+    /// Time allowed for writing a transaction to a single leader. A leader
+    /// that doesn't drain its stream in time is recorded as timed out and the
+    /// next leader is tried.
+    pub delivery_timeout: Duration,
+    /// Times leaders are resolved and sent to again when none of them
+    /// accepted the transaction and some failed transiently, e.g. after a
+    /// connection blip at a leader switch. Leaders refusing it don't cause a
+    /// retry. Each retry starts from the then-current slot. `0` disables
+    /// retries.
+    pub forward_retries: usize,
+    /// Streams that may be open towards leaders at once, across all
+    /// connections. Each leader a transaction is sent to takes one until the
+    /// leader acknowledged it, so a burst can't exhaust the validators' stream
+    /// limits or stall on flow control. `0` disables the limit.
+    pub max_in_flight_streams: usize,
+    /// Time a transaction waits for stream permits once
+    /// `max_in_flight_streams` is reached before it fails with
+    /// [`GatewayError::Saturated`](crate::error::GatewayError::Saturated).
+    pub stream_permit_timeout: Duration,
+    /// Streams that may be open on a single leader connection at once. Sends
+    /// over the limit wait for an earlier stream on the connection to be
+    /// acknowledged, within the [`delivery_timeout`](Self::delivery_timeout),
+    /// rather than stalling when the validator's stream limit is reached.
+    /// Validators let unstaked clients open 128 streams at once and staked
+    /// ones more. `0` disables the limit.
+    pub max_streams_per_connection: usize,
+    /// Minimum spacing between consecutive sends to the same leader socket.
+    /// Sends inside the window queue until it has passed, and fail as timed
+    /// out if that would take longer than [`delivery_timeout`](Self::delivery_timeout).
+    /// `None` sends immediately.
+    pub min_send_interval: Option<Duration>,
+    /// When less than this is estimated to remain of the current slot, the
+    /// leader of the next slot is sent to first, since the current one is
+    /// unlikely to still include the transaction. `None` always prefers the
+    /// current leader.
+    pub late_slot_threshold: Option<Duration>,
+    /// Leaders that failed more than this share of their recent deliveries
+    /// are left out of the fanout, unless no other leader remains. `None`
+    /// sends to every selected leader regardless of its record.
+    pub max_leader_failure_ratio: Option<f64>,
+    /// Fixed TPU sockets every transaction is sent to, bypassing the leader
+    /// tracker, e.g. a local `solana-test-validator`. Empty follows the
+    /// leader schedule.
+    pub static_tpu_addresses: Vec<SocketAddr>,
+    /// Resolve and log the leaders each transaction would go to without
+    /// opening any stream, e.g. to test a client integration against mainnet.
+    /// Every leader is reported undelivered, with
+    /// [`LeaderDeliveryResult::dry_run`](super::LeaderDeliveryResult::dry_run) set.
+    pub dry_run: bool,
+    /// Time a QUIC connection to a leader gets to be established on the send
+    /// path before the transaction is sent to the leader's legacy UDP TPU
+    /// port instead, as a single datagram. `None` disables the fallback, and
+    /// leaders without an open connection are skipped.
+    ///
+    /// UDP is a last resort for validators or network paths that block QUIC:
+    /// a datagram counts as delivered once the local network stack took it,
+    /// it may be dropped anywhere on the way, and validators shed UDP
+    /// traffic first under load. Only leaders that advertise a UDP TPU
+    /// address fall back, so [`static_tpu_addresses`](Self::static_tpu_addresses)
+    /// never do.
+    pub udp_fallback: Option<Duration>,
+    /// Identity the QUIC client certificate is derived from. `None` uses a
+    /// fresh unstaked identity.
+    ///
+    /// Validators apply stake-weighted QoS: they look up the stake of the
+    /// identity presenting the certificate, and staked identities get more
+    /// connections and streams and are throttled last under load. Pass a
+    /// staked validator's identity keypair, or one of an identity it peers
+    /// with, to benefit. See [`TpuConnectionManager::with_identity`](super::TpuConnectionManager::with_identity).
+    pub identity: Option<Arc<Keypair>>,
+    /// Validators connections may be made to. `None` accepts any validator,
+    /// as solana's own clients do. Connections to others fail their
+    /// handshake, so leaders that aren't pinned are skipped. See
+    /// [`ValidatorPins`] for what to pin.
+    pub pinned_validators: Option<ValidatorPins>,
+    /// QUIC transport parameters of connections to validators.
+    pub transport: QuicTransportConfig,
+}
+
+impl TpuClientConfig {
+    /// Forwards to a `solana-test-validator` on this machine instead of the
+    /// cluster's leaders, at its default TPU QUIC socket
+    /// [`DEFAULT_TPU_ADDRESS`].
+    pub fn local_validator() -> Self {
+        Self::local_validator_at(DEFAULT_TPU_ADDRESS.parse().unwrap())
+    }
+
+    /// Forwards to a single validator's TPU QUIC socket at `addr` instead of
+    /// the cluster's leaders, e.g. a test validator started with a custom
+    /// port range.
+    pub fn local_validator_at(addr: SocketAddr) -> Self {
+        Self {
+            static_tpu_addresses: vec![addr],
+            reject_loopback: false,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for TpuClientConfig {
+    fn default() -> Self {
+        Self {
+            handshake_race: 1,
+            connect_attempts: 3,
+            connect_retry_delay: Duration::from_millis(100),
+            selection_seed: None,
+            not_ready_policy: NotReadyPolicy::Reject,
+            reject_loopback: false,
+            max_connection_lifetime: None,
+            idle_ttl: Duration::from_secs(300),
+            fanout: None,
+            max_spray_targets: 8,
+            prewarm: PrewarmConfig::default(),
+            delivery_timeout: Duration::from_millis(500),
+            forward_retries: 2,
+            max_in_flight_streams: 1024,
+            stream_permit_timeout: Duration::from_millis(100),
+            max_streams_per_connection: 0,
+            min_send_interval: None,
+            late_slot_threshold: None,
+            max_leader_failure_ratio: None,
+            static_tpu_addresses: Vec::new(),
+            dry_run: false,
+            udp_fallback: None,
+            identity: None,
+            pinned_validators: None,
+            transport: QuicTransportConfig::default(),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Outcome and duration of the QUIC handshakes made to each leader socket.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds, in milliseconds, of the handshake duration histogram buckets.
+pub const HANDSHAKE_DURATION_BUCKETS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500];
+
+/// How a single handshake went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HandshakeAttempt {
+    /// A resumption ticket was available, so early data could be sent.
+    pub zero_rtt_attempted: bool,
+    /// The validator accepted the early data.
+    pub zero_rtt_accepted: bool,
+    /// The connection was established.
+    pub established: bool,
+    /// Time from starting the handshake until the connection was usable or
+    /// the attempt failed.
+    pub duration: Duration,
+}
+
+/// Handshake counters of one leader socket, or of all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HandshakeStats {
+    /// Handshakes started, including the losers of a handshake race.
+    pub attempts: u64,
+    /// Handshakes that resumed a session and sent 0-RTT data.
+    pub zero_rtt_attempted: u64,
+    /// Handshakes whose 0-RTT data the validator accepted. Compare with
+    /// `zero_rtt_attempted` to see whether early data pays off.
+    pub zero_rtt_accepted: u64,
+    /// Handshakes that failed.
+    pub failed: u64,
+    /// Handshakes per duration bucket of [`HANDSHAKE_DURATION_BUCKETS_MS`],
+    /// plus one for anything slower.
+    pub duration_buckets: [u64; HANDSHAKE_DURATION_BUCKETS_MS.len() + 1],
+    /// Sum of all handshake durations.
+    pub total_duration: Duration,
+}
+
+impl HandshakeStats {
+    fn record(&mut self, attempt: &HandshakeAttempt) {
+        self.attempts += 1;
+        self.zero_rtt_attempted += attempt.zero_rtt_attempted as u64;
+        self.zero_rtt_accepted += attempt.zero_rtt_accepted as u64;
+        self.failed += !attempt.established as u64;
+
+        let bucket = HANDSHAKE_DURATION_BUCKETS_MS
+            .iter()
+            .position(|le| attempt.duration.as_millis() <= *le as u128)
+            .unwrap_or(HANDSHAKE_DURATION_BUCKETS_MS.len());
+        self.duration_buckets[bucket] += 1;
+        self.total_duration += attempt.duration;
+    }
+
+    /// Adds the counters of `other` to these.
+    pub fn merge(&mut self, other: &HandshakeStats) {
+        self.attempts += other.attempts;
+        self.zero_rtt_attempted += other.zero_rtt_attempted;
+        self.zero_rtt_accepted += other.zero_rtt_accepted;
+        self.failed += other.failed;
+        for (bucket, count) in self.duration_buckets.iter_mut().zip(other.duration_buckets) {
+            *bucket += count;
+        }
+        self.total_duration += other.total_duration;
+    }
+
+    /// Share of 0-RTT attempts the validators accepted, or `None` if none
+    /// were made.
+    pub fn zero_rtt_acceptance(&self) -> Option<f64> {
+        (self.zero_rtt_attempted > 0)
+            .then(|| self.zero_rtt_accepted as f64 / self.zero_rtt_attempted as f64)
+    }
+}
+
+/// Accumulates handshake outcomes per leader socket.
+#[derive(Debug, Default)]
+pub(crate) struct HandshakeLog {
+    sockets: Mutex<HashMap<String, HandshakeStats>>,
+}
+
+impl HandshakeLog {
+    pub(crate) fn record(&self, socket: &str, attempt: HandshakeAttempt) {
+        self.sockets
+            .lock()
+            .unwrap()
+            .entry(socket.to_string())
+            .or_default()
+            .record(&attempt);
+    }
+
+    /// Returns the counters of every socket a handshake was made to.
+    pub(crate) fn by_socket(&self) -> HashMap<String, HandshakeStats> {
+        self.sockets.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(zero_rtt: Option<bool>, established: bool, millis: u64) -> HandshakeAttempt {
+        HandshakeAttempt {
+            zero_rtt_attempted: zero_rtt.is_some(),
+            zero_rtt_accepted: zero_rtt == Some(true),
+            established,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_handshakes_accumulate_per_socket() {
+        let log = HandshakeLog::default();
+        log.record("10.0.0.1:8009", attempt(None, true, 40));
+        log.record("10.0.0.1:8009", attempt(Some(true), true, 3));
+        log.record("10.0.0.1:8009", attempt(Some(false), true, 45));
+        log.record("10.0.0.2:8009", attempt(None, false, 5000));
+
+        let sockets = log.by_socket();
+        let first = &sockets["10.0.0.1:8009"];
+        assert_eq!(first.attempts, 3);
+        assert_eq!(first.zero_rtt_attempted, 2);
+        assert_eq!(first.zero_rtt_accepted, 1);
+        assert_eq!(first.zero_rtt_acceptance(), Some(0.5));
+        assert_eq!(first.failed, 0);
+        assert_eq!(first.duration_buckets[1], 1); // <= 5ms
+        assert_eq!(first.duration_buckets[4], 2); // <= 50ms
+        assert_eq!(first.total_duration, Duration::from_millis(88));
+
+        let second = &sockets["10.0.0.2:8009"];
+        assert_eq!(second.failed, 1);
+        assert_eq!(second.zero_rtt_acceptance(), None);
+        assert_eq!(
+            second.duration_buckets[HANDSHAKE_DURATION_BUCKETS_MS.len()],
+            1
+        );
+
+        let mut total = HandshakeStats::default();
+        total.merge(first);
+        total.merge(second);
+        assert_eq!(total.attempts, 4);
+        assert_eq!(total.duration_buckets.iter().sum::<u64>(), 4);
+    }
+}
@@ -0,0 +1,136 @@
+//! Rolling delivery success rate of each leader socket.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Deliveries kept per socket.
+const HEALTH_WINDOW: usize = 32;
+/// Age after which a delivery no longer counts, so that a skipped leader is
+/// eventually tried again.
+const HEALTH_MAX_AGE: Duration = Duration::from_secs(60);
+/// Deliveries needed before a leader can be judged unhealthy.
+const MIN_SAMPLES: usize = 4;
+
+/// Recent delivery record of a leader socket.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeaderHealth {
+    /// Share of recent deliveries that succeeded, between 0 and 1.
+    pub success_ratio: f64,
+    /// Number of recent deliveries the ratio is computed over.
+    pub samples: usize,
+    /// Error of the most recent failed delivery.
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Record {
+    /// (time, delivered) of the latest deliveries, oldest first
+    outcomes: VecDeque<(Instant, bool)>,
+    last_error: Option<String>,
+}
+
+impl Record {
+    fn expire(&mut self, now: Instant) {
+        while let Some((at, _)) = self.outcomes.front()
+            && now.duration_since(*at) > HEALTH_MAX_AGE
+        {
+            self.outcomes.pop_front();
+        }
+    }
+}
+
+/// Tracks the outcome of the latest deliveries to each leader socket.
+#[derive(Debug, Default)]
+pub struct HealthTracker {
+    records: Mutex<HashMap<String, Record>>,
+}
+
+impl HealthTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the outcome of a delivery to `socket`.
+    pub fn record(&self, socket: &str, outcome: &Result<Duration, String>) {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(socket.to_string()).or_default();
+
+        record.expire(now);
+        if record.outcomes.len() == HEALTH_WINDOW {
+            record.outcomes.pop_front();
+        }
+        record.outcomes.push_back((now, outcome.is_ok()));
+        if let Err(e) = outcome {
+            record.last_error = Some(e.clone());
+        }
+    }
+
+    /// Returns the recent delivery record of `socket`, or `None` if nothing
+    /// was sent to it lately.
+    pub fn health(&self, socket: &str) -> Option<LeaderHealth> {
+        let mut records = self.records.lock().unwrap();
+        let record = records.get_mut(socket)?;
+
+        record.expire(Instant::now());
+        if record.outcomes.is_empty() {
+            records.remove(socket);
+            return None;
+        }
+
+        let samples = record.outcomes.len();
+        let delivered = record.outcomes.iter().filter(|(_, ok)| *ok).count();
+        Some(LeaderHealth {
+            success_ratio: delivered as f64 / samples as f64,
+            samples,
+            last_error: record.last_error.clone(),
+        })
+    }
+
+    /// Returns `true` if more than `max_failure_ratio` of the recent
+    /// deliveries to `socket` failed. Sockets with too few recent deliveries
+    /// to tell are considered healthy.
+    pub fn is_unhealthy(&self, socket: &str, max_failure_ratio: f64) -> bool {
+        self.health(socket).is_some_and(|health| {
+            health.samples >= MIN_SAMPLES && 1.0 - health.success_ratio > max_failure_ratio
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_follows_recent_deliveries() {
+        let tracker = HealthTracker::new();
+        let socket = "10.0.0.1:8009";
+        assert_eq!(tracker.health(socket), None);
+
+        tracker.record(socket, &Ok(Duration::from_millis(2)));
+        tracker.record(socket, &Err("No open connection".to_string()));
+        tracker.record(socket, &Err("Delivery timeout".to_string()));
+
+        let health = tracker.health(socket).unwrap();
+        assert_eq!(health.samples, 3);
+        assert!((health.success_ratio - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(health.last_error.as_deref(), Some("Delivery timeout"));
+
+        // Too few deliveries to judge
+        assert!(!tracker.is_unhealthy(socket, 0.5));
+        tracker.record(socket, &Err("Delivery timeout".to_string()));
+        assert!(tracker.is_unhealthy(socket, 0.5));
+        assert!(!tracker.is_unhealthy(socket, 0.8));
+
+        // Only the latest deliveries count
+        for _ in 0..HEALTH_WINDOW {
+            tracker.record(socket, &Ok(Duration::from_millis(2)));
+        }
+        let health = tracker.health(socket).unwrap();
+        assert_eq!(health.samples, HEALTH_WINDOW);
+        assert_eq!(health.success_ratio, 1.0);
+        assert!(!tracker.is_unhealthy(socket, 0.0));
+    }
+}
@@ -1,32 +1,777 @@
 use anyhow::{Context, Result, anyhow};
 use dashmap::DashMap;
-use log::{debug, info};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use log::{debug, info, warn};
 use quinn::{
-    ClientConfig, Connection as QuinnConnection, Endpoint, IdleTimeout, TransportConfig,
+    ClientConfig, Connection as QuinnConnection, ConnectionError, Endpoint,
     crypto::rustls::QuicClientConfig,
 };
-use std::net::SocketAddr;
+use rustls::client::Resumption;
+use serde::Serialize;
+use solana_sdk::signature::{Keypair, Signer};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::u8;
-use tokio::sync::RwLock;
+use tokio::net::UdpSocket;
+use tokio::sync::{RwLock, Semaphore};
 
-use crate::tpu_client::LeaderTracker;
+use crate::Slot;
+use crate::constants::PACKET_DATA_SIZE;
+use crate::error::GatewayError;
+use crate::tpu_client::handshakes::{HandshakeAttempt, HandshakeLog};
+use crate::tpu_client::pinning::{self, ValidatorPins};
+use crate::tpu_client::tickets::{TicketStore, server_name};
+use crate::tpu_client::{
+    HandshakeStats, HealthTracker, LeaderHealth, LeaderSelector, LeaderTracker, NotReadyPolicy,
+    QuicTransportConfig, SendPacer, TargetSlot, TpuClientConfig,
+};
+use crate::utils::jitter;
+use crate::utils::metrics::{Metrics, MetricsSnapshot};
 
 const ALPN_TPU_PROTOCOL_ID: &[u8] = b"solana-tpu";
-const QUIC_MAX_TIMEOUT: Duration = Duration::from_secs(5);
-const QUIC_KEEP_ALIVE: Duration = Duration::from_secs(4);
+/// Time validators get to acknowledge our close on a graceful shutdown.
+const GRACEFUL_CLOSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Result of delivering a transaction to a single leader.
+///
+/// Delivered means the validator's QUIC stack acknowledged every byte of the
+/// transaction without stopping the stream, not that the validator processed
+/// it, let alone included it in a block. Whether a transaction landed can
+/// only be told by polling its signature status over RPC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderDeliveryResult {
+    pub leader_identity: String,
+    /// TPU socket of the leader.
+    pub socket: String,
+    /// Soonest slot the leader leads among the ones looked at.
+    pub slot: Slot,
+    /// QUIC stream priority the transaction was sent with on the leader's
+    /// connection, higher for nearer slots.
+    pub priority: i32,
+    /// Time taken to write the transaction, or why it could not be sent.
+    pub outcome: Result<Duration, String>,
+    /// The QUIC connection to `socket` failed and the transaction was sent
+    /// to the leader's UDP TPU port instead, see
+    /// [`TpuClientConfig::udp_fallback`].
+    pub udp_fallback: bool,
+    /// The delivery failed in a way another attempt may get past, see
+    /// [`GatewayError::is_transient`]. Only such failures are retried.
+    pub retryable: bool,
+    /// The transaction was only resolved to this leader, in
+    /// [`TpuClientConfig::dry_run`] mode.
+    pub dry_run: bool,
+}
+
+impl LeaderDeliveryResult {
+    /// Returns `true` if this leader acknowledged the transaction.
+    pub fn is_delivered(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// Exhaustive outcome of forwarding a transaction, for embedders that need to
+/// know exactly what happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardOutcome {
+    /// At least one leader received the transaction. `leaders` holds the result
+    /// for every leader attempted, including the ones that failed.
+    Delivered { leaders: Vec<LeaderDeliveryResult> },
+    /// No leader with a known socket was scheduled for the target slot.
+    NoLeaders,
+    /// Every attempted leader failed.
+    AllFailed { errors: Vec<LeaderDeliveryResult> },
+    /// Forwarding did not finish within the allotted time.
+    Timeout,
+}
+
+impl ForwardOutcome {
+    /// Classifies the per-leader results of a completed fan-out.
+    pub fn from_deliveries(deliveries: Vec<LeaderDeliveryResult>) -> Self {
+        if deliveries.is_empty() {
+            ForwardOutcome::NoLeaders
+        } else if deliveries.iter().any(LeaderDeliveryResult::is_delivered) {
+            ForwardOutcome::Delivered {
+                leaders: deliveries,
+            }
+        } else {
+            ForwardOutcome::AllFailed { errors: deliveries }
+        }
+    }
+
+    /// Returns `true` if at least one leader received the transaction.
+    pub fn is_delivered(&self) -> bool {
+        matches!(self, ForwardOutcome::Delivered { .. })
+    }
+}
 
-/// Result of a transaction delivery attempt.
+/// Streams that may be open on a single connection at once, see
+/// [`TpuClientConfig::max_streams_per_connection`].
+#[derive(Debug)]
+struct StreamPool {
+    permits: Semaphore,
+    size: usize,
+}
+
+impl StreamPool {
+    /// Creates a pool of `size` streams, unbounded if `0`.
+    fn new(size: usize) -> Self {
+        let size = if size == 0 {
+            Semaphore::MAX_PERMITS
+        } else {
+            size
+        };
+        Self {
+            permits: Semaphore::new(size),
+            size,
+        }
+    }
+
+    /// Streams currently open on the connection.
+    fn open(&self) -> usize {
+        self.size - self.permits.available_permits()
+    }
+}
+
+impl Default for StreamPool {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// State of a validator's entry in the connection pool, see
+/// [`TpuConnectionManager::get_connection`].
 #[derive(Debug, Clone)]
-pub struct DeliveryConfirmation {
-    pub delivered: bool,
-    pub latency: Duration,
+pub enum ConnState {
+    /// An open connection that can be reused.
+    Live(QuinnConnection),
+    /// A placeholder whose handshake is still in flight.
+    Connecting,
+    /// No entry, or one that was closed or outlived its lifetime. The next
+    /// connect replaces it.
+    Absent,
 }
 
 #[derive(Default, Debug)]
 pub struct Connection {
     conn: Option<QuinnConnection>,
+    last_used: Option<Instant>,
+    /// When the handshake completed, `None` while still connecting
+    established_at: Option<Instant>,
+    /// Shared by every send on the connection
+    streams: Arc<StreamPool>,
+}
+
+impl Connection {
+    /// Returns `true` if the connection has outlived `max_lifetime`.
+    fn is_expired(&self, max_lifetime: Option<Duration>) -> bool {
+        match (self.established_at, max_lifetime) {
+            (Some(established_at), Some(max_lifetime)) => established_at.elapsed() >= max_lifetime,
+            _ => false,
+        }
+    }
+}
+
+/// Lifecycle state of a pooled connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    /// A handshake is in flight.
+    Connecting,
+    /// The connection is open and can be reused.
+    Active,
+    /// The connection was closed and is waiting to be replaced.
+    Closed,
+}
+
+/// Point-in-time view of a single pooled connection, for diagnostics.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionSnapshot {
+    pub socket: String,
+    pub status: ConnectionStatus,
+    /// Milliseconds since the connection was last handed out, if ever.
+    pub last_used_ms_ago: Option<u64>,
+    /// Current RTT estimate in milliseconds, if the connection is established.
+    pub rtt_ms: Option<u64>,
+    /// Milliseconds since the handshake completed, if it has.
+    pub age_ms: Option<u64>,
+    /// Whether a session ticket is cached for the leader, so a new connection
+    /// to it could send 0-RTT data instead of waiting for a full handshake.
+    pub zero_rtt_capable: bool,
+    /// Streams currently open on the connection.
+    pub open_streams: usize,
+}
+
+impl ConnectionSnapshot {
+    fn new(socket: &str, conn: &Connection, tickets: &TicketStore) -> Self {
+        let status = match &conn.conn {
+            None => ConnectionStatus::Connecting,
+            Some(c) if c.close_reason().is_none() => ConnectionStatus::Active,
+            Some(_) => ConnectionStatus::Closed,
+        };
+
+        Self {
+            socket: socket.to_string(),
+            status,
+            last_used_ms_ago: conn.last_used.map(|t| t.elapsed().as_millis() as u64),
+            rtt_ms: conn.conn.as_ref().map(|c| c.rtt().as_millis() as u64),
+            age_ms: conn.established_at.map(|t| t.elapsed().as_millis() as u64),
+            zero_rtt_capable: tickets.has_ticket(socket),
+            open_streams: conn.streams.open(),
+        }
+    }
+}
+
+/// Drives all `attempts` concurrently and returns the first one to succeed.
+///
+/// Attempts that complete after the winner are passed to `close_loser` in the
+/// background so they don't linger. Fails only if every attempt fails.
+async fn race_handshakes<T, F>(
+    attempts: Vec<F>,
+    close_loser: impl Fn(T) + Send + 'static,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T>> + Send + 'static,
+{
+    let mut pending: FuturesUnordered<F> = attempts.into_iter().collect();
+    let mut last_err = None;
+
+    while let Some(result) = pending.next().await {
+        match result {
+            Ok(winner) => {
+                if !pending.is_empty() {
+                    tokio::spawn(async move {
+                        while let Some(result) = pending.next().await {
+                            if let Ok(loser) = result {
+                                close_loser(loser);
+                            }
+                        }
+                    });
+                }
+                return Ok(winner);
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| anyhow!("No handshake attempts")))
+}
+
+/// Calls `connect` up to `attempts` times, sleeping `delay` between failures.
+/// Returns the last error if every attempt fails, or right away if the
+/// validator's certificate was refused, see [`is_tls_refusal`].
+async fn connect_with_retry<T, F, Fut>(
+    attempts: usize,
+    delay: Duration,
+    mut connect: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let attempts = attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        match connect().await {
+            Ok(conn) => return Ok(conn),
+            Err(e) if attempt < attempts && !is_tls_refusal(&e) => {
+                debug!(
+                    "Connect attempt {}/{} failed: {}, retrying in {:?}",
+                    attempt, attempts, e, delay
+                );
+                attempt += 1;
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                return Err(e).context(format!("Failed to connect after {} attempt(s)", attempts));
+            }
+        }
+    }
+}
+
+/// Returns `true` if a handshake failed on a TLS alert, e.g. for a validator
+/// that isn't pinned, which another attempt wouldn't change.
+fn is_tls_refusal(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<ConnectionError>(),
+            Some(ConnectionError::TransportError(e)) if (0x100..0x200).contains(&u64::from(e.code))
+        )
+    })
+}
+
+/// Refuses loopback and unspecified destinations when `reject_loopback` is set,
+/// so a misconfigured leader socket can't make Bifrost forward to itself.
+fn check_destination(addr: &SocketAddr, reject_loopback: bool) -> Result<()> {
+    let ip = addr.ip().to_canonical();
+    if reject_loopback && (ip.is_loopback() || ip.is_unspecified()) {
+        return Err(anyhow!("Refusing to forward to loopback address {}", addr));
+    }
+    Ok(())
+}
+
+/// Applies `policy` if `leader_tracker` has not seen a slot yet.
+async fn wait_for_ready(
+    leader_tracker: &LeaderTracker,
+    policy: NotReadyPolicy,
+) -> Result<(), GatewayError> {
+    if leader_tracker.is_ready() {
+        return Ok(());
+    }
+
+    match policy {
+        NotReadyPolicy::Reject => Err(GatewayError::NotReady(
+            "current slot is unknown".to_string(),
+        )),
+        NotReadyPolicy::Wait(max_wait) => {
+            debug!("Waiting up to {:?} for leader tracker", max_wait);
+            let mut ready = leader_tracker.subscribe_ready();
+            match tokio::time::timeout(max_wait, ready.wait_for(|ready| *ready)).await {
+                Ok(Ok(_)) => Ok(()),
+                _ => Err(GatewayError::NotReady(format!(
+                    "no slot after waiting {:?}",
+                    max_wait
+                ))),
+            }
+        }
+    }
+}
+
+/// Removes the entry for `socket` if it has outlived `max_lifetime`, closing it
+/// gracefully so the next access re-establishes it. Returns `true` if it was removed.
+fn evict_expired(
+    connections: &DashMap<String, Connection>,
+    socket: &str,
+    max_lifetime: Option<Duration>,
+) -> bool {
+    match connections.remove_if(socket, |_, conn| conn.is_expired(max_lifetime)) {
+        Some((_, expired)) => {
+            debug!("Renewing connection to {} after max lifetime", socket);
+            if let Some(conn) = expired.conn {
+                conn.close(0u32.into(), b"max lifetime reached");
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stream priority of a transaction sent to a leader for `slot`.
+///
+/// QUIC only orders streams within a connection, so the priority has to be
+/// comparable between the transactions sharing a leader's connection, rather
+/// than between the leaders of one transaction. The nearer the slot, the
+/// higher the priority, so a send for the leader's imminent slot isn't delayed
+/// behind sends ahead of its later windows.
+fn stream_priority(slot: Slot, current_slot: Slot) -> i32 {
+    i32::try_from(slot.saturating_sub(current_slot)).map_or(i32::MIN, |distance| -distance)
+}
+
+/// Rejects transactions validators would drop for not fitting in a packet.
+fn check_packet_size(tx_data: &[u8]) -> Result<(), GatewayError> {
+    if tx_data.len() > PACKET_DATA_SIZE {
+        return Err(GatewayError::InvalidTransaction(format!(
+            "{} bytes exceeds the {} byte packet limit",
+            tx_data.len(),
+            PACKET_DATA_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// Awaits a single leader's `send`, giving up once `timeout` elapses so a
+/// validator that never drains its stream can't stall the remaining leaders.
+async fn send_within<F>(timeout: Duration, send: F) -> Result<(), GatewayError>
+where
+    F: Future<Output = Result<(), GatewayError>>,
+{
+    tokio::time::timeout(timeout, send)
+        .await
+        .unwrap_or(Err(GatewayError::DeliveryTimeout))
+}
+
+/// Logs what would be sent to `leaders` and reports each as not delivered.
+fn dry_run(tx_data: &[u8], leaders: Vec<(String, String, Slot, i32)>) -> Vec<LeaderDeliveryResult> {
+    leaders
+        .into_iter()
+        .map(|(leader_identity, socket, slot, priority)| {
+            info!(
+                "Dry run: would send {} bytes to {} at {} for slot {} (priority {})",
+                tx_data.len(),
+                leader_identity,
+                socket,
+                slot,
+                priority
+            );
+            LeaderDeliveryResult {
+                leader_identity,
+                socket,
+                slot,
+                priority,
+                outcome: Err("dry run".to_string()),
+                udp_fallback: false,
+                retryable: false,
+                dry_run: true,
+            }
+        })
+        .collect()
+}
+
+/// Resolves the leaders to send to from the slot selected by `target`.
+///
+/// With a `fanout`, looks ahead `fanout` leader windows of 4 slots and keeps the
+/// first `fanout` distinct leaders. Leaders are already deduplicated by the
+/// tracker, so a validator leading consecutive windows is only sent to once.
+///
+/// Never returns more than `max_targets` leaders, whatever the fanout.
+async fn select_leaders(
+    leader_tracker: &LeaderTracker,
+    target: TargetSlot,
+    fanout: Option<usize>,
+    max_targets: usize,
+) -> Vec<(String, String, u64)> {
+    let mut leaders = match fanout {
+        Some(fanout) => {
+            let mut leaders = leader_tracker
+                .get_future_leaders_for(target, 0, fanout as u64 * 4)
+                .await;
+            leaders.truncate(fanout);
+            leaders
+        }
+        None => leader_tracker.get_leaders_for(target).await,
+    };
+    leaders.truncate(max_targets);
+    leaders
+}
+
+/// Moves the leader of the slot after `target` to the front of `leaders` when
+/// less than `threshold` remains of the current slot.
+///
+/// Leaves `leaders` untouched while there is time left, when slot timing is
+/// unknown, or when the next slot has the same leader.
+async fn prefer_next_leader(
+    leader_tracker: &LeaderTracker,
+    target: TargetSlot,
+    mut leaders: Vec<(String, String, u64)>,
+    threshold: Duration,
+) -> Vec<(String, String, u64)> {
+    let late = leader_tracker
+        .slot_time_remaining()
+        .await
+        .is_some_and(|remaining| remaining < threshold);
+    if !late {
+        return leaders;
+    }
+
+    let next = leader_tracker.get_future_leaders_for(target, 1, 2).await;
+    if let Some((next_identity, _, _)) = next.first()
+        && let Some(index) = leaders.iter().position(|(id, _, _)| id == next_identity)
+        && index > 0
+    {
+        debug!(
+            "Late in slot, sending to next leader {} first",
+            next_identity
+        );
+        let next = leaders.remove(index);
+        leaders.insert(0, next);
+    }
+
+    leaders
+}
+
+/// Binds the socket transactions are sent from over UDP, see
+/// [`TpuClientConfig::udp_fallback`]. Dual-stack where IPv6 is available,
+/// like the QUIC endpoints.
+fn udp_fallback_socket() -> Result<UdpSocket> {
+    let socket = std::net::UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .or_else(|_| std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)))
+        .context("Failed to bind UDP fallback socket")?;
+    socket
+        .set_nonblocking(true)
+        .context("Failed to configure UDP fallback socket")?;
+    UdpSocket::from_std(socket).context("Failed to register UDP fallback socket")
+}
+
+/// `target` as `socket` can send to it: dual-stack IPv6 sockets reach IPv4
+/// hosts on their IPv4-mapped address.
+fn udp_target(socket: &UdpSocket, target: SocketAddr) -> SocketAddr {
+    match (socket.local_addr(), target) {
+        (Ok(SocketAddr::V6(_)), SocketAddr::V4(v4)) => {
+            SocketAddr::new(v4.ip().to_ipv6_mapped().into(), v4.port())
+        }
+        _ => target,
+    }
+}
+
+/// Identity reported for the fixed sockets of
+/// [`TpuClientConfig::static_tpu_addresses`], which have no known leader.
+const STATIC_LEADER_IDENTITY: &str = "static";
+
+/// Binds a client endpoint that reaches both IPv4 and IPv6 validators, or
+/// only IPv4 ones on hosts without IPv6.
+fn client_endpoint() -> Result<Endpoint> {
+    match Endpoint::client((Ipv6Addr::UNSPECIFIED, 0).into()) {
+        Ok(endpoint) => Ok(endpoint),
+        Err(e) => {
+            warn!("IPv6 unavailable, reaching IPv4 validators only: {}", e);
+            Endpoint::client((Ipv4Addr::UNSPECIFIED, 0).into())
+                .context("Failed to bind QUIC client endpoint")
+        }
+    }
+}
+
+/// Fan-out candidates for fixed TPU sockets, in the form leaders are selected
+/// in: (identity, socket, slot).
+fn static_leaders(addresses: &[SocketAddr]) -> Vec<(String, String, u64)> {
+    addresses
+        .iter()
+        .map(|addr| (STATIC_LEADER_IDENTITY.to_string(), addr.to_string(), 0))
+        .collect()
+}
+
+/// Leaves out leaders `pins` would refuse, so neither a handshake nor a UDP
+/// datagram goes to them.
+fn skip_unpinned(
+    pins: &ValidatorPins,
+    leaders: Vec<(String, String, u64)>,
+) -> Vec<(String, String, u64)> {
+    leaders
+        .into_iter()
+        .filter(|(identity, socket, _)| {
+            let allowed = pins.may_allow(identity);
+            if !allowed {
+                debug!("Skipping unpinned leader {} at {}", identity, socket);
+            }
+            allowed
+        })
+        .collect()
+}
+
+/// Leaves out leaders that failed more than `max_failure_ratio` of their
+/// recent deliveries. If that would leave none, the first (current) leader is
+/// kept regardless.
+fn skip_unhealthy(
+    health: &HealthTracker,
+    leaders: Vec<(String, String, u64)>,
+    max_failure_ratio: f64,
+) -> Vec<(String, String, u64)> {
+    let Some(first) = leaders.first().cloned() else {
+        return leaders;
+    };
+
+    let healthy: Vec<_> = leaders
+        .into_iter()
+        .filter(|(identity, socket, _)| {
+            let unhealthy = health.is_unhealthy(socket, max_failure_ratio);
+            if unhealthy {
+                debug!("Skipping unhealthy leader {} at {}", identity, socket);
+            }
+            !unhealthy
+        })
+        .collect();
+
+    if healthy.is_empty() {
+        vec![first]
+    } else {
+        healthy
+    }
+}
+
+/// Connections to open and drop to warm the pool for upcoming leaders.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PrewarmPlan {
+    /// (identity, socket) of leaders to connect to, most imminent first
+    connect: Vec<(String, String)>,
+    /// Pooled sockets to close to make room
+    evict: Vec<String>,
+}
+
+/// Returns the leaders of the next `lookahead_slots` slots, each once, with the
+/// number of slots until its next window starts. Leaders without a known
+/// socket are skipped.
+async fn upcoming_leaders(
+    tracker: &LeaderTracker,
+    lookahead_slots: u64,
+) -> Vec<(String, String, u64)> {
+    let schedule = tracker.upcoming_leader_schedule(lookahead_slots).await;
+    // The schedule starts at the current slot
+    let Some(current) = schedule.first().map(|(_, slots, _)| *slots.start()) else {
+        return vec![];
+    };
+
+    let mut seen = HashSet::new();
+    schedule
+        .into_iter()
+        .filter(|(leader, _, _)| seen.insert(*leader))
+        .filter_map(|(leader, slots, socket)| {
+            let socket = socket?;
+            Some((
+                leader.to_string(),
+                socket.to_string(),
+                slots.start() - current,
+            ))
+        })
+        .collect()
+}
+
+/// Plans which of the `upcoming` leaders to warm without letting the pool grow
+/// past `max_warm`. Each leader comes with the slots until its next window,
+/// most imminent first.
+///
+/// Only the first `max_warm` distinct leaders are considered, and with
+/// `warm_ahead` set only those whose window is at most that many slots away.
+/// Pooled sockets that aren't among them are evicted only as far as needed to
+/// fit the new connections: leaders no longer upcoming first, then the ones
+/// furthest out.
+fn plan_prewarm(
+    upcoming: Vec<(String, String, u64)>,
+    pool: &DashMap<String, Connection>,
+    max_warm: usize,
+    warm_ahead: Option<u64>,
+) -> PrewarmPlan {
+    let mut wanted = HashSet::new();
+    let mut distances = HashMap::new();
+    let mut connect = Vec::new();
+
+    for (identity, socket, distance) in upcoming {
+        distances.entry(socket.clone()).or_insert(distance);
+        if wanted.len() == max_warm || warm_ahead.is_some_and(|ahead| distance > ahead) {
+            continue;
+        }
+        if !wanted.insert(socket.clone()) {
+            continue;
+        }
+        if !pool.contains_key(&socket) {
+            connect.push((identity, socket));
+        }
+    }
+
+    let overflow = (pool.len() + connect.len()).saturating_sub(max_warm);
+    let mut candidates: Vec<String> = pool
+        .iter()
+        .map(|entry| entry.key().clone())
+        .filter(|socket| !wanted.contains(socket))
+        .collect();
+    candidates.sort_by_key(|socket| {
+        std::cmp::Reverse(distances.get(socket).copied().unwrap_or(u64::MAX))
+    });
+    candidates.truncate(overflow);
+
+    PrewarmPlan {
+        connect,
+        evict: candidates,
+    }
+}
+
+/// Removes closed connections and connections idle for longer than `idle_ttl`,
+/// returning how many were removed. Entries still connecting are kept.
+fn reap(connections: &DashMap<String, Connection>, idle_ttl: Duration) -> usize {
+    let before = connections.len();
+
+    connections.retain(|socket, entry| {
+        let Some(conn) = &entry.conn else {
+            return true;
+        };
+
+        if conn.close_reason().is_some() {
+            debug!("Reaping closed connection to {}", socket);
+            return false;
+        }
+
+        let idle = entry.last_used.is_some_and(|t| t.elapsed() > idle_ttl);
+        if idle {
+            debug!("Reaping idle connection to {}", socket);
+            conn.close(0u32.into(), b"idle");
+        }
+        !idle
+    });
+
+    before - connections.len()
+}
+
+/// Removes and closes the pool entries for `sockets`, returning how many were
+/// removed. Handshakes in flight are dropped as well, since the address they
+/// target no longer belongs to the leader they were started for.
+fn invalidate<'a>(
+    connections: &DashMap<String, Connection>,
+    sockets: impl IntoIterator<Item = &'a str>,
+) -> usize {
+    let mut invalidated = 0;
+
+    for socket in sockets {
+        if let Some((_, entry)) = connections.remove(socket) {
+            debug!("Invalidating connection to {}", socket);
+            if let Some(conn) = entry.conn {
+                conn.close(0u32.into(), b"leader socket changed");
+            }
+            invalidated += 1;
+        }
+    }
+
+    invalidated
+}
+
+/// Aggregate health of the connection pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Entries in the pool, whatever their state.
+    pub total: usize,
+    /// Established connections that have not been closed.
+    pub live: usize,
+    /// Entries whose handshake is still in flight.
+    pub pending: usize,
+    /// When each validator's connection was last handed out, keyed by TPU socket.
+    pub last_used: HashMap<String, Instant>,
+    /// Handshakes made since the manager was created, across all validators.
+    pub handshakes: HandshakeStats,
+    /// Handshakes made since the manager was created, keyed by TPU socket.
+    /// Kept after a socket leaves the pool.
+    pub handshakes_by_socket: HashMap<String, HandshakeStats>,
+    /// Streams currently being written to leaders, see
+    /// [`TpuClientConfig::max_in_flight_streams`].
+    pub in_flight_streams: usize,
+    /// Streams currently open on pooled connections, see
+    /// [`TpuClientConfig::max_streams_per_connection`].
+    pub open_streams: usize,
+}
+
+fn pool_stats(connections: &DashMap<String, Connection>) -> PoolStats {
+    let mut stats = PoolStats {
+        total: connections.len(),
+        ..PoolStats::default()
+    };
+
+    for entry in connections.iter() {
+        match &entry.conn {
+            None => stats.pending += 1,
+            Some(conn) if conn.close_reason().is_none() => stats.live += 1,
+            Some(_) => {}
+        }
+        if let Some(last_used) = entry.last_used {
+            stats.last_used.insert(entry.key().clone(), last_used);
+        }
+        stats.open_streams += entry.streams.open();
+    }
+
+    stats
+}
+
+/// Builds a snapshot of every entry in the pool, sorted by socket address.
+fn snapshot_pool(
+    connections: &DashMap<String, Connection>,
+    tickets: &TicketStore,
+) -> Vec<ConnectionSnapshot> {
+    let mut snapshot: Vec<ConnectionSnapshot> = connections
+        .iter()
+        .map(|entry| ConnectionSnapshot::new(entry.key(), entry.value(), tickets))
+        .collect();
+    snapshot.sort_by(|a, b| a.socket.cmp(&b.socket));
+    snapshot
 }
 
 /// Manages QUIC connections to Solana TPU endpoints.
@@ -34,23 +779,117 @@ pub struct Connection {
 /// Maintains a connection pool and handles automatic reconnection.
 #[derive(Debug)]
 pub struct TpuConnectionManager {
-    endpoint: Endpoint,
+    endpoints: Vec<Endpoint>,
     connections: Arc<RwLock<DashMap<String, Connection>>>,
     leader_tracker: Arc<LeaderTracker>,
+    selector: LeaderSelector,
+    pacer: Option<SendPacer>,
+    tickets: Arc<TicketStore>,
+    health: HealthTracker,
+    handshakes: Arc<HandshakeLog>,
+    metrics: Arc<Metrics>,
+    /// Permits for streams towards leaders, see
+    /// [`TpuClientConfig::max_in_flight_streams`]
+    stream_permits: Semaphore,
+    /// Bound only with [`TpuClientConfig::udp_fallback`]
+    udp_socket: Option<UdpSocket>,
+    config: TpuClientConfig,
 }
 
 impl TpuConnectionManager {
-    /// Creates a new TPU connection manager.
+    /// Creates a new TPU connection manager with the default configuration.
     ///
     /// # Errors
     ///
     /// Returns an error if the QUIC endpoint cannot be initialized.
     pub fn new(leader_tracker: Arc<LeaderTracker>) -> Result<Self> {
+        Self::with_config(leader_tracker, TpuClientConfig::default())
+    }
+
+    /// Creates a new TPU connection manager that authenticates to validators
+    /// as `identity`, so its traffic is prioritized by the identity's stake.
+    /// See [`TpuClientConfig::identity`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the QUIC endpoint cannot be initialized.
+    pub fn with_identity(leader_tracker: Arc<LeaderTracker>, identity: Keypair) -> Result<Self> {
+        let config = TpuClientConfig {
+            identity: Some(Arc::new(identity)),
+            ..TpuClientConfig::default()
+        };
+        Self::with_config(leader_tracker, config)
+    }
+
+    /// Creates a new TPU connection manager.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the QUIC endpoints cannot be initialized or
+    /// [`TpuClientConfig::transport`] is invalid.
+    pub fn with_config(
+        leader_tracker: Arc<LeaderTracker>,
+        config: TpuClientConfig,
+    ) -> Result<Self> {
         info!("Creating TPU connection manager");
 
-        let client_certificate = solana_tls_utils::QuicClientCertificate::new(None);
+        let tickets = Arc::new(TicketStore::new());
+        let client_config = Self::client_config(
+            config.identity.as_deref(),
+            config.pinned_validators.as_ref(),
+            &config.transport,
+            tickets.clone(),
+        )?;
+        match &config.identity {
+            Some(identity) => info!("Using client identity {}", identity.pubkey()),
+            None => info!("Using an ephemeral unstaked client identity"),
+        }
+
+        // One endpoint (and therefore one source port) per racing handshake
+        let endpoints = (0..config.handshake_race.max(1))
+            .map(|_| -> Result<Endpoint> {
+                let mut endpoint = client_endpoint()?;
+                endpoint.set_default_client_config(client_config.clone());
+                Ok(endpoint)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let udp_socket = match config.udp_fallback {
+            Some(_) => Some(udp_fallback_socket()?),
+            None => None,
+        };
+
+        info!("TPU connection manager created");
+
+        Ok(Self {
+            endpoints,
+            connections: Arc::new(RwLock::new(DashMap::new())),
+            leader_tracker,
+            selector: LeaderSelector::new(config.selection_seed),
+            pacer: config.min_send_interval.map(SendPacer::new),
+            tickets,
+            health: HealthTracker::new(),
+            handshakes: Arc::new(HandshakeLog::default()),
+            metrics: Arc::new(Metrics::new()),
+            stream_permits: Semaphore::new(config.max_in_flight_streams),
+            udp_socket,
+            config,
+        })
+    }
+
+    fn client_config(
+        identity: Option<&Keypair>,
+        pins: Option<&ValidatorPins>,
+        transport: &QuicTransportConfig,
+        tickets: Arc<TicketStore>,
+    ) -> Result<ClientConfig> {
+        let client_certificate = solana_tls_utils::QuicClientCertificate::new(identity);
 
-        let mut crypto = solana_tls_utils::tls_client_config_builder()
+        let builder = match pins {
+            Some(pins) => pinning::tls_client_config_builder(pins)?,
+            None => solana_tls_utils::tls_client_config_builder(),
+        };
+        let mut crypto = builder
             .with_client_auth_cert(
                 vec![client_certificate.certificate.clone()],
                 client_certificate.key.clone_key(),
@@ -58,30 +897,17 @@ impl TpuConnectionManager {
             .expect("Failed to set QUIC client certificates");
 
         crypto.enable_early_data = true;
+        crypto.resumption = Resumption::store(tickets);
         crypto.alpn_protocols = vec![ALPN_TPU_PROTOCOL_ID.to_vec()];
 
-        let transport_config = {
-            let mut res = TransportConfig::default();
-            let timeout = IdleTimeout::try_from(QUIC_MAX_TIMEOUT).unwrap();
-            res.max_idle_timeout(Some(timeout));
-            res.keep_alive_interval(Some(QUIC_KEEP_ALIVE));
-            res.send_fairness(false);
-            res
-        };
+        let transport_config = transport
+            .transport_config()
+            .context("Invalid QUIC transport config")?;
 
         let mut config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto).unwrap()));
         config.transport_config(Arc::new(transport_config));
 
-        let mut endpoint = Endpoint::client("0.0.0.0:0".parse()?)?;
-        endpoint.set_default_client_config(config);
-
-        info!("TPU connection manager created");
-
-        Ok(Self {
-            endpoint,
-            connections: Arc::new(RwLock::new(DashMap::new())),
-            leader_tracker,
-        })
+        Ok(config)
     }
 
     /// Sends a Solana transaction to the specified validator's TPU.
@@ -91,132 +917,686 @@ impl TpuConnectionManager {
     /// * `validator` - TPU address (e.g., "127.0.0.1:8001")
     /// * `transaction` - The transaction to send
     ///
+    /// Returns one [`LeaderDeliveryResult`] per leader the transaction was
+    /// attempted on. It was delivered if at least one of them succeeded.
+    ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - Serialization fails
-    /// - Connection fails
-    /// - Stream creation fails
-    /// - Data transmission fails
-    pub async fn send_transaction(&self, tx_data: &[u8]) -> Result<DeliveryConfirmation> {
-        debug!("Packet preview: {:02x?}", &tx_data[..tx_data.len().min(32)]);
+    /// Returns:
+    /// - [`GatewayError::NotReady`] if the leader tracker is not ready and the
+    ///   policy rejects or times out
+    /// - [`GatewayError::StaleTracker`] if the current slot stopped advancing
+    /// - [`GatewayError::NoLeaders`] if no leader is known for the target slot
+    /// - [`GatewayError::InvalidTransaction`] if the transaction doesn't fit in
+    ///   a packet
+    pub async fn send_transaction(
+        &self,
+        tx_data: &[u8],
+    ) -> Result<Vec<LeaderDeliveryResult>, GatewayError> {
+        self.send_transaction_targeting(tx_data, TargetSlot::default())
+            .await
+    }
 
+    /// Sends a transaction to the leaders resolved from the slot selected by `target`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`send_transaction`](Self::send_transaction).
+    pub async fn send_transaction_targeting(
+        &self,
+        tx_data: &[u8],
+        target: TargetSlot,
+    ) -> Result<Vec<LeaderDeliveryResult>, GatewayError> {
         let start = Instant::now();
-        let leaders = self.leader_tracker.get_leaders().await;
-        let mut tx_sent = false;
-        println!("leaders: {:#?}", leaders);
-
-        for (leader_identity, leader_socket, _curr_slot) in leaders {
-            if let Ok(Some(conn)) = self.get_connection(&leader_socket).await {
-                info!(
-                    "Sending {} bytes to {} at: {}",
-                    tx_data.len(),
-                    leader_identity,
-                    leader_socket
-                );
+        let result = self.forward(tx_data, target).await;
 
-                let mut send_stream = conn.open_uni().await.context("Failed to open uni stream")?;
+        match &result {
+            _ if self.config.dry_run => {}
+            Ok(results) if results.iter().any(LeaderDeliveryResult::is_delivered) => {
+                self.metrics.record_forwarded(start.elapsed())
+            }
+            _ => self.metrics.record_failed(),
+        }
 
-                send_stream
-                    .write_all(&tx_data)
-                    .await
-                    .context("Failed to write transaction data")?;
+        match result {
+            Ok(results) if results.is_empty() => Err(GatewayError::NoLeaders),
+            result => result,
+        }
+    }
 
-                send_stream.finish().context("Failed to finish stream")?;
+    /// Like [`send_transaction_targeting`](Self::send_transaction_targeting),
+    /// but reports the result as a [`ForwardOutcome`] and gives up after `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the transaction doesn't fit in a packet, or if
+    /// the leader tracker is not ready and the not-ready policy rejects the
+    /// transaction.
+    pub async fn send_transaction_outcome(
+        &self,
+        tx_data: &[u8],
+        target: TargetSlot,
+        timeout: Duration,
+    ) -> Result<ForwardOutcome, GatewayError> {
+        let start = Instant::now();
 
-                tx_sent = true;
-            } else {
-                info!(
-                    "Connection failed for {} at: {}",
-                    leader_identity, leader_socket
-                );
-            };
-        }
+        let outcome = match tokio::time::timeout(timeout, self.forward(tx_data, target)).await {
+            Ok(deliveries) => ForwardOutcome::from_deliveries(deliveries?),
+            Err(_) => ForwardOutcome::Timeout,
+        };
 
-        if !tx_sent {
-            return Err(anyhow!("Failed sending TX"));
+        if outcome.is_delivered() {
+            self.metrics.record_forwarded(start.elapsed());
+        } else {
+            self.metrics.record_failed();
         }
 
-        Ok(DeliveryConfirmation {
-            delivered: true,
-            latency: start.elapsed(),
-        })
+        Ok(outcome)
     }
 
-    pub async fn get_connection(&self, validator: &str) -> Result<Option<QuinnConnection>> {
-        let conns = self.connections.read().await;
-
-        if let Some(conn) = conns.get(validator) {
-            // If we are already connected check connection is active
-            match &conn.conn {
-                Some(conn) => {
-                    if conn.close_reason().is_none() {
-                        debug!("Reusing connection to {}", validator);
-                        return Ok(Some(conn.clone()));
-                    }
-                }
-                None => return Err(anyhow!("No connection is open")),
+    /// Resolves the leaders and sends to them, again up to
+    /// [`TpuClientConfig::forward_retries`] times while none accepted the
+    /// transaction and some failed transiently. Leaders that refused it are
+    /// not retried. Returns the deliveries of the last attempt.
+    async fn forward(
+        &self,
+        tx_data: &[u8],
+        target: TargetSlot,
+    ) -> Result<Vec<LeaderDeliveryResult>, GatewayError> {
+        let mut retries = 0;
+        loop {
+            let results = self.forward_once(tx_data, target).await?;
+            let retry = !results.iter().any(LeaderDeliveryResult::is_delivered)
+                && results.iter().any(|result| result.retryable);
+            if !retry || retries == self.config.forward_retries {
+                return Ok(results);
             }
-        }
 
-        return Ok(None);
+            retries += 1;
+            info!(
+                "No leader accepted the transaction, retrying ({}/{})",
+                retries, self.config.forward_retries
+            );
+        }
     }
 
-    /// Gets an existing connection or creates a new one to the validator.
-    pub async fn get_or_create_connection(&self, validator: &str) -> Result<QuinnConnection> {
-        match self.get_connection(validator).await {
-            // we have active connection
-            Ok(Some(conn)) => return Ok(conn),
-            // We have no connection, try to connect
-            Ok(None) => (),
-            // We are are still trying to connect
-            Err(_) => return Err(anyhow!("Already connecting")),
+    async fn forward_once(
+        &self,
+        tx_data: &[u8],
+        target: TargetSlot,
+    ) -> Result<Vec<LeaderDeliveryResult>, GatewayError> {
+        debug!("Packet preview: {:02x?}", &tx_data[..tx_data.len().min(32)]);
+
+        check_packet_size(tx_data)?;
+
+        let mut leaders = if self.config.static_tpu_addresses.is_empty() {
+            wait_for_ready(&self.leader_tracker, self.config.not_ready_policy).await?;
+            if let Some(age) = self.leader_tracker.staleness().await {
+                warn!("Refusing to forward, no new slot for {:?}", age);
+                return Err(GatewayError::StaleTracker { age });
+            }
+
+            let leaders = select_leaders(
+                &self.leader_tracker,
+                target,
+                self.config.fanout,
+                self.config.max_spray_targets,
+            )
+            .await;
+            match &self.config.pinned_validators {
+                Some(pins) => skip_unpinned(pins, leaders),
+                None => leaders,
+            }
+        } else {
+            static_leaders(&self.config.static_tpu_addresses)
+        };
+        if let Some(max_failure_ratio) = self.config.max_leader_failure_ratio {
+            leaders = skip_unhealthy(&self.health, leaders, max_failure_ratio);
         }
 
-        let conns = self.connections.write().await;
-        if let Some(conn) = conns.get(validator)
-            && let None = conn.conn
+        // Ties are shuffled before the late slot preference, so it isn't undone
+        leaders = self.selector.order_by(leaders, |(_, _, slot)| *slot);
+        if self.config.static_tpu_addresses.is_empty()
+            && let Some(threshold) = self.config.late_slot_threshold
         {
-            return Err(anyhow!("Already connecting"));
+            leaders = prefer_next_leader(&self.leader_tracker, target, leaders, threshold).await;
         }
-        conns.insert(validator.to_string(), Connection::default());
-        drop(conns);
 
-        debug!("Creating new connection to {}", validator);
-        let addr: SocketAddr = validator.parse().context("Invalid validator address")?;
+        // Priorities order the streams sharing a leader's connection
+        let current_slot = self
+            .leader_tracker
+            .slots_tracker
+            .read()
+            .await
+            .current_slot();
+        let leaders = leaders
+            .into_iter()
+            .map(|(identity, socket, slot)| {
+                let priority = stream_priority(slot, current_slot);
+                (identity, socket, slot, priority)
+            })
+            .collect::<Vec<_>>();
+        debug!("Leaders: {:?}", leaders);
 
-        let connection = match self.endpoint.connect(addr, "solana")?.into_0rtt() {
-            Ok((conn, rtt_accepted)) => {
-                debug!("Waiting for 0-RTT for: {}", addr);
+        if self.config.dry_run {
+            return Ok(dry_run(tx_data, leaders));
+        }
 
-                if rtt_accepted.await {
-                    debug!("0-RTT accepted");
-                }
-                conn
+        // One stream per leader, held until every send finished
+        let _permits = self.acquire_stream_permits(leaders.len()).await?;
+
+        // Leaders are sent to at once, so one slow to acknowledge doesn't
+        // hold back the others
+        let sends = leaders
+            .into_iter()
+            .map(|(identity, socket, slot, priority)| {
+                self.deliver(tx_data, identity, socket, slot, priority)
+            });
+        Ok(futures_util::future::join_all(sends).await)
+    }
+
+    /// Sends `tx_data` to a single leader within
+    /// [`TpuClientConfig::delivery_timeout`], falling back to UDP if enabled
+    /// and the leader can't be reached over QUIC.
+    async fn deliver(
+        &self,
+        tx_data: &[u8],
+        leader_identity: String,
+        leader_socket: String,
+        slot: Slot,
+        priority: i32,
+    ) -> LeaderDeliveryResult {
+        let start = Instant::now();
+        let connection = match self.config.udp_fallback {
+            Some(timeout) => self.connect_within(&leader_socket, timeout).await,
+            None => match self.get_connection(&leader_socket).await {
+                ConnState::Live(conn) => Ok(Some(conn)),
+                ConnState::Connecting => Err(GatewayError::ConnectionFailed(
+                    "Still connecting".to_string(),
+                )),
+                ConnState::Absent => Ok(None),
+            },
+        };
+        let connected = matches!(connection, Ok(Some(_)));
+        let mut retryable = false;
+        let mut outcome = match connection {
+            Ok(Some(conn)) => {
+                let streams = self.stream_pool(&leader_socket).await;
+                info!(
+                    "Sending {} bytes to {} at: {} for slot {} (priority {})",
+                    tx_data.len(),
+                    leader_identity,
+                    leader_socket,
+                    slot,
+                    priority
+                );
+
+                // Waiting for the pacer or a stream counts against the delivery
+                // timeout
+                let send = async {
+                    if let Some(pacer) = &self.pacer
+                        && !pacer
+                            .wait(&leader_socket, self.config.delivery_timeout)
+                            .await
+                    {
+                        return Err(GatewayError::DeliveryTimeout);
+                    }
+                    let _stream = match &streams {
+                        // The semaphore is never closed
+                        Some(streams) => streams.permits.acquire().await.ok(),
+                        None => None,
+                    };
+                    Self::send_to_leader(&conn, &leader_identity, &leader_socket, priority, tx_data)
+                        .await
+                };
+                send_within(self.config.delivery_timeout, send)
+                    .await
+                    .map(|()| start.elapsed())
+                    .map_err(|e| {
+                        retryable = e.is_transient();
+                        format!("{:#}", e)
+                    })
             }
-            Err(connecting) => {
-                debug!("0-RTT not accepted, waiting for handshake to complete");
-                match connecting.await {
-                    Ok(conn) => conn,
-                    Err(e) => {
-                        // Failed to connect, return error and remove from list of connections
-                        self.connections.write().await.remove(validator);
-                        return Err(e.into());
+            Ok(None) => {
+                retryable = true;
+                Err("No open connection".to_string())
+            }
+            Err(e) => {
+                retryable = e.is_transient();
+                Err(e.to_string())
+            }
+        };
+
+        if let Err(e) = &outcome {
+            info!(
+                "Delivery failed for {} at {}: {}",
+                leader_identity, leader_socket, e
+            );
+        }
+        self.health.record(&leader_socket, &outcome);
+
+        // Unreachable over QUIC, try the leader's UDP port
+        let udp_addr = match self.config.udp_fallback {
+            Some(_) if !connected => self.leader_tracker.udp_socket(&leader_identity).await,
+            _ => None,
+        };
+        let udp_fallback = udp_addr.is_some();
+        if let Some(udp_addr) = udp_addr {
+            info!(
+                "Falling back to UDP for {} at {}",
+                leader_identity, udp_addr
+            );
+            self.metrics.record_udp_fallback();
+            outcome = self
+                .send_udp(udp_addr, tx_data)
+                .await
+                .map(|()| start.elapsed())
+                .map_err(|e| format!("{:#}", e));
+            retryable = outcome.is_err();
+        }
+
+        self.metrics
+            .record_leader_delivery(&leader_identity, &leader_socket, outcome.is_ok());
+
+        LeaderDeliveryResult {
+            leader_identity,
+            socket: leader_socket,
+            slot,
+            priority,
+            outcome,
+            udp_fallback,
+            retryable,
+            dry_run: false,
+        }
+    }
+
+    /// Reserves `streams` stream permits, waiting up to
+    /// [`TpuClientConfig::stream_permit_timeout`] for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GatewayError::Saturated`] if they didn't free up in time.
+    async fn acquire_stream_permits(
+        &self,
+        streams: usize,
+    ) -> Result<Option<tokio::sync::SemaphorePermit<'_>>, GatewayError> {
+        // A fanout over the limit takes every permit rather than none
+        let streams = streams.min(self.config.max_in_flight_streams) as u32;
+        if streams == 0 {
+            return Ok(None);
+        }
+
+        let acquire = self.stream_permits.acquire_many(streams);
+        match tokio::time::timeout(self.config.stream_permit_timeout, acquire).await {
+            Ok(Ok(permits)) => Ok(Some(permits)),
+            // The semaphore is never closed
+            Ok(Err(_)) | Err(_) => {
+                let in_flight = self.in_flight_streams();
+                warn!("Connection pool saturated, {} streams in flight", in_flight);
+                Err(GatewayError::Saturated { in_flight })
+            }
+        }
+    }
+
+    /// Streams currently being written to leaders.
+    pub fn in_flight_streams(&self) -> usize {
+        self.config.max_in_flight_streams - self.stream_permits.available_permits()
+    }
+
+    /// Returns the stream pool of the connection to `validator`, if it is in
+    /// the pool.
+    async fn stream_pool(&self, validator: &str) -> Option<Arc<StreamPool>> {
+        let conns = self.connections.read().await;
+        conns.get(validator).map(|entry| entry.streams.clone())
+    }
+
+    /// Writes `tx_data` to a fresh unidirectional stream on `conn` with the given
+    /// priority, and waits for the validator to acknowledge it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GatewayError::Stopped`] if the validator refused the stream
+    /// with STOP_SENDING.
+    #[tracing::instrument(name = "send", skip(conn, tx_data), fields(bytes = tx_data.len()))]
+    async fn send_to_leader(
+        conn: &QuinnConnection,
+        leader: &str,
+        socket: &str,
+        priority: i32,
+        tx_data: &[u8],
+    ) -> Result<(), GatewayError> {
+        let mut send_stream = conn
+            .open_uni()
+            .await
+            .map_err(|e| GatewayError::ConnectionFailed(e.to_string()))?;
+        send_stream
+            .set_priority(priority)
+            .map_err(|e| GatewayError::Stream(format!("failed to set stream priority: {}", e)))?;
+
+        match send_stream.write_all(tx_data).await {
+            Err(quinn::WriteError::Stopped(code)) => {
+                return Err(GatewayError::Stopped(code.into_inner()));
+            }
+            written => written?,
+        }
+        send_stream.finish()?;
+
+        // A written stream has only left this host. The validator either
+        // acknowledges all of it or refuses it, e.g. when throttling us
+        match send_stream.stopped().await? {
+            Some(code) => Err(GatewayError::Stopped(code.into_inner())),
+            None => Ok(()),
+        }
+    }
+
+    /// Looks up the pooled connection to `validator` without connecting. A
+    /// live connection is marked as used, and one that outlived
+    /// [`TpuClientConfig::max_connection_lifetime`] is evicted.
+    pub async fn get_connection(&self, validator: &str) -> ConnState {
+        let conns = self.connections.read().await;
+
+        if evict_expired(&conns, validator, self.config.max_connection_lifetime) {
+            return ConnState::Absent;
+        }
+
+        let Some(mut entry) = conns.get_mut(validator) else {
+            return ConnState::Absent;
+        };
+        match entry.conn.clone() {
+            None => ConnState::Connecting,
+            Some(conn) if conn.close_reason().is_none() => {
+                debug!("Reusing connection to {}", validator);
+                entry.last_used = Some(Instant::now());
+                ConnState::Live(conn)
+            }
+            Some(_) => ConnState::Absent,
+        }
+    }
+
+    /// Gets an open connection to `validator`, establishing one if needed
+    /// within `timeout`, see [`TpuClientConfig::udp_fallback`].
+    async fn connect_within(
+        &self,
+        validator: &str,
+        timeout: Duration,
+    ) -> Result<Option<QuinnConnection>, GatewayError> {
+        match tokio::time::timeout(timeout, self.get_or_create_connection(validator)).await {
+            Ok(connection) => connection.map(Some),
+            Err(_) => Err(GatewayError::ConnectionFailed(format!(
+                "No connection within {:?}",
+                timeout
+            ))),
+        }
+    }
+
+    /// Sends `tx_data` to a leader's legacy UDP TPU port as a single
+    /// datagram. Succeeds once the datagram was handed to the network stack.
+    async fn send_udp(&self, target: SocketAddr, tx_data: &[u8]) -> Result<()> {
+        let socket = self
+            .udp_socket
+            .as_ref()
+            .context("UDP fallback is disabled")?;
+        socket
+            .send_to(tx_data, udp_target(socket, target))
+            .await
+            .context(format!("Failed to send to {} over UDP", target))?;
+        Ok(())
+    }
+
+    /// Gets an existing connection or creates a new one to the validator.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GatewayError::ConnectionFailed`] if the address is invalid or
+    /// refused, a handshake is already in flight, or every attempt fails.
+    pub async fn get_or_create_connection(
+        &self,
+        validator: &str,
+    ) -> Result<QuinnConnection, GatewayError> {
+        let already_connecting =
+            || GatewayError::ConnectionFailed("Already connecting".to_string());
+
+        match self.get_connection(validator).await {
+            ConnState::Live(conn) => return Ok(conn),
+            ConnState::Connecting => return Err(already_connecting()),
+            ConnState::Absent => {}
+        }
+
+        let addr: SocketAddr = validator.parse().map_err(|_| {
+            GatewayError::ConnectionFailed(format!("Invalid validator address {}", validator))
+        })?;
+        check_destination(&addr, self.config.reject_loopback)
+            .map_err(|e| GatewayError::ConnectionFailed(e.to_string()))?;
+
+        let conns = self.connections.write().await;
+        if let Some(conn) = conns.get(validator)
+            && let None = conn.conn
+        {
+            return Err(already_connecting());
+        }
+        conns.insert(validator.to_string(), Connection::default());
+        drop(conns);
+
+        // The placeholder stays in the pool across retries, so concurrent callers
+        // keep seeing "Already connecting" instead of starting their own attempts.
+        // The attempts run in their own task, so a caller that stops waiting, e.g.
+        // for the UDP fallback, doesn't strand the placeholder
+        debug!("Creating new connection to {}", validator);
+        let endpoints: Vec<Endpoint> = self
+            .endpoints
+            .iter()
+            .take(self.config.handshake_race.max(1))
+            .cloned()
+            .collect();
+        let handshakes = self.handshakes.clone();
+        let connections = self.connections.clone();
+        let (attempts, retry_delay) = (
+            self.config.connect_attempts,
+            self.config.connect_retry_delay,
+        );
+        let max_streams = self.config.max_streams_per_connection;
+        let validator = validator.to_string();
+        let establishing = tokio::spawn(async move {
+            let connecting = connect_with_retry(attempts, retry_delay, || {
+                Self::connect(&endpoints, addr, &handshakes)
+            });
+            let connection = match connecting.await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    // Failed to connect, return error and remove from list of connections
+                    connections.write().await.remove(&validator);
+                    return Err(GatewayError::ConnectionFailed(format!("{:#}", e)));
+                }
+            };
+
+            // The pool may have been cleared meanwhile, e.g. on shutdown
+            let conns = connections.write().await;
+            let Some(mut entry) = conns.get_mut(&validator) else {
+                connection.close(0u32.into(), b"pool cleared");
+                return Err(GatewayError::ConnectionFailed(
+                    "Pool cleared while connecting".to_string(),
+                ));
+            };
+            *entry = Connection {
+                conn: Some(connection.clone()),
+                last_used: Some(Instant::now()),
+                established_at: Some(Instant::now()),
+                streams: Arc::new(StreamPool::new(max_streams)),
+            };
+            drop(entry);
+            drop(conns);
+
+            debug!("Connected to {}", validator);
+
+            Ok(connection)
+        });
+
+        establishing
+            .await
+            .map_err(|e| GatewayError::ConnectionFailed(e.to_string()))?
+    }
+
+    /// Connects to `addr`, racing a handshake from each of `endpoints`.
+    async fn connect(
+        endpoints: &[Endpoint],
+        addr: SocketAddr,
+        handshakes: &Arc<HandshakeLog>,
+    ) -> Result<QuinnConnection> {
+        let attempts = endpoints
+            .iter()
+            .map(|endpoint| Box::pin(Self::handshake(endpoint.clone(), addr, handshakes.clone())))
+            .collect();
+
+        race_handshakes(attempts, |loser: QuinnConnection| {
+            loser.close(0u32.into(), b"lost handshake race");
+        })
+        .await
+    }
+
+    /// Runs a single handshake to `addr`, recording how it went in `log`.
+    async fn handshake(
+        endpoint: Endpoint,
+        addr: SocketAddr,
+        log: Arc<HandshakeLog>,
+    ) -> Result<QuinnConnection> {
+        let started = Instant::now();
+        let mut attempt = HandshakeAttempt {
+            zero_rtt_attempted: false,
+            zero_rtt_accepted: false,
+            established: false,
+            duration: Duration::ZERO,
+        };
+
+        let result = async {
+            match endpoint
+                .connect(addr, &server_name(&addr.to_string()))?
+                .into_0rtt()
+            {
+                Ok((conn, rtt_accepted)) => {
+                    debug!("Waiting for 0-RTT for: {}", addr);
+                    attempt.zero_rtt_attempted = true;
+
+                    if rtt_accepted.await {
+                        debug!("0-RTT accepted");
+                        attempt.zero_rtt_accepted = true;
                     }
+                    Ok::<_, anyhow::Error>(conn)
+                }
+                Err(connecting) => {
+                    debug!("0-RTT not accepted, waiting for handshake to complete");
+                    Ok(connecting.await?)
                 }
             }
+        }
+        .await;
+
+        attempt.established = result.is_ok();
+        attempt.duration = started.elapsed();
+        log.record(&addr.to_string(), attempt);
+
+        result
+    }
+
+    /// Connects to the leaders of the next `lookahead_slots` slots ahead of
+    /// time, within the limits of [`PrewarmConfig`](crate::tpu_client::PrewarmConfig).
+    ///
+    /// Leaders are warmed in order of imminence, and with
+    /// [`warm_ahead_slots`](crate::tpu_client::PrewarmConfig::warm_ahead_slots)
+    /// set only shortly before their window. If the pool is at its cap,
+    /// connections to leaders that are no longer upcoming are closed first,
+    /// then those to the leaders furthest out.
+    ///
+    /// Handshakes are started with a random delay of up to
+    /// [`connect_stagger`](crate::tpu_client::PrewarmConfig::connect_stagger)
+    /// each.
+    pub async fn prewarm(&self, lookahead_slots: u64) {
+        let prewarm = self.config.prewarm;
+        self.warm(
+            lookahead_slots,
+            prewarm.warm_ahead_slots,
+            prewarm.connect_stagger,
+        )
+        .await;
+    }
+
+    /// Connects to the leaders of the next `lookahead` slots right away, e.g.
+    /// at startup so the first transactions don't pay for the handshakes.
+    /// Unlike [`prewarm`](Self::prewarm), leaders are connected to regardless
+    /// of [`warm_ahead_slots`](crate::tpu_client::PrewarmConfig::warm_ahead_slots),
+    /// still within the pool's cap.
+    ///
+    /// Returns the number of connections established, leaving out those that
+    /// were already open.
+    pub async fn warmup(&self, lookahead: u64) -> usize {
+        self.warm(lookahead, None, Duration::ZERO).await
+    }
+
+    /// Warms the upcoming leaders as planned by [`plan_prewarm`], starting
+    /// each handshake up to `stagger` after the previous one, and returns the
+    /// number of connections established.
+    async fn warm(
+        &self,
+        lookahead_slots: u64,
+        warm_ahead_slots: Option<u64>,
+        stagger: Duration,
+    ) -> usize {
+        let prewarm = self.config.prewarm;
+        let upcoming = if self.config.static_tpu_addresses.is_empty() {
+            let upcoming = upcoming_leaders(&self.leader_tracker, lookahead_slots).await;
+            match &self.config.pinned_validators {
+                Some(pins) => skip_unpinned(pins, upcoming),
+                None => upcoming,
+            }
+        } else {
+            static_leaders(&self.config.static_tpu_addresses)
         };
 
-        self.connections.write().await.insert(
-            validator.to_string(),
-            Connection {
-                conn: Some(connection.clone()),
-            },
+        let plan = plan_prewarm(
+            upcoming,
+            &*self.connections.read().await,
+            prewarm.max_warm_connections,
+            warm_ahead_slots,
         );
 
-        debug!("Connected to {}", validator);
+        if !plan.evict.is_empty() {
+            let conns = self.connections.write().await;
+            for socket in &plan.evict {
+                if let Some((_, evicted)) = conns.remove(socket)
+                    && let Some(conn) = evicted.conn
+                {
+                    debug!("Evicting {} to stay within prewarm allowance", socket);
+                    conn.close(0u32.into(), b"prewarm allowance");
+                }
+            }
+        }
 
-        Ok(connection)
+        futures_util::stream::iter(plan.connect)
+            .then(|leader| async move {
+                tokio::time::sleep(jitter(stagger)).await;
+                leader
+            })
+            .map(|(identity, socket)| async move {
+                match self.get_or_create_connection(&socket).await {
+                    Ok(_) => {
+                        debug!("Pre-connected to leader {} at {}", identity, socket);
+                        true
+                    }
+                    Err(e) => {
+                        debug!("Failed to pre-connect to {}: {}", socket, e);
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(prewarm.concurrency.max(1))
+            .filter(|connected| std::future::ready(*connected))
+            .count()
+            .await
+    }
+
+    /// Returns the recent delivery record of the leader at `socket`, or `None`
+    /// if nothing was sent to it lately.
+    pub fn leader_health(&self, socket: &str) -> Option<LeaderHealth> {
+        self.health.health(socket)
     }
 
     /// Returns the number of active connections.
@@ -224,7 +1604,102 @@ impl TpuConnectionManager {
         self.connections.read().await.len()
     }
 
+    /// Returns the forwarding counters shared with this manager.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Returns the forwarding metrics together with pool and slot gauges.
+    pub async fn metrics_snapshot(&self) -> MetricsSnapshot {
+        let mut snapshot = self.metrics.snapshot();
+        snapshot.connection_count = self.connection_count().await as u64;
+        snapshot.current_slot = self
+            .leader_tracker
+            .slots_tracker
+            .read()
+            .await
+            .current_slot();
+        snapshot
+    }
+
+    /// Renders the forwarding metrics and the pool and slot gauges in the
+    /// Prometheus text format.
+    pub async fn prometheus_metrics(&self) -> String {
+        let snapshot = self.metrics_snapshot().await;
+        let open_streams = self.pool_stats().await.open_streams as u64;
+        self.metrics.render_prometheus(
+            snapshot.connection_count,
+            open_streams,
+            snapshot.current_slot,
+        )
+    }
+
+    /// Removes dead and idle connections from the pool and returns how many
+    /// were removed.
+    ///
+    /// Runs under the pool's write lock, so it can't interleave with
+    /// [`get_or_create_connection`](Self::get_or_create_connection) replacing
+    /// an entry for the same validator.
+    pub async fn reap_dead_connections(&self) -> usize {
+        let reaped = reap(&*self.connections.write().await, self.config.idle_ttl);
+        if reaped > 0 {
+            info!("Reaped {} dead connection(s)", reaped);
+        }
+        reaped
+    }
+
+    /// Drops pooled connections to `sockets`, e.g. the
+    /// [`stale_sockets`](crate::tpu_client::SocketChanges::stale_sockets)
+    /// of a leader socket refresh, and returns how many were removed.
+    pub async fn invalidate_connections<'a>(
+        &self,
+        sockets: impl IntoIterator<Item = &'a str>,
+    ) -> usize {
+        let invalidated = invalidate(&*self.connections.write().await, sockets);
+        if invalidated > 0 {
+            info!("Invalidated {} connection(s) to moved leaders", invalidated);
+        }
+        invalidated
+    }
+
+    /// Returns connection counts and per-validator last-use times, to spot
+    /// stale entries that should be reaped.
+    pub async fn pool_stats(&self) -> PoolStats {
+        let mut stats = pool_stats(&*self.connections.read().await);
+        stats.handshakes_by_socket = self.handshakes.by_socket();
+        stats.in_flight_streams = self.in_flight_streams();
+        for socket_stats in stats.handshakes_by_socket.values() {
+            stats.handshakes.merge(socket_stats);
+        }
+        stats
+    }
+
+    /// Returns a snapshot of the connection pool for diagnostics.
+    pub async fn snapshot(&self) -> Vec<ConnectionSnapshot> {
+        snapshot_pool(&*self.connections.read().await, &self.tickets)
+    }
+
+    /// Writes the current pool snapshot to `path` as pretty-printed JSON.
+    pub async fn write_snapshot(&self, path: &Path) -> Result<()> {
+        let snapshot = self.snapshot().await;
+        let json = serde_json::to_vec_pretty(&snapshot).context("Failed to serialize snapshot")?;
+        tokio::fs::write(path, json)
+            .await
+            .context(format!("Failed to write snapshot to {}", path.display()))?;
+
+        info!(
+            "Wrote snapshot of {} connection(s) to {}",
+            snapshot.len(),
+            path.display()
+        );
+
+        Ok(())
+    }
+
     /// Closes all connections.
+    ///
+    /// Dropping the manager only closes connections on a best-effort basis,
+    /// so owners should call this when shutting down.
     pub async fn close_all(&self) {
         let connections = self.connections.write().await;
         for conn in connections.iter() {
@@ -234,24 +1709,81 @@ impl TpuConnectionManager {
         }
         connections.clear();
     }
+
+    /// Closes all connections and waits until the close frames went out, for
+    /// at most [`GRACEFUL_CLOSE_TIMEOUT`].
+    ///
+    /// [`close_all`](Self::close_all) only queues the frames, which a process
+    /// exiting right after may never send, leaving validators to time the
+    /// connections out.
+    pub async fn close_all_graceful(&self) {
+        let closing: Vec<QuinnConnection> = {
+            let connections = self.connections.write().await;
+            let closing = connections
+                .iter()
+                .filter_map(|conn| conn.value().conn.clone())
+                .collect();
+            connections.clear();
+            closing
+        };
+
+        for conn in &closing {
+            conn.close(0u32.into(), b"shutdown");
+        }
+
+        let closed = async {
+            futures_util::future::join_all(closing.iter().map(|conn| conn.closed())).await;
+            // Closed connections linger until the close was sent, which is
+            // when the endpoints become idle
+            futures_util::future::join_all(self.endpoints.iter().map(Endpoint::wait_idle)).await;
+        };
+        if tokio::time::timeout(GRACEFUL_CLOSE_TIMEOUT, closed)
+            .await
+            .is_err()
+        {
+            warn!(
+                "Timed out closing {} TPU connection(s) after {:?}",
+                closing.len(),
+                GRACEFUL_CLOSE_TIMEOUT
+            );
+        }
+    }
 }
 
 impl Drop for TpuConnectionManager {
+    /// Closes pooled connections without blocking or needing a runtime.
+    ///
+    /// `Connection::close` is synchronous, so this works on any thread. If the
+    /// pool is locked at drop time the connections are left to close when
+    /// their last handle goes away.
     fn drop(&mut self) {
-        let handle = tokio::runtime::Handle::current();
-
-        handle.block_on(self.close_all());
+        if let Ok(connections) = self.connections.try_read() {
+            for entry in connections.iter() {
+                if let Some(conn) = &entry.conn {
+                    conn.close(0u32.into(), b"shutdown");
+                }
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::SLOT_DURATION;
+    use crate::tpu_client::LeaderTrackerConfig;
+    use crate::tpu_client::mock_tpu::MockTpu;
+    use crate::tpu_client::tracker::schedule_tracking::ScheduleTracker;
+    use solana_client::rpc_response::SlotUpdate;
+    use solana_sdk::instruction::Instruction;
+    use solana_sdk::message::Message;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::transaction::Transaction;
 
     #[tokio::test]
     async fn test_manager_creation() {
         let leader_tracker = Arc::new(
-            LeaderTracker::new()
+            LeaderTracker::new(LeaderTrackerConfig::default())
                 .await
                 .expect("Failed to create LeaderTracker"),
         );
@@ -263,11 +1795,1171 @@ mod tests {
     #[ignore] // Requires live RPC connection
     async fn test_connection_count() {
         let leader_tracker = Arc::new(
-            LeaderTracker::new()
+            LeaderTracker::new(LeaderTrackerConfig::default())
                 .await
                 .expect("Failed to create LeaderTracker"),
         );
         let manager = TpuConnectionManager::new(leader_tracker).unwrap();
         assert_eq!(manager.connection_count().await, 0);
     }
+
+    #[test]
+    fn test_snapshot_reflects_pool() {
+        let pool = DashMap::new();
+        pool.insert("10.0.0.2:8009".to_string(), Connection::default());
+        pool.insert(
+            "10.0.0.1:8009".to_string(),
+            Connection {
+                conn: None,
+                last_used: Some(Instant::now()),
+                established_at: None,
+                ..Connection::default()
+            },
+        );
+
+        let tickets = TicketStore::new();
+        tickets.record_inserted(&server_name("10.0.0.1:8009"));
+
+        let snapshot = snapshot_pool(&pool, &tickets);
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].socket, "10.0.0.1:8009");
+        assert_eq!(snapshot[0].status, ConnectionStatus::Connecting);
+        assert!(snapshot[0].last_used_ms_ago.is_some());
+        assert_eq!(snapshot[1].socket, "10.0.0.2:8009");
+        assert_eq!(snapshot[1].last_used_ms_ago, None);
+        assert_eq!(snapshot[1].rtt_ms, None);
+        assert!(snapshot[0].zero_rtt_capable);
+        assert!(!snapshot[1].zero_rtt_capable);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"status\":\"connecting\""));
+    }
+
+    fn offline_tracker() -> Arc<LeaderTracker> {
+        let schedule = ScheduleTracker::from_schedules(0, 432_000, HashMap::new(), HashMap::new());
+        Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            schedule,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_stalled_send_times_out() {
+        let stalled = std::future::pending::<Result<(), GatewayError>>();
+        let err = send_within(Duration::from_millis(20), stalled)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::DeliveryTimeout));
+
+        let sent = send_within(Duration::from_millis(20), async { Ok(()) }).await;
+        assert!(sent.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_transaction_rejected() {
+        // Serialized transaction of exactly `size` bytes
+        let payer = Pubkey::new_unique();
+        let transaction = |size: usize| -> Vec<u8> {
+            (0..size)
+                .map(|len| {
+                    let ix =
+                        Instruction::new_with_bytes(Pubkey::new_unique(), &vec![0; len], vec![]);
+                    let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&payer)));
+                    bincode::serialize(&tx).unwrap()
+                })
+                .find(|tx_data| tx_data.len() == size)
+                .unwrap()
+        };
+        let fits = transaction(PACKET_DATA_SIZE);
+        let too_large = transaction(PACKET_DATA_SIZE + 1);
+
+        assert!(check_packet_size(&fits).is_ok());
+        let err = check_packet_size(&too_large).unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidTransaction(_)));
+        assert_eq!(
+            err.to_string(),
+            "Invalid transaction: 1233 bytes exceeds the 1232 byte packet limit"
+        );
+
+        // Rejected before waiting on the (offline) leader tracker
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+        let err = manager.send_transaction(&too_large).await.unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidTransaction(_)));
+        let err = manager.send_transaction(&fits).await.unwrap_err();
+        assert!(matches!(err, GatewayError::NotReady(_)));
+    }
+
+    #[tokio::test]
+    async fn test_startup_is_told_apart_from_missing_leaders() {
+        // The tracker is still at slot 0 and its schedule is empty
+        let tracker = offline_tracker();
+        let config = TpuClientConfig {
+            not_ready_policy: NotReadyPolicy::Wait(Duration::from_secs(1)),
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(tracker.clone(), config).unwrap();
+
+        // Times out as not ready while no slot arrives
+        let impatient = TpuConnectionManager::with_config(
+            tracker.clone(),
+            TpuClientConfig {
+                not_ready_policy: NotReadyPolicy::Wait(Duration::from_millis(20)),
+                ..TpuClientConfig::default()
+            },
+        )
+        .unwrap();
+        let err = impatient
+            .send_transaction(b"transaction")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "not_ready");
+
+        // Waits for the first slot, then finds no leader for it
+        let tracker_clone = tracker.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let event = SlotUpdate::FirstShredReceived {
+                slot: 100,
+                timestamp: 0,
+            };
+            LeaderTracker::handle_slot_event(&tracker_clone, event)
+                .await
+                .unwrap();
+        });
+        let err = manager.send_transaction(b"transaction").await.unwrap_err();
+        assert_eq!(err.code(), "no_leaders");
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_reject() {
+        let tracker = offline_tracker();
+
+        let err = wait_for_ready(&tracker, NotReadyPolicy::Reject)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not ready"));
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_wait() {
+        let tracker = offline_tracker();
+
+        // Times out if nothing arrives
+        let policy = NotReadyPolicy::Wait(Duration::from_millis(20));
+        assert!(wait_for_ready(&tracker, policy).await.is_err());
+
+        // Succeeds once a slot update is recorded while waiting
+        let tracker_clone = tracker.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let event = SlotUpdate::FirstShredReceived {
+                slot: 100,
+                timestamp: 0,
+            };
+            LeaderTracker::handle_slot_event(&tracker_clone, event)
+                .await
+                .unwrap();
+        });
+
+        let policy = NotReadyPolicy::Wait(Duration::from_secs(1));
+        assert!(wait_for_ready(&tracker, policy).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reap_removes_closed_and_idle_connections() {
+        let (closed, idle, fresh) = (MockTpu::spawn(), MockTpu::spawn(), MockTpu::spawn());
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+        let mut conns = Vec::new();
+        for tpu in [&closed, &idle, &fresh] {
+            let conn = manager
+                .get_or_create_connection(&tpu.socket())
+                .await
+                .unwrap();
+            conns.push(conn);
+        }
+        conns[0].close(0u32.into(), b"test");
+
+        let pool = manager.connections.write().await;
+        pool.insert(
+            "10.0.0.1:8009".to_string(),
+            Connection {
+                conn: None,
+                last_used: Some(Instant::now()),
+                established_at: None,
+                ..Connection::default()
+            },
+        );
+
+        // Only the idle connection and the handshake in flight have gone
+        // unused for too long, but a handshake in flight is never reaped
+        let idle_ttl = Duration::from_millis(20);
+        tokio::time::sleep(idle_ttl * 2).await;
+        for tpu in [&closed, &fresh] {
+            pool.get_mut(&tpu.socket()).unwrap().last_used = Some(Instant::now());
+        }
+        assert_eq!(reap(&pool, idle_ttl), 2);
+
+        let mut remaining = pool
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        let mut expected = vec![fresh.socket(), "10.0.0.1:8009".to_string()];
+        expected.sort();
+        assert_eq!(remaining, expected);
+
+        // Idle connections are closed as they are dropped
+        assert!(conns[1].close_reason().is_some());
+        assert!(conns[2].close_reason().is_none());
+    }
+
+    #[test]
+    fn test_invalidate_only_listed_sockets() {
+        let pool = DashMap::new();
+        for socket in ["10.0.0.1:8009", "10.0.0.2:8009", "10.0.0.3:8009"] {
+            pool.insert(socket.to_string(), Connection::default());
+        }
+
+        assert_eq!(invalidate(&pool, ["10.0.0.2:8009", "10.0.0.9:8009"]), 1);
+        assert!(!pool.contains_key("10.0.0.2:8009"));
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_pool_stats_counts_entries() {
+        let pool = DashMap::new();
+        let used_at = Instant::now();
+        pool.insert("10.0.0.1:8009".to_string(), Connection::default());
+        pool.insert(
+            "10.0.0.2:8009".to_string(),
+            Connection {
+                conn: None,
+                last_used: Some(used_at),
+                established_at: None,
+                ..Connection::default()
+            },
+        );
+
+        let stats = pool_stats(&pool);
+
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.pending, 2);
+        assert_eq!(stats.live, 0);
+        assert_eq!(
+            stats.last_used,
+            HashMap::from([("10.0.0.2:8009".to_string(), used_at)])
+        );
+    }
+
+    #[test]
+    fn test_connection_renewed_after_max_lifetime() {
+        let pool = DashMap::new();
+        let socket = "10.0.0.1:8009";
+        let max_lifetime = Some(Duration::from_millis(20));
+
+        pool.insert(
+            socket.to_string(),
+            Connection {
+                conn: None,
+                last_used: Some(Instant::now()),
+                established_at: Some(Instant::now()),
+                ..Connection::default()
+            },
+        );
+
+        // Young connections and pools without a lifetime are left alone
+        assert!(!evict_expired(&pool, socket, max_lifetime));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!evict_expired(&pool, socket, None));
+        assert!(pool.contains_key(socket));
+
+        // Past the threshold the entry is dropped so the next access reconnects
+        assert!(evict_expired(&pool, socket, max_lifetime));
+        assert!(!pool.contains_key(socket));
+
+        // A pending handshake has no age and is never renewed
+        pool.insert(socket.to_string(), Connection::default());
+        assert!(!evict_expired(&pool, socket, Some(Duration::ZERO)));
+    }
+
+    #[tokio::test]
+    async fn test_fanout_selects_distinct_leaders() {
+        // leader-0 leads three consecutive windows, then one leader per window
+        let schedule = (0..100)
+            .map(|i| (i, format!("leader-{}", (i / 4).saturating_sub(2))))
+            .collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 432_000, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(
+                (0..10)
+                    .map(|i| (format!("leader-{}", i), format!("10.0.0.{}:8009", i)))
+                    .collect(),
+            )
+            .await;
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 1,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let identities = |leaders: Vec<(String, String, u64)>| -> Vec<String> {
+            leaders.into_iter().map(|(id, _, _)| id).collect()
+        };
+
+        // Slots 1-16 span four windows but only three distinct leaders
+        let leaders = select_leaders(&tracker, TargetSlot::LeadingEdge, Some(4), usize::MAX).await;
+        assert_eq!(
+            identities(leaders),
+            vec!["leader-0", "leader-1", "leader-2"]
+        );
+
+        // Mid-window, the look-ahead reaches a third leader which is cut off
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 14,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+        let leaders = select_leaders(&tracker, TargetSlot::LeadingEdge, Some(2), usize::MAX).await;
+        assert_eq!(identities(leaders), vec!["leader-1", "leader-2"]);
+
+        let leaders = select_leaders(&tracker, TargetSlot::LeadingEdge, None, usize::MAX).await;
+        assert_eq!(identities(leaders), vec!["leader-1"]);
+
+        // However far the fanout looks ahead, the cap bounds the leaders sent to
+        let leaders = select_leaders(&tracker, TargetSlot::LeadingEdge, Some(50), 2).await;
+        assert_eq!(leaders.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_reaches_mock_tpu() {
+        let tpu = MockTpu::spawn();
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+
+        let conn = manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+        TpuConnectionManager::send_to_leader(&conn, "mock", &tpu.socket(), 0, b"transaction")
+            .await
+            .unwrap();
+        assert_eq!(tpu.received(1).await, vec![b"transaction".to_vec()]);
+
+        // The pooled connection is reused for the next send
+        let reused = manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+        assert_eq!(reused.stable_id(), conn.stable_id());
+        assert_eq!(manager.connection_count().await, 1);
+
+        // The first handshake has no ticket to resume with
+        let stats = manager.pool_stats().await;
+        assert_eq!(stats.handshakes.attempts, 1);
+        assert_eq!(stats.handshakes.failed, 0);
+        assert_eq!(stats.handshakes.zero_rtt_attempted, 0);
+        assert_eq!(stats.handshakes_by_socket[&tpu.socket()], stats.handshakes);
+    }
+
+    #[tokio::test]
+    async fn test_get_connection_tracks_entry_state() {
+        let tpu = MockTpu::spawn();
+        let socket = tpu.socket();
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+        assert!(matches!(
+            manager.get_connection(&socket).await,
+            ConnState::Absent
+        ));
+
+        // A half-open entry holds off other callers until its handshake ends
+        manager
+            .connections
+            .read()
+            .await
+            .insert(socket.clone(), Connection::default());
+        assert!(matches!(
+            manager.get_connection(&socket).await,
+            ConnState::Connecting
+        ));
+        let err = manager.get_or_create_connection(&socket).await.unwrap_err();
+        assert!(matches!(err, GatewayError::ConnectionFailed(ref e) if e == "Already connecting"));
+        manager.connections.read().await.remove(&socket);
+
+        let conn = manager.get_or_create_connection(&socket).await.unwrap();
+        match manager.get_connection(&socket).await {
+            ConnState::Live(live) => assert_eq!(live.stable_id(), conn.stable_id()),
+            state => panic!("expected a live connection, got {:?}", state),
+        }
+
+        // A closed connection is replaced by the next connect
+        conn.close(0u32.into(), b"closed");
+        assert!(matches!(
+            manager.get_connection(&socket).await,
+            ConnState::Absent
+        ));
+        let replaced = manager.get_or_create_connection(&socket).await.unwrap();
+        assert_ne!(replaced.stable_id(), conn.stable_id());
+        assert_eq!(manager.connection_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_connection_is_absent() {
+        let tpu = MockTpu::spawn();
+        let socket = tpu.socket();
+        let config = TpuClientConfig {
+            max_connection_lifetime: Some(Duration::ZERO),
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        manager.get_or_create_connection(&socket).await.unwrap();
+        assert!(matches!(
+            manager.get_connection(&socket).await,
+            ConnState::Absent
+        ));
+        assert_eq!(manager.connection_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_validators_refuse_unknown_identities() {
+        let tpu = MockTpu::spawn();
+        let pinned = |identity| TpuClientConfig {
+            pinned_validators: Some(ValidatorPins::identities([identity])),
+            ..TpuClientConfig::default()
+        };
+
+        let manager =
+            TpuConnectionManager::with_config(offline_tracker(), pinned(tpu.identity())).unwrap();
+        manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+
+        let manager =
+            TpuConnectionManager::with_config(offline_tracker(), pinned(Pubkey::new_unique()))
+                .unwrap();
+        let err = manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::ConnectionFailed(_)));
+        assert_eq!(manager.connection_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unpinned_leaders_are_skipped() {
+        let pinned = Pubkey::new_unique();
+        let unpinned = Pubkey::new_unique();
+        let schedule = (0..8)
+            .map(|i| (i, if i < 4 { pinned } else { unpinned }.to_string()))
+            .collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 432_000, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(HashMap::from([
+                (pinned.to_string(), "10.0.0.0:8009".to_string()),
+                (unpinned.to_string(), "10.0.0.1:8009".to_string()),
+            ]))
+            .await;
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 3,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let config = TpuClientConfig {
+            pinned_validators: Some(ValidatorPins::identities([pinned])),
+            dry_run: true,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(tracker, config).unwrap();
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].leader_identity, pinned.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_graceful_close_reaches_validator() {
+        let tpu = MockTpu::spawn();
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+
+        let conn = manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+        TpuConnectionManager::send_to_leader(&conn, "mock", &tpu.socket(), 0, b"transaction")
+            .await
+            .unwrap();
+        tpu.received(1).await;
+
+        manager.close_all_graceful().await;
+        assert_eq!(manager.connection_count().await, 0);
+        assert!(conn.close_reason().is_some());
+        tpu.closed(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_stopped_stream_is_not_delivered() {
+        let tpu = MockTpu::spawn_rejecting(7);
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+
+        let conn = manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+        let err = TpuConnectionManager::send_to_leader(
+            &conn,
+            "mock",
+            &tpu.socket(),
+            0,
+            &[0; PACKET_DATA_SIZE],
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::Stopped(7)), "{err}");
+        assert_eq!(err.code(), "stream_stopped");
+    }
+
+    #[tokio::test]
+    async fn test_warmup_reports_new_connections() {
+        let tpu = MockTpu::spawn();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        assert_eq!(manager.warmup(4).await, 1);
+        assert_eq!(manager.connection_count().await, 1);
+
+        // Already warm
+        assert_eq!(manager.warmup(4).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retries_forward_only_on_transient_failures() {
+        let tpu = MockTpu::spawn_rejecting(7);
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            forward_retries: 2,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        let deliveries = manager
+            .send_transaction(&[0; PACKET_DATA_SIZE])
+            .await
+            .unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert!(!deliveries[0].is_delivered());
+        assert!(!deliveries[0].retryable);
+
+        // The refusal was not retried
+        let health = manager.health.health(&tpu.socket()).unwrap();
+        assert_eq!(health.samples, 1);
+
+        // Takes datagrams but never answers a handshake
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent = silent.local_addr().unwrap();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![silent],
+            udp_fallback: Some(Duration::from_millis(20)),
+            forward_retries: 2,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert!(deliveries[0].retryable);
+
+        // The first attempt and both retries were sent
+        let health = manager.health.health(&silent.to_string()).unwrap();
+        assert_eq!(health.samples, 3);
+        assert_eq!(health.success_ratio, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_reaches_ipv4_and_ipv6_validators() {
+        // Hosts without IPv6 loopback can't run this
+        if std::net::UdpSocket::bind("[::1]:0").is_err() {
+            return;
+        }
+        let v4 = MockTpu::spawn();
+        let v6 = MockTpu::spawn_at("[::1]:0".parse().unwrap());
+        assert!(v6.socket().starts_with("[::1]:"));
+        let manager = TpuConnectionManager::new(offline_tracker()).unwrap();
+
+        for tpu in [&v4, &v6] {
+            let conn = manager
+                .get_or_create_connection(&tpu.socket())
+                .await
+                .unwrap();
+            TpuConnectionManager::send_to_leader(&conn, "mock", &tpu.socket(), 0, b"transaction")
+                .await
+                .unwrap();
+            assert_eq!(tpu.received(1).await, vec![b"transaction".to_vec()]);
+        }
+        assert_eq!(manager.connection_count().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_identity_presented_to_validators() {
+        let tpu = MockTpu::spawn();
+        let identity = Keypair::new();
+        let pubkey = identity.pubkey();
+        let manager = TpuConnectionManager::with_identity(offline_tracker(), identity).unwrap();
+
+        let conn = manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+        TpuConnectionManager::send_to_leader(&conn, "mock", &tpu.socket(), 0, b"transaction")
+            .await
+            .unwrap();
+        tpu.received(1).await;
+        assert_eq!(tpu.client_identities(), vec![pubkey]);
+
+        // Without an identity, each manager presents a fresh one
+        let unstaked = TpuConnectionManager::new(offline_tracker()).unwrap();
+        let conn = unstaked
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+        TpuConnectionManager::send_to_leader(&conn, "mock", &tpu.socket(), 0, b"transaction")
+            .await
+            .unwrap();
+        tpu.received(2).await;
+        let identities = tpu.client_identities();
+        assert_eq!(identities.len(), 2);
+        assert_ne!(identities[1], pubkey);
+    }
+
+    #[tokio::test]
+    async fn test_saturated_pool_asks_clients_to_back_off() {
+        let tpu = MockTpu::spawn();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            max_in_flight_streams: 1,
+            stream_permit_timeout: Duration::from_millis(20),
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        // Another transaction is still being written
+        let busy = manager.stream_permits.acquire().await.unwrap();
+        assert_eq!(manager.pool_stats().await.in_flight_streams, 1);
+
+        let err = manager.send_transaction(b"transaction").await.unwrap_err();
+        assert!(matches!(err, GatewayError::Saturated { in_flight: 1 }));
+        assert_eq!(err.code(), "saturated");
+
+        drop(busy);
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert!(deliveries[0].is_delivered());
+        assert_eq!(manager.pool_stats().await.in_flight_streams, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_pool_bounds_streams_per_connection() {
+        let tpu = MockTpu::spawn();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            max_streams_per_connection: 1,
+            delivery_timeout: Duration::from_millis(50),
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+        manager
+            .get_or_create_connection(&tpu.socket())
+            .await
+            .unwrap();
+
+        // Another transaction holds the connection's only stream
+        let streams = manager.stream_pool(&tpu.socket()).await.unwrap();
+        let busy = streams.permits.acquire().await.unwrap();
+        assert_eq!(manager.pool_stats().await.open_streams, 1);
+        assert_eq!(manager.snapshot().await[0].open_streams, 1);
+        assert!(
+            manager
+                .prometheus_metrics()
+                .await
+                .contains("bifrost_connection_streams 1")
+        );
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert_eq!(
+            deliveries[0].outcome,
+            Err(GatewayError::DeliveryTimeout.to_string())
+        );
+
+        drop(busy);
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert!(deliveries[0].is_delivered());
+        assert_eq!(manager.pool_stats().await.open_streams, 0);
+        assert_eq!(manager.connection_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_opens_no_streams() {
+        let tpu = MockTpu::spawn();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            dry_run: true,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].socket, tpu.socket());
+        assert!(deliveries[0].dry_run);
+        assert!(!deliveries[0].is_delivered());
+        assert_eq!(manager.connection_count().await, 0);
+        assert!(tpu.client_identities().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_static_tpu_bypasses_leader_tracker() {
+        let tpu = MockTpu::spawn();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![tpu.socket().parse().unwrap()],
+            ..TpuClientConfig::default()
+        };
+        // The tracker never sees a slot, so any schedule lookup would fail
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        // Connect ahead of time, as prewarming would for upcoming leaders
+        manager.prewarm(0).await;
+        assert_eq!(manager.connection_count().await, 1);
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].leader_identity, STATIC_LEADER_IDENTITY);
+        assert!(deliveries[0].is_delivered());
+        assert_eq!(tpu.received(1).await, vec![b"transaction".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_udp_without_quic() {
+        // Takes datagrams but never answers a handshake
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let udp_tpu = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let schedule = (0..100).map(|i| (i, "leader".to_string())).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 432_000, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(HashMap::from([(
+                "leader".to_string(),
+                silent.local_addr().unwrap().to_string(),
+            )]))
+            .await;
+        tracker
+            .set_udp_sockets(HashMap::from([(
+                "leader".to_string(),
+                udp_tpu.local_addr().unwrap(),
+            )]))
+            .await;
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 1,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let config = TpuClientConfig {
+            udp_fallback: Some(Duration::from_millis(100)),
+            forward_retries: 0,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(tracker, config).unwrap();
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert!(deliveries[0].is_delivered());
+        assert!(deliveries[0].udp_fallback);
+
+        let mut buf = [0; 64];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(1), udp_tpu.recv_from(&mut buf))
+            .await
+            .expect("no datagram received")
+            .unwrap();
+        assert_eq!(&buf[..len], b"transaction");
+        assert!(
+            manager
+                .metrics
+                .render_prometheus(0, 0, 0)
+                .contains("bifrost_udp_fallbacks_total 1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_handshake_continues_past_udp_fallback() {
+        // Takes datagrams but never answers a handshake, until the TPU takes its port
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = silent.local_addr().unwrap();
+        let config = TpuClientConfig {
+            static_tpu_addresses: vec![addr],
+            udp_fallback: Some(Duration::from_millis(50)),
+            forward_retries: 0,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(offline_tracker(), config).unwrap();
+
+        let deliveries = manager.send_transaction(b"first").await.unwrap();
+        assert!(!deliveries[0].is_delivered());
+
+        // The handshake outlives the first send and completes once the TPU is up
+        drop(silent);
+        let tpu = MockTpu::spawn_at(addr);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !matches!(
+                manager.get_connection(&addr.to_string()).await,
+                ConnState::Live(_)
+            ) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("handshake abandoned");
+
+        let deliveries = manager.send_transaction(b"second").await.unwrap();
+        assert!(deliveries[0].is_delivered());
+        assert!(!deliveries[0].udp_fallback);
+        assert_eq!(tpu.received(1).await, vec![b"second".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_transport_config_requires_keep_alive_below_idle_timeout() {
+        let transport = QuicTransportConfig {
+            max_idle_timeout: Duration::from_secs(4),
+            keep_alive_interval: Duration::from_secs(4),
+            ..QuicTransportConfig::default()
+        };
+        let config = TpuClientConfig {
+            transport,
+            ..TpuClientConfig::default()
+        };
+        assert!(TpuConnectionManager::with_config(offline_tracker(), config).is_err());
+
+        let config = TpuClientConfig {
+            transport: QuicTransportConfig {
+                keep_alive_interval: Duration::from_secs(1),
+                ..transport
+            },
+            ..TpuClientConfig::default()
+        };
+        assert!(TpuConnectionManager::with_config(offline_tracker(), config).is_ok());
+    }
+
+    #[test]
+    fn test_unhealthy_leaders_are_skipped() {
+        let health = HealthTracker::new();
+        let leaders: Vec<(String, String, u64)> = (0..3)
+            .map(|i| (format!("leader-{}", i), format!("10.0.0.{}:8009", i), 100))
+            .collect();
+        let identities = |leaders: Vec<(String, String, u64)>| -> Vec<String> {
+            leaders.into_iter().map(|(id, _, _)| id).collect()
+        };
+        for _ in 0..4 {
+            health.record("10.0.0.0:8009", &Err("Delivery timeout".to_string()));
+            health.record("10.0.0.1:8009", &Ok(Duration::from_millis(2)));
+        }
+
+        let kept = skip_unhealthy(&health, leaders.clone(), 0.5);
+        assert_eq!(identities(kept), vec!["leader-1", "leader-2"]);
+
+        // The current leader is kept when it is the only one
+        let kept = skip_unhealthy(&health, leaders[..1].to_vec(), 0.5);
+        assert_eq!(identities(kept), vec!["leader-0"]);
+    }
+
+    #[test]
+    fn test_drop_outside_runtime() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let manager = runtime
+            .block_on(async { TpuConnectionManager::new(offline_tracker()) })
+            .unwrap();
+
+        std::thread::spawn(move || drop(manager))
+            .join()
+            .expect("dropping the manager should not panic");
+    }
+
+    #[tokio::test]
+    async fn test_late_in_slot_targets_next_leader_first() {
+        // leader-0 leads slots 0-3, leader-1 slots 4-7
+        let schedule = (0..8).map(|i| (i, format!("leader-{}", i / 4))).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 432_000, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(HashMap::from([
+                ("leader-0".to_string(), "10.0.0.0:8009".to_string()),
+                ("leader-1".to_string(), "10.0.0.1:8009".to_string()),
+            ]))
+            .await;
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 3,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let identities = |leaders: Vec<(String, String, u64)>| -> Vec<String> {
+            leaders.into_iter().map(|(id, _, _)| id).collect()
+        };
+        let leaders = select_leaders(&tracker, TargetSlot::LeadingEdge, None, usize::MAX).await;
+        assert_eq!(identities(leaders.clone()), vec!["leader-0", "leader-1"]);
+
+        // A whole slot duration as threshold means the slot is always "late"
+        let late = prefer_next_leader(
+            &tracker,
+            TargetSlot::LeadingEdge,
+            leaders.clone(),
+            SLOT_DURATION + Duration::from_millis(1),
+        )
+        .await;
+        assert_eq!(identities(late), vec!["leader-1", "leader-0"]);
+
+        let early =
+            prefer_next_leader(&tracker, TargetSlot::LeadingEdge, leaders, Duration::ZERO).await;
+        assert_eq!(identities(early), vec!["leader-0", "leader-1"]);
+
+        // Forwarding applies the threshold as well
+        let config = TpuClientConfig {
+            late_slot_threshold: Some(SLOT_DURATION + Duration::from_millis(1)),
+            dry_run: true,
+            ..TpuClientConfig::default()
+        };
+        let manager = TpuConnectionManager::with_config(tracker, config).unwrap();
+
+        let deliveries = manager.send_transaction(b"transaction").await.unwrap();
+        let identities: Vec<&str> = deliveries
+            .iter()
+            .map(|delivery| delivery.leader_identity.as_str())
+            .collect();
+        assert_eq!(identities, vec!["leader-1", "leader-0"]);
+    }
+
+    #[tokio::test]
+    async fn test_connect_retries_until_success() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = AtomicUsize::new(0);
+        let flaky = || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if call < 2 {
+                    Err(anyhow!("handshake failed"))
+                } else {
+                    Ok(call)
+                }
+            }
+        };
+
+        let conn = connect_with_retry(3, Duration::from_millis(1), flaky)
+            .await
+            .unwrap();
+        assert_eq!(conn, 2);
+
+        // Gives up after the configured number of attempts
+        calls.store(0, Ordering::SeqCst);
+        let err = connect_with_retry(2, Duration::from_millis(1), flaky)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("after 2 attempt(s)"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_prewarm_respects_allowance() {
+        let upcoming: Vec<(String, String, u64)> = (0..6)
+            .map(|i| (format!("leader-{}", i), format!("10.0.0.{}:8009", i), i * 4))
+            .collect();
+
+        // Empty pool: only the most imminent leaders up to the allowance
+        let pool = DashMap::new();
+        let plan = plan_prewarm(upcoming.clone(), &pool, 3, None);
+        let sockets: Vec<&str> = plan.connect.iter().map(|(_, s)| s.as_str()).collect();
+        assert_eq!(
+            sockets,
+            vec!["10.0.0.0:8009", "10.0.0.1:8009", "10.0.0.2:8009"]
+        );
+        assert!(plan.evict.is_empty());
+
+        // Full pool with one imminent leader and two stale ones
+        for socket in ["10.0.0.0:8009", "10.0.0.9:8009", "10.0.0.8:8009"] {
+            pool.insert(socket.to_string(), Connection::default());
+        }
+        let plan = plan_prewarm(upcoming, &pool, 3, None);
+        assert_eq!(plan.connect.len(), 2);
+        assert_eq!(plan.evict.len(), 2);
+        assert!(!plan.evict.contains(&"10.0.0.0:8009".to_string()));
+        assert!(pool.len() - plan.evict.len() + plan.connect.len() <= 3);
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_follows_schedule() {
+        // leaders[i] leads slots 4i..4i+3
+        let leaders: Vec<Pubkey> = (0..5u8).map(|i| Pubkey::new_from_array([i; 32])).collect();
+        let socket = |i: usize| format!("10.0.0.{}:8009", i);
+        let schedule = (0..20).map(|i| (i, leaders[i / 4].to_string())).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 432_000, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(
+                leaders
+                    .iter()
+                    .enumerate()
+                    .map(|(i, leader)| (leader.to_string(), socket(i)))
+                    .collect(),
+            )
+            .await;
+
+        async fn advance_to(tracker: &Arc<LeaderTracker>, slot: u64) {
+            let event = SlotUpdate::FirstShredReceived { slot, timestamp: 0 };
+            LeaderTracker::handle_slot_event(tracker, event)
+                .await
+                .unwrap();
+        }
+        async fn connect_at(
+            tracker: &Arc<LeaderTracker>,
+            pool: &DashMap<String, Connection>,
+        ) -> Vec<String> {
+            let upcoming = upcoming_leaders(tracker, 20).await;
+            let plan = plan_prewarm(upcoming, pool, 3, Some(2));
+            plan.connect.into_iter().map(|(_, socket)| socket).collect()
+        }
+        let pool = DashMap::new();
+
+        // Slot 1: leaders[1] is 3 slots out, one too many to warm yet
+        advance_to(&tracker, 1).await;
+        assert_eq!(connect_at(&tracker, &pool).await, vec![socket(0)]);
+        pool.insert(socket(0), Connection::default());
+
+        // Slot 2: leaders[1] is now just 2 slots out
+        advance_to(&tracker, 2).await;
+        assert_eq!(connect_at(&tracker, &pool).await, vec![socket(1)]);
+        pool.insert(socket(1), Connection::default());
+
+        // Slot 10: the pool is full of leaders that are done or far out, and
+        // the ones no longer upcoming are evicted before the distant one
+        pool.insert(socket(4), Connection::default());
+        advance_to(&tracker, 10).await;
+        let upcoming = upcoming_leaders(&tracker, 20).await;
+        let plan = plan_prewarm(upcoming, &pool, 3, Some(2));
+        assert_eq!(plan.connect.len(), 2);
+        assert_eq!(plan.evict.len(), 2);
+        assert!(!plan.evict.contains(&socket(4)));
+    }
+
+    #[test]
+    fn test_forward_outcome_mixed_fanout() {
+        let delivery = |socket: &str, outcome: Result<Duration, String>| LeaderDeliveryResult {
+            leader_identity: format!("leader-{}", socket),
+            socket: socket.to_string(),
+            slot: 100,
+            priority: 0,
+            outcome,
+            udp_fallback: false,
+            retryable: false,
+            dry_run: false,
+        };
+        let delivered = delivery("10.0.0.1:8009", Ok(Duration::from_millis(3)));
+        let failed = delivery("10.0.0.2:8009", Err("No open connection".to_string()));
+
+        let outcome = ForwardOutcome::from_deliveries(vec![delivered.clone(), failed.clone()]);
+        match outcome {
+            ForwardOutcome::Delivered { leaders } => {
+                assert_eq!(leaders.len(), 2);
+                assert_eq!(leaders[0].outcome, Ok(Duration::from_millis(3)));
+                assert_eq!(leaders[1].socket, "10.0.0.2:8009");
+                assert_eq!(leaders[1].outcome, Err("No open connection".to_string()));
+            }
+            other => panic!("expected Delivered, got {:?}", other),
+        }
+
+        assert_eq!(
+            ForwardOutcome::from_deliveries(vec![failed.clone()]),
+            ForwardOutcome::AllFailed {
+                errors: vec![failed]
+            }
+        );
+        assert_eq!(
+            ForwardOutcome::from_deliveries(Vec::new()),
+            ForwardOutcome::NoLeaders
+        );
+    }
+
+    #[test]
+    fn test_nearest_slot_gets_highest_priority() {
+        let priorities: Vec<i32> = (100..104).map(|slot| stream_priority(slot, 100)).collect();
+
+        assert_eq!(priorities[0], *priorities.iter().max().unwrap());
+        assert!(priorities.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(stream_priority(Slot::MAX, 0), i32::MIN);
+        // Slots already past, e.g. of static addresses, count as current
+        assert_eq!(stream_priority(0, 100), stream_priority(100, 100));
+    }
+
+    #[test]
+    fn test_reject_loopback() {
+        let loopback: SocketAddr = "127.0.0.1:8009".parse().unwrap();
+        let mapped: SocketAddr = "[::ffff:127.0.0.1]:8009".parse().unwrap();
+        let remote: SocketAddr = "203.0.113.7:8009".parse().unwrap();
+
+        assert!(check_destination(&loopback, true).is_err());
+        assert!(check_destination(&mapped, true).is_err());
+        assert!(check_destination(&remote, true).is_ok());
+        assert!(check_destination(&loopback, false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_race_handshakes_fastest_wins() {
+        use std::sync::Mutex;
+
+        let attempt = |id: u32, delay_ms: u64| {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                Ok::<_, anyhow::Error>(id)
+            })
+        };
+
+        let closed = Arc::new(Mutex::new(Vec::new()));
+        let closed_clone = closed.clone();
+
+        let winner = race_handshakes(
+            vec![attempt(1, 60), attempt(2, 10), attempt(3, 30)],
+            move |loser| closed_clone.lock().unwrap().push(loser),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(winner, 2);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut closed = closed.lock().unwrap().clone();
+        closed.sort();
+        assert_eq!(closed, vec![1, 3]);
+    }
 }
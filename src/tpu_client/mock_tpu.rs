@@ -0,0 +1,163 @@
+//! In-process stand-in for a validator's TPU port, so the connection path can
+//! be tested without network access.
+
+use quinn::crypto::rustls::QuicServerConfig;
+use quinn::{Endpoint, ServerConfig, TransportConfig, VarInt};
+use rustls::pki_types::CertificateDer;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+const ALPN_TPU_PROTOCOL_ID: &[u8] = b"solana-tpu";
+/// Largest stream the mock reads, well above any transaction.
+const MAX_STREAM_SIZE: usize = 64 * 1024;
+/// Stream receive window of a rejecting mock, small enough that a client is
+/// still sending a transaction when the stream is stopped.
+const REJECTING_STREAM_WINDOW: u32 = 64;
+
+/// QUIC server speaking the TPU protocol on a loopback port. Every
+/// unidirectional stream it receives is recorded, as is the identity of every
+/// client that connects and every connection the client closed.
+pub(crate) struct MockTpu {
+    endpoint: Endpoint,
+    received: watch::Receiver<Vec<Vec<u8>>>,
+    clients: Arc<Mutex<Vec<Pubkey>>>,
+    closed: watch::Receiver<usize>,
+    identity: Pubkey,
+}
+
+impl MockTpu {
+    /// Binds to an ephemeral loopback port and starts accepting connections.
+    pub(crate) fn spawn() -> Self {
+        Self::spawn_at("127.0.0.1:0".parse().unwrap())
+    }
+
+    /// Binds to `addr` and starts accepting connections.
+    pub(crate) fn spawn_at(addr: SocketAddr) -> Self {
+        Self::spawn_with(addr, None)
+    }
+
+    /// Binds to an ephemeral loopback port and refuses every stream with
+    /// STOP_SENDING carrying `code`, as a throttling validator would.
+    pub(crate) fn spawn_rejecting(code: u32) -> Self {
+        Self::spawn_with("127.0.0.1:0".parse().unwrap(), Some(VarInt::from_u32(code)))
+    }
+
+    fn spawn_with(addr: SocketAddr, stop_code: Option<VarInt>) -> Self {
+        let identity = Keypair::new();
+        let (cert, key) = solana_tls_utils::new_dummy_x509_certificate(&identity);
+        let mut crypto = solana_tls_utils::tls_server_config_builder()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        crypto.alpn_protocols = vec![ALPN_TPU_PROTOCOL_ID.to_vec()];
+        let mut config =
+            ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(crypto).unwrap()));
+        if stop_code.is_some() {
+            let mut transport = TransportConfig::default();
+            transport.stream_receive_window(VarInt::from_u32(REJECTING_STREAM_WINDOW));
+            config.transport_config(Arc::new(transport));
+        }
+        let endpoint = Endpoint::server(config, addr).unwrap();
+
+        let (sender, received) = watch::channel(Vec::new());
+        let (closes, closed) = watch::channel(0);
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let accepting = endpoint.clone();
+        let connected = clients.clone();
+        tokio::spawn(async move {
+            while let Some(incoming) = accepting.accept().await {
+                let sender = sender.clone();
+                let connected = connected.clone();
+                let closes = closes.clone();
+                tokio::spawn(async move {
+                    let Ok(conn) = incoming.await else {
+                        return;
+                    };
+                    if let Some(identity) = client_identity(&conn) {
+                        connected.lock().unwrap().push(identity);
+                    }
+                    while let Ok(mut stream) = conn.accept_uni().await {
+                        if let Some(code) = stop_code {
+                            let _ = stream.stop(code);
+                            continue;
+                        }
+                        let sender = sender.clone();
+                        tokio::spawn(async move {
+                            if let Ok(data) = stream.read_to_end(MAX_STREAM_SIZE).await {
+                                sender.send_modify(|received| received.push(data));
+                            }
+                        });
+                    }
+                    if let quinn::ConnectionError::ApplicationClosed(_) = conn.closed().await {
+                        closes.send_modify(|closed| *closed += 1);
+                    }
+                });
+            }
+        });
+
+        Self {
+            endpoint,
+            received,
+            clients,
+            closed,
+            identity: identity.pubkey(),
+        }
+    }
+
+    /// Identity the mock's certificate was generated from.
+    pub(crate) fn identity(&self) -> Pubkey {
+        self.identity
+    }
+
+    /// Identities of the clients that connected so far, in connection order.
+    pub(crate) fn client_identities(&self) -> Vec<Pubkey> {
+        self.clients.lock().unwrap().clone()
+    }
+
+    /// Socket address in the form the connection manager is given leaders in.
+    pub(crate) fn socket(&self) -> String {
+        self.endpoint.local_addr().unwrap().to_string()
+    }
+
+    /// Waits up to a few seconds until at least `count` streams were received
+    /// and returns everything received so far.
+    pub(crate) async fn received(&self, count: usize) -> Vec<Vec<u8>> {
+        let mut received = self.received.clone();
+        let wait = received.wait_for(|received| received.len() >= count);
+        tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .expect("mock TPU did not receive the expected streams")
+            .unwrap()
+            .clone()
+    }
+
+    /// Waits up to a few seconds until clients closed at least `count`
+    /// connections, i.e. their CONNECTION_CLOSE frames arrived.
+    pub(crate) async fn closed(&self, count: usize) {
+        let mut closed = self.closed.clone();
+        let wait = closed.wait_for(|closed| *closed >= count);
+        tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .expect("mock TPU did not see the expected connection closes")
+            .unwrap();
+    }
+}
+
+/// Identity a client's certificate was derived from, as a validator reads it
+/// for stake-weighted QoS.
+fn client_identity(conn: &quinn::Connection) -> Option<Pubkey> {
+    let certificates = conn
+        .peer_identity()?
+        .downcast::<Vec<CertificateDer<'static>>>()
+        .ok()?;
+    solana_tls_utils::get_pubkey_from_tls_certificate(certificates.first()?)
+}
+
+impl Drop for MockTpu {
+    fn drop(&mut self) {
+        self.endpoint.close(0u32.into(), b"mock TPU dropped");
+    }
+}
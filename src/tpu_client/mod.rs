@@ -1,7 +1,31 @@
 //! TPU connection management for Solana validators.
 
+mod config;
+mod handshakes;
+mod health;
 mod manager;
+#[cfg(test)]
+pub(crate) mod mock_tpu;
+mod pacing;
+mod pinning;
+mod selection;
+mod tickets;
 pub mod tracker;
 
-pub use manager::TpuConnectionManager;
-pub use tracker::leader_tracker::LeaderTracker;
+pub use config::{NotReadyPolicy, PrewarmConfig, QuicTransportConfig, TpuClientConfig};
+pub use handshakes::{HANDSHAKE_DURATION_BUCKETS_MS, HandshakeStats};
+pub use health::{HealthTracker, LeaderHealth};
+pub use manager::{
+    ConnState, ConnectionSnapshot, ConnectionStatus, ForwardOutcome, LeaderDeliveryResult,
+    PoolStats, TpuConnectionManager,
+};
+pub use pacing::SendPacer;
+pub use pinning::ValidatorPins;
+pub use selection::LeaderSelector;
+pub use tickets::TicketStore;
+pub use tracker::backoff::ReconnectBackoff;
+pub use tracker::leader_tracker::{
+    EpochRotation, LeaderTracker, LeaderTrackerConfig, SlotSource, SocketChanges, TrackerStatus,
+};
+pub use tracker::slots_tracker::TargetSlot;
+pub use tracker::source::{LeaderSource, RpcLeaderSource};
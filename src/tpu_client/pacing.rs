@@ -0,0 +1,95 @@
+//! Spacing of consecutive sends to the same leader.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{Instant, sleep_until};
+
+/// Enforces a minimum interval between sends to the same leader socket.
+///
+/// Bursts aimed at one leader are queued and released one interval apart
+/// instead of tripping the validator's per-connection rate limits. Sends to
+/// different sockets never wait on each other.
+#[derive(Debug)]
+pub struct SendPacer {
+    min_interval: Duration,
+    /// Earliest time the next send to each socket may start
+    next_send: Mutex<HashMap<String, Instant>>,
+}
+
+impl SendPacer {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            next_send: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until a send to `socket` is allowed, reserving the slot after it
+    /// for the next caller.
+    ///
+    /// Returns `false` right away, reserving nothing, if the send would have
+    /// to wait longer than `max_wait`, so a deep queue doesn't grow further
+    /// with sends that would time out anyway.
+    pub async fn wait(&self, socket: &str, max_wait: Duration) -> bool {
+        let now = Instant::now();
+        let start = {
+            let mut next_send = self.next_send.lock().unwrap();
+            // Forget sockets whose window has passed so the map stays small
+            next_send.retain(|_, next| *next > now);
+
+            let start = next_send.get(socket).copied().unwrap_or(now).max(now);
+            if start - now > max_wait {
+                return false;
+            }
+            next_send.insert(socket.to_string(), start + self.min_interval);
+            start
+        };
+
+        sleep_until(start).await;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::join_all;
+
+    #[tokio::test]
+    async fn test_sends_to_one_leader_are_spaced() {
+        let pacer = SendPacer::new(Duration::from_millis(20));
+        let started = Instant::now();
+
+        let sent_at = join_all((0..4).map(|_| async {
+            assert!(pacer.wait("10.0.0.1:8009", Duration::from_secs(1)).await);
+            Instant::now()
+        }))
+        .await;
+
+        // Each send is released at least one interval after the one before it
+        for (i, sent_at) in sent_at.iter().enumerate() {
+            assert!(*sent_at - started >= Duration::from_millis(20) * i as u32);
+        }
+
+        // Another leader is not held back by the queue
+        let waited = Instant::now();
+        assert!(pacer.wait("10.0.0.2:8009", Duration::ZERO).await);
+        assert!(waited.elapsed() < Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn test_sends_queued_too_long_are_refused() {
+        let pacer = SendPacer::new(Duration::from_millis(50));
+        let max_wait = Duration::from_millis(60);
+        assert!(pacer.wait("10.0.0.1:8009", max_wait).await);
+
+        // The second send fits the wait, the third would queue past it
+        let waits = join_all((0..2).map(|_| pacer.wait("10.0.0.1:8009", max_wait))).await;
+        assert_eq!(waits, vec![true, false]);
+
+        // The refused send reserved nothing, so the slot after the second send
+        // is within reach again
+        assert!(pacer.wait("10.0.0.1:8009", max_wait).await);
+    }
+}
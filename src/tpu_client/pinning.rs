@@ -0,0 +1,184 @@
+//! Pinning of the validators leader connections are made to, see
+//! [`TpuClientConfig::pinned_validators`](super::TpuClientConfig::pinned_validators).
+
+use anyhow::{Context, Result};
+use log::warn;
+use rustls::client::WantsClientCert;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, ConfigBuilder, DigitallySignedStruct, Error, SignatureScheme,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::cert::certificate_hash;
+
+/// Validators leader connections may be made to. A validator is accepted if
+/// its certificate matches either set.
+///
+/// Solana validators present a self-signed certificate generated from their
+/// identity keypair, so there is no CA to check it against. Pin identities:
+/// the certificate's key is the validator's identity key, which a validator
+/// can't present without holding it. Certificate fingerprints only match the
+/// exact bytes a validator's software generated, which may change on upgrade.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidatorPins {
+    /// Identities whose key a validator's certificate may carry.
+    pub identities: HashSet<Pubkey>,
+    /// SHA-256 fingerprints of accepted certificates, in hex as
+    /// [`certificate_hash`] writes them.
+    pub fingerprints: HashSet<String>,
+}
+
+impl ValidatorPins {
+    /// Accepts validators with one of `identities`.
+    pub fn identities(identities: impl IntoIterator<Item = Pubkey>) -> Self {
+        Self {
+            identities: identities.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns `true` if `cert` carries a pinned identity or has a pinned
+    /// fingerprint.
+    pub fn allows(&self, cert: &CertificateDer<'_>) -> bool {
+        solana_tls_utils::get_pubkey_from_tls_certificate(cert)
+            .is_some_and(|identity| self.identities.contains(&identity))
+            || self.fingerprints.contains(&certificate_hash(cert))
+    }
+
+    /// Returns `false` if the validator with `identity` would be refused, so
+    /// no connection needs to be attempted. While fingerprints are pinned any
+    /// validator might pass, as its certificate is only seen in the handshake.
+    pub fn may_allow(&self, identity: &str) -> bool {
+        !self.fingerprints.is_empty()
+            || identity
+                .parse::<Pubkey>()
+                .is_ok_and(|identity| self.identities.contains(&identity))
+    }
+}
+
+/// Same as [`solana_tls_utils::tls_client_config_builder`], but only accepts
+/// the validators in `pins`.
+pub(crate) fn tls_client_config_builder(
+    pins: &ValidatorPins,
+) -> Result<ConfigBuilder<ClientConfig, WantsClientCert>> {
+    let verifier = PinnedServerVerification {
+        pins: pins.clone(),
+        provider: Arc::new(solana_tls_utils::crypto_provider()),
+    };
+    let builder = ClientConfig::builder_with_provider(verifier.provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Failed to select TLS protocol versions")?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier));
+    Ok(builder)
+}
+
+/// Server certificate verifier that refuses validators that aren't pinned.
+/// Like solana's own verifier, it still checks the handshake signatures.
+#[derive(Debug)]
+struct PinnedServerVerification {
+    pins: ValidatorPins,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedServerVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        if self.pins.allows(end_entity) {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        match solana_tls_utils::get_pubkey_from_tls_certificate(end_entity) {
+            Some(identity) => warn!("Refusing unpinned validator {}", identity),
+            None => warn!("Refusing validator without an identity certificate"),
+        }
+        Err(Error::InvalidCertificate(
+            CertificateError::ApplicationVerificationFailure,
+        ))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn test_pins_match_identity_or_fingerprint() {
+        let pinned = Keypair::new();
+        let (cert, _) = solana_tls_utils::new_dummy_x509_certificate(&pinned);
+        let (other, _) = solana_tls_utils::new_dummy_x509_certificate(&Keypair::new());
+
+        let pins = ValidatorPins::identities([pinned.pubkey()]);
+        assert!(pins.allows(&cert));
+        assert!(!pins.allows(&other));
+
+        let pins = ValidatorPins {
+            fingerprints: HashSet::from([certificate_hash(&other)]),
+            ..ValidatorPins::default()
+        };
+        assert!(pins.allows(&other));
+        assert!(!pins.allows(&cert));
+        assert!(!ValidatorPins::default().allows(&cert));
+    }
+
+    #[test]
+    fn test_may_allow_only_pinned_identities() {
+        let pinned = Pubkey::new_unique();
+        let pins = ValidatorPins::identities([pinned]);
+        assert!(pins.may_allow(&pinned.to_string()));
+        assert!(!pins.may_allow(&Pubkey::new_unique().to_string()));
+        assert!(!pins.may_allow("not an identity"));
+
+        let pins = ValidatorPins {
+            fingerprints: HashSet::from(["00".to_string()]),
+            ..pins
+        };
+        assert!(pins.may_allow(&Pubkey::new_unique().to_string()));
+    }
+}
@@ -0,0 +1,98 @@
+//! Ordering of the leaders a transaction is fanned out to.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::sync::Mutex;
+
+/// Orders fan-out candidates, breaking ties between equal-priority leaders
+/// randomly.
+///
+/// Production uses an entropy-seeded RNG. Tests can pass a fixed seed so the
+/// resulting order is reproducible across runs.
+#[derive(Debug)]
+pub struct LeaderSelector {
+    rng: Mutex<StdRng>,
+}
+
+impl LeaderSelector {
+    /// Creates a selector, seeded from `seed` if given or from OS entropy otherwise.
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Self {
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// Sorts `candidates` by `key`, shuffling candidates that share a key.
+    ///
+    /// This runs on every send, so the RNG is only locked and drawn from when
+    /// there is a tie to break.
+    pub fn order_by<T, K: Ord>(&self, mut candidates: Vec<T>, key: impl Fn(&T) -> K) -> Vec<T> {
+        candidates.sort_by_key(&key);
+
+        let mut rng = None;
+        for ties in candidates.chunk_by_mut(|a, b| key(a) == key(b)) {
+            if ties.len() > 1 {
+                let rng = rng.get_or_insert_with(|| self.rng.lock().unwrap());
+                ties.shuffle(&mut **rng);
+            }
+        }
+        candidates
+    }
+}
+
+impl Default for LeaderSelector {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<(String, u64)> {
+        (0..16).map(|i| (format!("leader-{}", i), i % 3)).collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_order() {
+        let first = LeaderSelector::new(Some(42)).order_by(candidates(), |(_, slot)| *slot);
+        let second = LeaderSelector::new(Some(42)).order_by(candidates(), |(_, slot)| *slot);
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_order_respects_key() {
+        let ordered = LeaderSelector::new(Some(7)).order_by(candidates(), |(_, slot)| *slot);
+        let slots: Vec<u64> = ordered.iter().map(|(_, slot)| *slot).collect();
+
+        let mut sorted = slots.clone();
+        sorted.sort();
+        assert_eq!(slots, sorted);
+    }
+
+    #[test]
+    fn test_only_ties_are_shuffled() {
+        let distinct: Vec<(String, u64)> = (0..16)
+            .rev()
+            .map(|i| (format!("leader-{}", i), i))
+            .collect();
+        let mut sorted = distinct.clone();
+        sorted.sort_by_key(|(_, slot)| *slot);
+
+        // Without ties nothing is drawn, so the next tie break is unaffected
+        let selector = LeaderSelector::new(Some(42));
+        assert_eq!(selector.order_by(distinct, |(_, slot)| *slot), sorted);
+        assert_eq!(
+            selector.order_by(candidates(), |(_, slot)| *slot),
+            LeaderSelector::new(Some(42)).order_by(candidates(), |(_, slot)| *slot)
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! TLS session tickets, used to tell which leaders can be reached with 0-RTT.
+
+use log::debug;
+use rustls::NamedGroup;
+use rustls::client::{
+    ClientSessionMemoryCache, ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue,
+};
+use rustls::pki_types::ServerName;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Servers sessions are cached for, same as rustls' default.
+const SESSION_CACHE_SIZE: usize = 256;
+/// TLS 1.3 tickets rustls' in-memory cache keeps per server.
+const MAX_TICKETS_PER_SERVER: usize = 8;
+
+/// TLS server name to connect to `socket` with.
+///
+/// Validators don't check the name, but rustls caches session tickets per
+/// name, so each leader needs its own to resume with 0-RTT.
+pub(crate) fn server_name(socket: &str) -> String {
+    let socket: String = socket
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("solana-{}", socket.trim_matches('-'))
+}
+
+/// In-memory session cache that also keeps count of the resumption tickets
+/// held for each server.
+///
+/// The counts are best-effort: the cache may evict a server's tickets on its
+/// own, and a server may reject a ticket that is still cached.
+#[derive(Debug)]
+pub struct TicketStore {
+    cache: ClientSessionMemoryCache,
+    tickets: Mutex<HashMap<String, usize>>,
+}
+
+impl TicketStore {
+    pub fn new() -> Self {
+        Self {
+            cache: ClientSessionMemoryCache::new(SESSION_CACHE_SIZE),
+            tickets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `true` if a resumption ticket is cached for `socket`, so the
+    /// next handshake to it can send 0-RTT data.
+    pub fn has_ticket(&self, socket: &str) -> bool {
+        self.tickets
+            .lock()
+            .unwrap()
+            .get(&server_name(socket))
+            .is_some_and(|count| *count > 0)
+    }
+
+    pub(crate) fn record_inserted(&self, server_name: &str) {
+        let mut tickets = self.tickets.lock().unwrap();
+        let count = tickets.entry(server_name.to_string()).or_default();
+        *count = (*count + 1).min(MAX_TICKETS_PER_SERVER);
+    }
+
+    fn record_taken(&self, server_name: &str, found: bool) {
+        let mut tickets = self.tickets.lock().unwrap();
+        match (found, tickets.get_mut(server_name)) {
+            (true, Some(count)) if *count > 1 => *count -= 1,
+            _ => {
+                tickets.remove(server_name);
+            }
+        }
+    }
+}
+
+impl Default for TicketStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientSessionStore for TicketStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.cache.set_kx_hint(server_name, group)
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.cache.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.cache.set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.cache.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.cache.remove_tls12_session(server_name)
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: Tls13ClientSessionValue,
+    ) {
+        debug!("Cached session ticket for {}", server_name.to_str());
+        self.record_inserted(&server_name.to_str());
+        self.cache.insert_tls13_ticket(server_name, value)
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        let ticket = self.cache.take_tls13_ticket(server_name);
+        self.record_taken(&server_name.to_str(), ticket.is_some());
+        ticket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_name_is_unique_per_socket() {
+        assert_eq!(server_name("10.0.0.1:8009"), "solana-10-0-0-1-8009");
+        assert_ne!(server_name("10.0.0.1:8009"), server_name("10.0.0.2:8009"));
+        assert!(ServerName::try_from(server_name("[2001:db8::1]:8009")).is_ok());
+    }
+
+    #[test]
+    fn test_has_ticket_follows_cached_tickets() {
+        let store = TicketStore::new();
+        let socket = "10.0.0.1:8009";
+        let name = server_name(socket);
+
+        assert!(!store.has_ticket(socket));
+
+        store.record_inserted(&name);
+        store.record_inserted(&name);
+        assert!(store.has_ticket(socket));
+        assert!(!store.has_ticket("10.0.0.2:8009"));
+
+        // Each handshake consumes one ticket
+        store.record_taken(&name, true);
+        assert!(store.has_ticket(socket));
+        store.record_taken(&name, true);
+        assert!(!store.has_ticket(socket));
+
+        // A miss means the cache evicted whatever was counted
+        store.record_inserted(&name);
+        store.record_taken(&name, false);
+        assert!(!store.has_ticket(socket));
+
+        // Nothing is cached in the real store, so it can't hand out a ticket
+        let server = ServerName::try_from(name).unwrap();
+        assert!(store.take_tls13_ticket(&server).is_none());
+    }
+}
@@ -0,0 +1,97 @@
+//! Exponential backoff for re-establishing the slot updates subscription.
+
+use std::time::Duration;
+
+/// Delay before the first reconnect attempt.
+pub const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(250);
+/// Upper bound the delay doubles up to.
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// A subscription that streamed at least this long resets the backoff.
+pub const RECONNECT_RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// Tracks consecutive subscription failures and the delay before the next retry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectBackoff {
+    initial: Duration,
+    max: Duration,
+    reset_after: Duration,
+    /// Delay that will be used for the next reconnect
+    next_delay: Duration,
+    /// Reconnects attempted since the last healthy subscription
+    attempts: u32,
+}
+
+impl ReconnectBackoff {
+    pub fn new(initial: Duration, max: Duration, reset_after: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            reset_after,
+            next_delay: initial,
+            attempts: 0,
+        }
+    }
+
+    /// Records that a subscription ended after streaming for `streamed_for`,
+    /// and returns how long to wait before reconnecting.
+    ///
+    /// A subscription that stayed up for at least `reset_after` is considered
+    /// healthy, so the backoff starts over from the initial delay.
+    pub fn on_disconnect(&mut self, streamed_for: Duration) -> Duration {
+        if streamed_for >= self.reset_after {
+            self.next_delay = self.initial;
+            self.attempts = 0;
+        }
+
+        let delay = self.next_delay;
+        self.next_delay = (delay * 2).min(self.max);
+        self.attempts += 1;
+        delay
+    }
+
+    /// Delay the next reconnect will wait for.
+    pub fn next_delay(&self) -> Duration {
+        self.next_delay
+    }
+
+    /// Number of reconnects since the subscription was last healthy.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self::new(
+            INITIAL_RECONNECT_DELAY,
+            MAX_RECONNECT_DELAY,
+            RECONNECT_RESET_AFTER,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doubles_to_cap_and_resets() {
+        let mut backoff = ReconnectBackoff::default();
+
+        let delays: Vec<Duration> = (0..10)
+            .map(|_| backoff.on_disconnect(Duration::ZERO))
+            .collect();
+        assert_eq!(delays[0], Duration::from_millis(250));
+        assert_eq!(delays[1], Duration::from_millis(500));
+        assert_eq!(delays[2], Duration::from_secs(1));
+        assert_eq!(delays[7], Duration::from_secs(30));
+        assert_eq!(delays[9], Duration::from_secs(30));
+        assert_eq!(backoff.attempts(), 10);
+
+        // A minute of streaming counts as a healthy connection
+        let delay = backoff.on_disconnect(Duration::from_secs(60));
+        assert_eq!(delay, Duration::from_millis(250));
+        assert_eq!(backoff.attempts(), 1);
+        assert_eq!(backoff.next_delay(), Duration::from_millis(500));
+    }
+}
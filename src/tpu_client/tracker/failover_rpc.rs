@@ -0,0 +1,174 @@
+//! JSON-RPC client spreading calls over several endpoints, so a single failing
+//! RPC node doesn't stall leader tracking.
+
+use anyhow::{Result, anyhow};
+use futures_util::future::BoxFuture;
+use log::warn;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Consecutive failures after which an endpoint is skipped.
+const FAILURE_THRESHOLD: u32 = 3;
+/// Time a failing endpoint is skipped for before it is tried again.
+const SKIP_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    skipped_until: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn is_skipped(&self, now: Instant) -> bool {
+        self.skipped_until.is_some_and(|until| now < until)
+    }
+
+    fn record(&mut self, succeeded: bool) {
+        if succeeded {
+            *self = Self::default();
+            return;
+        }
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= FAILURE_THRESHOLD {
+            self.skipped_until = Some(Instant::now() + SKIP_DURATION);
+        }
+    }
+}
+
+struct Endpoint {
+    client: RpcClient,
+    health: Mutex<EndpointHealth>,
+}
+
+/// Rotates calls round-robin over its endpoints and fails over to the next
+/// one when a call errors. Endpoints that failed [`FAILURE_THRESHOLD`] calls
+/// in a row are skipped for [`SKIP_DURATION`], unless every endpoint is.
+pub struct FailoverRpcClient {
+    endpoints: Vec<Endpoint>,
+    next: AtomicUsize,
+}
+
+impl FailoverRpcClient {
+    /// Creates a client for `urls`, which must not be empty.
+    pub fn new(urls: impl IntoIterator<Item = String>) -> Self {
+        Self::from_clients(urls.into_iter().map(RpcClient::new).collect())
+    }
+
+    pub(crate) fn from_clients(clients: Vec<RpcClient>) -> Self {
+        assert!(!clients.is_empty(), "at least one RPC endpoint is required");
+        Self {
+            endpoints: clients
+                .into_iter()
+                .map(|client| Endpoint {
+                    client,
+                    health: Mutex::new(EndpointHealth::default()),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Runs `call` against the endpoints, starting with the next one in
+    /// rotation, until one succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns the last endpoint's error if every endpoint failed.
+    pub async fn call<T>(
+        &self,
+        method: &str,
+        call: impl for<'c> Fn(&'c RpcClient) -> BoxFuture<'c, ClientResult<T>>,
+    ) -> Result<T> {
+        let mut last_error = None;
+        for index in self.attempt_order() {
+            let endpoint = &self.endpoints[index];
+            let result = call(&endpoint.client).await;
+            endpoint.health.lock().unwrap().record(result.is_ok());
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    warn!(
+                        "RPC call to {} on {} failed: {}",
+                        method,
+                        endpoint.client.url(),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        let e = last_error.expect("at least one endpoint is attempted");
+        Err(anyhow!(e).context(format!("RPC call to {} failed on every endpoint", method)))
+    }
+
+    /// Indices of the endpoints to try, healthy ones in rotation first, then
+    /// skipped ones in case they recovered.
+    fn attempt_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let (healthy, skipped): (Vec<usize>, Vec<usize>) = (0..self.endpoints.len())
+            .map(|offset| (start + offset) % self.endpoints.len())
+            .partition(|index| {
+                !self.endpoints[*index]
+                    .health
+                    .lock()
+                    .unwrap()
+                    .is_skipped(now)
+            });
+        healthy.into_iter().chain(skipped).collect()
+    }
+
+    /// URLs of the endpoints, in configuration order.
+    pub fn urls(&self) -> Vec<String> {
+        self.endpoints.iter().map(|e| e.client.url()).collect()
+    }
+}
+
+impl fmt::Debug for FailoverRpcClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FailoverRpcClient")
+            .field("urls", &self.urls())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[tokio::test]
+    async fn test_fails_over_to_next_endpoint() {
+        // The mock sender answers nothing on "fails" and canned data on "succeeds"
+        let rpc = FailoverRpcClient::from_clients(vec![
+            RpcClient::new_mock("fails".to_string()),
+            RpcClient::new_mock("succeeds".to_string()),
+        ]);
+
+        for _ in 0..FAILURE_THRESHOLD {
+            // Always start at the failing endpoint
+            rpc.next.store(0, Ordering::Relaxed);
+            let epoch_info = rpc
+                .call("get_epoch_info", |client| client.get_epoch_info().boxed())
+                .await
+                .unwrap();
+            assert!(epoch_info.slots_in_epoch > 0);
+        }
+
+        // The failing endpoint is now skipped, even when it is next in rotation
+        rpc.next.store(0, Ordering::Relaxed);
+        assert_eq!(rpc.attempt_order(), vec![1, 0]);
+
+        let failing =
+            FailoverRpcClient::from_clients(vec![RpcClient::new_mock("fails".to_string())]);
+        let err = failing
+            .call("get_epoch_info", |client| client.get_epoch_info().boxed())
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("failed on every endpoint"));
+    }
+}
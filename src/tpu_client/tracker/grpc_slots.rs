@@ -0,0 +1,116 @@
+//! Slot updates from a Geyser gRPC endpoint (Yellowstone), as a lower-latency
+//! alternative to the RPC node's WebSocket.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use log::warn;
+use solana_client::rpc_response::SlotUpdate;
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
+use yellowstone_grpc_proto::geyser::{SlotStatus, SubscribeRequest, SubscribeRequestFilterSlots};
+
+use crate::Slot;
+
+/// Name of the slots filter in the subscribe request.
+const SLOTS_FILTER: &str = "bifrost";
+
+/// Subscribes to slot notifications on `endpoint` and translates them into the
+/// [`SlotUpdate`]s the RPC WebSocket would have sent. The stream ends when the
+/// subscription is lost.
+pub(crate) async fn slot_stream(
+    endpoint: &str,
+    x_token: Option<&str>,
+) -> Result<BoxStream<'static, SlotUpdate>> {
+    let mut client = GeyserGrpcClient::build_from_shared(endpoint.to_string())
+        .context("Invalid gRPC endpoint")?
+        .x_token(x_token.map(str::to_string))
+        .context("Invalid gRPC x-token")?
+        .tls_config(ClientTlsConfig::new().with_native_roots())
+        .context("Failed to configure gRPC TLS")?
+        .connect()
+        .await
+        .context("Failed to connect to gRPC endpoint")?;
+
+    let request = SubscribeRequest {
+        slots: HashMap::from([(
+            SLOTS_FILTER.to_string(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(false),
+                // First shred and completed notifications are interslot updates
+                interslot_updates: Some(true),
+            },
+        )]),
+        ..Default::default()
+    };
+    let (requests, notifications) = client
+        .subscribe_with_request(Some(request))
+        .await
+        .context("Failed to subscribe to gRPC slot updates")?;
+
+    let updates = notifications
+        .take_while(|notification| {
+            let alive = notification.is_ok();
+            if let Err(status) = notification {
+                warn!("gRPC slot updates stream failed: {}", status);
+            }
+            std::future::ready(alive)
+        })
+        .filter_map(|notification| {
+            let update = match notification.ok().and_then(|n| n.update_oneof) {
+                Some(UpdateOneof::Slot(slot)) => SlotStatus::try_from(slot.status)
+                    .ok()
+                    .and_then(|status| slot_update(slot.slot, status)),
+                _ => None,
+            };
+            std::future::ready(update)
+        })
+        .boxed();
+
+    // Dropping the request sink ends the subscription, so it lives as long as
+    // the stream does
+    let updates = stream::unfold((updates, requests), |(mut updates, requests)| async move {
+        let update = updates.next().await?;
+        Some((update, (updates, requests)))
+    });
+    Ok(updates.boxed())
+}
+
+/// Maps a gRPC slot status onto the WebSocket update the slots tracker already
+/// turns into a slot start or end. Other statuses carry no timing information
+/// the tracker uses.
+fn slot_update(slot: Slot, status: SlotStatus) -> Option<SlotUpdate> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default();
+
+    match status {
+        SlotStatus::SlotFirstShredReceived => {
+            Some(SlotUpdate::FirstShredReceived { slot, timestamp })
+        }
+        SlotStatus::SlotCompleted => Some(SlotUpdate::Completed { slot, timestamp }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_update_maps_start_and_end() {
+        assert!(matches!(
+            slot_update(10, SlotStatus::SlotFirstShredReceived),
+            Some(SlotUpdate::FirstShredReceived { slot: 10, .. })
+        ));
+        assert!(matches!(
+            slot_update(10, SlotStatus::SlotCompleted),
+            Some(SlotUpdate::Completed { slot: 10, .. })
+        ));
+        assert!(slot_update(10, SlotStatus::SlotProcessed).is_none());
+        assert!(slot_update(10, SlotStatus::SlotConfirmed).is_none());
+    }
+}
@@ -1,18 +1,199 @@
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::ops::{Range, RangeInclusive};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use futures_util::stream::StreamExt;
-use log::{error, info, warn};
-use solana_client::nonblocking::pubsub_client::PubsubClient;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use tokio::sync::RwLock;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use solana_client::rpc_response::RpcContactInfo;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::{RwLock, watch};
 
-use crate::tpu_client::tracker::schedule_tracking::ScheduleTracker;
-use crate::tpu_client::tracker::slots_tracker::SlotsTracker;
+use crate::Slot;
+use crate::constants::SLOT_DURATION;
+use crate::tpu_client::tracker::backoff::ReconnectBackoff;
+use crate::tpu_client::tracker::schedule_tracking::{self, ScheduleTracker};
+use crate::tpu_client::tracker::slots_tracker::{SlotsTracker, TargetSlot};
+use crate::tpu_client::tracker::source::{LeaderSource, RpcLeaderSource};
 
 pub const RPC_URL: &str = "https://api.devnet.solana.com";
-const WS_RPC_URL: &str = "wss://api.devnet.solana.com/";
+pub const WS_RPC_URL: &str = "wss://api.devnet.solana.com/";
+/// Default of [`LeaderTrackerConfig::stale_after`]
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(10);
+
+/// Cluster endpoints the tracker fetches schedules, sockets and slots from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderTrackerConfig {
+    /// JSON-RPC endpoint used for epoch info, leader schedules and cluster nodes
+    pub rpc_url: String,
+    /// Further JSON-RPC endpoints serving the same cluster. Calls rotate over
+    /// them and `rpc_url`, and fail over to the next one on error.
+    pub fallback_rpc_urls: Vec<String>,
+    /// WebSocket endpoint used for the slot updates subscription
+    pub ws_url: String,
+    /// Where slot updates come from
+    pub slot_source: SlotSource,
+    /// File the leader schedules are cached in across restarts, so a cold
+    /// start within the same epoch skips fetching them. Disabled if `None`.
+    pub schedule_cache: Option<PathBuf>,
+    /// Time without the current slot advancing after which the tracker is
+    /// considered stale, e.g. because the slot subscription dropped and can't
+    /// reconnect. A stale tracker fails readiness and transactions are refused
+    /// rather than sent to leaders that may be long gone. `None` never
+    /// considers it stale.
+    pub stale_after: Option<Duration>,
+}
+
+impl Default for LeaderTrackerConfig {
+    /// Devnet endpoints.
+    fn default() -> Self {
+        Self {
+            rpc_url: RPC_URL.to_string(),
+            fallback_rpc_urls: Vec::new(),
+            ws_url: WS_RPC_URL.to_string(),
+            slot_source: SlotSource::default(),
+            schedule_cache: None,
+            stale_after: Some(DEFAULT_STALE_AFTER),
+        }
+    }
+}
+
+/// Feed the current slot is estimated from. Schedules and cluster nodes are
+/// always read over JSON-RPC.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SlotSource {
+    /// `slotsUpdatesSubscribe` on the RPC node's WebSocket at `ws_url`
+    #[default]
+    Rpc,
+    /// Slot notifications from a Geyser gRPC (Yellowstone) endpoint, which
+    /// arrive sooner and aren't subject to the RPC node's rate limits.
+    /// Requires the `grpc` feature.
+    Grpc {
+        /// gRPC endpoint, e.g. `https://grpc.example.com:10000`
+        endpoint: String,
+        /// Access token sent as `x-token`, if the endpoint requires one
+        x_token: Option<String>,
+    },
+}
+
+/// Returns the TPU QUIC address advertised by `node`.
+///
+/// Nodes that advertise an unspecified TPU IP are reached on their gossip IP instead.
+fn tpu_socket(node: &RpcContactInfo) -> Option<SocketAddr> {
+    reachable_socket(node, node.tpu_quic?)
+}
+
+/// Returns the legacy UDP TPU address advertised by `node`, the same way as
+/// [`tpu_socket`].
+fn tpu_udp_socket(node: &RpcContactInfo) -> Option<SocketAddr> {
+    reachable_socket(node, node.tpu?)
+}
+
+fn reachable_socket(node: &RpcContactInfo, socket: SocketAddr) -> Option<SocketAddr> {
+    let ip = if socket.ip().is_unspecified() {
+        node.gossip?.ip()
+    } else {
+        socket.ip()
+    };
+
+    Some(SocketAddr::new(ip, socket.port()))
+}
+
+/// Difference between two refreshes of the leader sockets, keyed by
+/// validator identity.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SocketChanges {
+    /// Validators seen for the first time, with their socket.
+    pub added: Vec<(String, String)>,
+    /// Validators whose socket moved, as `(identity, old, new)`.
+    pub changed: Vec<(String, String, String)>,
+    /// Validators no longer advertised, with their last known socket.
+    pub removed: Vec<(String, String)>,
+}
+
+impl SocketChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Previous sockets of validators that moved. Connections pooled for them
+    /// no longer reach the leader they were opened for.
+    pub fn stale_sockets(&self) -> impl Iterator<Item = &str> {
+        self.changed.iter().map(|(_, old, _)| old.as_str())
+    }
+}
+
+/// Computes what changed from `old` to `new`, sorted by identity. Sockets are
+/// reported in the form the connection pool is keyed by.
+fn diff_sockets(
+    old: &HashMap<String, SocketAddr>,
+    new: &HashMap<String, SocketAddr>,
+) -> SocketChanges {
+    let mut changes = SocketChanges::default();
+
+    for (identity, socket) in new {
+        match old.get(identity) {
+            None => changes.added.push((identity.clone(), socket.to_string())),
+            Some(previous) if previous != socket => {
+                changes
+                    .changed
+                    .push((identity.clone(), previous.to_string(), socket.to_string()))
+            }
+            Some(_) => {}
+        }
+    }
+    for (identity, socket) in old {
+        if !new.contains_key(identity) {
+            changes.removed.push((identity.clone(), socket.to_string()));
+        }
+    }
+
+    changes.added.sort();
+    changes.changed.sort();
+    changes.removed.sort();
+    changes
+}
+
+/// Sent to the [`LeaderTracker::on_epoch_rotation`] subscriber once the
+/// tracker moved on to a new epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochRotation {
+    /// First slot of the epoch that just began
+    pub new_epoch_start: Slot,
+    pub slots_in_epoch: u64,
+}
+
+/// The tracker's view of the cluster, for diagnosing a stuck slot
+/// subscription or incomplete socket discovery.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrackerStatus {
+    /// Latest slot seen, 0 before the first slot update
+    pub current_slot: Slot,
+    pub curr_epoch_slot_start: Slot,
+    pub next_epoch_slot_start: Slot,
+    pub slots_in_epoch: u64,
+    /// Leaders whose TPU socket is known
+    pub known_sockets: usize,
+    /// Whether the current epoch's leader schedule is known
+    pub schedule_loaded: bool,
+    /// Whether the current slot stopped advancing, see
+    /// [`LeaderTrackerConfig::stale_after`]
+    pub stale: bool,
+}
+
+impl TrackerStatus {
+    /// Whether leaders can be resolved: a slot was seen recently, the
+    /// schedule is loaded and some leader sockets are known.
+    pub fn is_ready(&self) -> bool {
+        self.current_slot != 0 && !self.stale && self.schedule_loaded && self.known_sockets > 0
+    }
+}
 
 /**
  * We have 3 actions that are needed in order to track leaders properly:
@@ -29,35 +210,229 @@ const WS_RPC_URL: &str = "wss://api.devnet.solana.com/";
  * and the IPs can change anytime, this way we are not locking the schedule while doing ip updates.
 **/
 
+/// Generic over where the cluster data comes from, see [`LeaderSource`]. The
+/// default reads it from a validator's RPC endpoints.
 #[derive(Debug)]
-pub struct LeaderTracker {
+pub struct LeaderTracker<S = RpcLeaderSource> {
     pub slots_tracker: RwLock<SlotsTracker>,
     schedule_tracker: RwLock<ScheduleTracker>,
-    leader_sockets: RwLock<HashMap<String, String>>,
+    leader_sockets: RwLock<HashMap<String, SocketAddr>>,
+    /// Legacy UDP TPU addresses by identity, replaced on every refresh
+    udp_sockets: RwLock<HashMap<String, SocketAddr>>,
+    /// Flips to `true` once the first slot update has been recorded
+    ready: watch::Sender<bool>,
+    /// Backoff state of the slot updates subscription supervisor
+    reconnect: Mutex<ReconnectBackoff>,
+    source: S,
+    /// File the schedules are saved to whenever the next epoch's is fetched
+    schedule_cache: Option<PathBuf>,
+    /// See [`LeaderTrackerConfig::stale_after`]
+    stale_after: Option<Duration>,
+    /// See [`on_epoch_rotation`](Self::on_epoch_rotation)
+    epoch_rotations: Mutex<Option<mpsc::Sender<EpochRotation>>>,
 }
 
 impl LeaderTracker {
-    pub async fn new() -> Result<Self> {
-        let rpc_client = RpcClient::new(RPC_URL.to_string());
+    /// Creates a tracker that reads the cluster from the RPC endpoints in
+    /// `config`.
+    pub async fn new(config: LeaderTrackerConfig) -> Result<Self> {
+        let source = RpcLeaderSource::new(&config);
+        let mut tracker = Self::with_source_cached(source, config.schedule_cache).await?;
+        tracker.stale_after = config.stale_after;
+        Ok(tracker)
+    }
+
+    /// Creates an RPC-backed tracker around an already-initialized schedule,
+    /// with no slot or socket information yet.
+    pub(crate) fn with_schedule(
+        config: LeaderTrackerConfig,
+        schedule_tracker: ScheduleTracker,
+    ) -> Self {
+        let mut tracker = Self::from_parts(RpcLeaderSource::new(&config), schedule_tracker);
+        tracker.stale_after = config.stale_after;
+        tracker
+    }
+}
 
-        let schedule_tracker = ScheduleTracker::new(&rpc_client)
+impl<S: LeaderSource> LeaderTracker<S> {
+    /// Creates a tracker that reads the cluster from `source`.
+    pub async fn with_source(source: S) -> Result<Self> {
+        Self::with_source_cached(source, None).await
+    }
+
+    /// Same as [`with_source`](Self::with_source), caching the leader
+    /// schedules in `schedule_cache`, see [`LeaderTrackerConfig::schedule_cache`].
+    pub async fn with_source_cached(source: S, schedule_cache: Option<PathBuf>) -> Result<Self> {
+        let schedule_tracker = ScheduleTracker::load_or_fetch(&source, schedule_cache.as_deref())
             .await
             .context("Failed to initialize schedule tracker")?;
 
-        Ok(Self {
+        let mut tracker = Self::from_parts(source, schedule_tracker);
+        tracker.schedule_cache = schedule_cache;
+        Ok(tracker)
+    }
+
+    /// Creates a tracker around an already-initialized schedule, with no slot
+    /// or socket information yet.
+    pub(crate) fn from_parts(source: S, schedule_tracker: ScheduleTracker) -> Self {
+        Self {
+            source,
             slots_tracker: RwLock::new(SlotsTracker::new()),
             schedule_tracker: RwLock::new(schedule_tracker),
             leader_sockets: RwLock::new(HashMap::new()),
-        })
+            udp_sockets: RwLock::new(HashMap::new()),
+            ready: watch::Sender::new(false),
+            reconnect: Mutex::new(ReconnectBackoff::default()),
+            schedule_cache: None,
+            stale_after: Some(DEFAULT_STALE_AFTER),
+            epoch_rotations: Mutex::new(None),
+        }
+    }
+
+    /// Returns `true` once the current slot is known.
+    pub fn is_ready(&self) -> bool {
+        *self.ready.borrow()
+    }
+
+    /// Subscribes to readiness changes.
+    pub fn subscribe_ready(&self) -> watch::Receiver<bool> {
+        self.ready.subscribe()
+    }
+
+    /// Notifies `sender` of every epoch rotation from now on, replacing any
+    /// previous subscriber, e.g. for embedders that cache per-epoch data.
+    ///
+    /// Notifications are sent without waiting: one that finds the channel
+    /// full is dropped, and a dropped receiver unsubscribes. Neither affects
+    /// the tracker.
+    pub fn on_epoch_rotation(&self, sender: mpsc::Sender<EpochRotation>) {
+        *self.epoch_rotations.lock().unwrap() = Some(sender);
+    }
+
+    /// Sends `rotation` to the subscriber, if any.
+    fn notify_epoch_rotation(&self, rotation: EpochRotation) {
+        let mut epoch_rotations = self.epoch_rotations.lock().unwrap();
+        let Some(sender) = epoch_rotations.as_ref() else {
+            return;
+        };
+
+        match sender.try_send(rotation) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    "Epoch rotation subscriber lagging, dropped rotation to slot {}",
+                    rotation.new_epoch_start
+                );
+            }
+            Err(TrySendError::Closed(_)) => {
+                debug!("Epoch rotation subscriber went away");
+                *epoch_rotations = None;
+            }
+        }
+    }
+
+    /// Time the current slot has not advanced for, if that is longer than
+    /// [`LeaderTrackerConfig::stale_after`]. `None` while the tracker is
+    /// current or before any slot is known.
+    pub async fn staleness(&self) -> Option<Duration> {
+        let age = self.slots_tracker.read().await.time_in_current_slot()?;
+        self.is_stale(age).then_some(age)
+    }
+
+    fn is_stale(&self, slot_age: Duration) -> bool {
+        self.stale_after
+            .is_some_and(|stale_after| slot_age > stale_after)
+    }
+
+    /// Returns the current slot, the epoch bounds and how many leader sockets
+    /// are known.
+    pub async fn status(&self) -> TrackerStatus {
+        // Acquire all locks together for consistent view
+        let slot_tracker = self.slots_tracker.read().await;
+        let schedule_tracker = self.schedule_tracker.read().await;
+        let leader_sockets = self.leader_sockets.read().await;
+
+        TrackerStatus {
+            current_slot: slot_tracker.current_slot(),
+            curr_epoch_slot_start: schedule_tracker.current_epoch_slot_start(),
+            next_epoch_slot_start: schedule_tracker.next_epoch_slot_start(),
+            slots_in_epoch: schedule_tracker.slots_in_epoch(),
+            known_sockets: leader_sockets.len(),
+            schedule_loaded: !schedule_tracker.curr_schedule_ref().is_empty(),
+            stale: slot_tracker
+                .time_in_current_slot()
+                .is_some_and(|age| self.is_stale(age)),
+        }
+    }
+
+    /// Estimated time left in the current slot, assuming slots last
+    /// [`SLOT_DURATION`]. `None` before any slot is known.
+    pub async fn slot_time_remaining(&self) -> Option<Duration> {
+        let elapsed = self.slots_tracker.read().await.time_in_current_slot()?;
+        Some(SLOT_DURATION.saturating_sub(elapsed))
+    }
+
+    /// Identity of the leader of the absolute `slot`, from the current or the
+    /// next epoch's schedule. `None` outside of those two epochs, or while the
+    /// next epoch's schedule isn't fetched yet.
+    pub async fn leader_at_slot(&self, slot: Slot) -> Option<String> {
+        self.schedule_tracker
+            .read()
+            .await
+            .leader_at_slot(slot)
+            .map(str::to_string)
+    }
+
+    /// Returns the current reconnect backoff state of [`run_supervised`](Self::run_supervised).
+    pub fn reconnect_backoff(&self) -> ReconnectBackoff {
+        self.reconnect.lock().unwrap().clone()
     }
 
+    /// Returns the distinct leaders with a known socket of the slots `start`
+    /// to `end` (exclusive) ahead of the current one. A leader of several of
+    /// those slots is returned once, with the soonest slot it leads.
+    ///
+    /// Output = Vec<(leader identity, leader socket, slot it leads)>
     pub async fn get_future_leaders(&self, start: u64, end: u64) -> Vec<(String, String, u64)> {
+        self.get_future_leaders_for(TargetSlot::LeadingEdge, start, end)
+            .await
+    }
+
+    /// Same as [`get_future_leaders`](Self::get_future_leaders), counting the
+    /// offsets from the slot selected by `target` instead of the current slot.
+    pub async fn get_future_leaders_for(
+        &self,
+        target: TargetSlot,
+        start: u64,
+        end: u64,
+    ) -> Vec<(String, String, u64)> {
+        self.collect_leaders(target, start..end, usize::MAX).await
+    }
+
+    /// Returns up to `amount` distinct leaders with a known socket, in
+    /// schedule order from the current slot, looking as far ahead as needed
+    /// within the current and next epoch.
+    ///
+    /// Output = Vec<(leader identity, leader socket, slot it leads)>
+    pub async fn get_next_leaders(&self, amount: usize) -> Vec<(String, String, u64)> {
+        self.collect_leaders(TargetSlot::LeadingEdge, 0..u64::MAX, amount)
+            .await
+    }
+
+    /// Collects the distinct leaders with a known socket of the slots at
+    /// `offsets` from the slot selected by `target`, stopping after `limit`.
+    async fn collect_leaders(
+        &self,
+        target: TargetSlot,
+        offsets: Range<u64>,
+        limit: usize,
+    ) -> Vec<(String, String, u64)> {
         // Acquire all locks together for consistent view
         let slot_tracker = self.slots_tracker.read().await;
         let schedule_tracker = self.schedule_tracker.read().await;
         let leader_sockets = self.leader_sockets.read().await;
 
-        let curr_slot = slot_tracker.current_slot();
+        let curr_slot = slot_tracker.target_slot(target);
 
         if curr_slot == 0 {
             return vec![];
@@ -79,33 +454,33 @@ impl LeaderTracker {
         let mut leaders = Vec::new();
         let mut seen = HashSet::new();
 
-        for i in start..end {
+        for i in offsets {
+            if leaders.len() >= limit {
+                break;
+            }
+
             let target_slot = match curr_slot.checked_add(i) {
                 Some(s) => s,
                 None => break, // Overflow protection
             };
 
-            // Skip if out of current epoch range
-            if target_slot >= schedule_tracker.next_epoch_slot_start() {
+            // Slots near the end of the epoch continue into the next epoch's
+            // schedule, stop where no schedule is known
+            if target_slot >= schedule_tracker.known_slot_end() {
                 break;
             }
 
-            // Convert absolute slot to epoch-relative index
-            let slot_index = match schedule_tracker.slot_to_index(target_slot) {
-                Some(idx) => idx,
-                None => continue,
-            };
-
             // Get leader for this slot
-            if let Some(leader_pubkey) = schedule_tracker.get_leader_for_slot_index(slot_index) {
-                // Deduplicate - only add each leader once
+            if let Some(leader_pubkey) = schedule_tracker.leader_at_slot(target_slot) {
+                // Deduplicate - only add each leader once, at its soonest slot
                 if !seen.insert(leader_pubkey.to_string()) {
                     continue;
                 }
 
                 match leader_sockets.get(leader_pubkey) {
                     Some(socket) => {
-                        leaders.push((leader_pubkey.to_string(), socket.clone(), curr_slot));
+                        // Display brackets IPv6 addresses, so this parses back
+                        leaders.push((leader_pubkey.to_string(), socket.to_string(), target_slot));
                     }
                     None => {
                         warn!("Leader {} has no known socket address", leader_pubkey);
@@ -119,47 +494,193 @@ impl LeaderTracker {
 
     /// Get the current leader, and next leader if close to leader switch
     ///
-    /// Output = Vec<(leader identity, leader socket, current slot)>
+    /// Output = Vec<(leader identity, leader socket, slot it leads)>
     pub async fn get_leaders(&self) -> Vec<(String, String, u64)> {
         self.get_future_leaders(0, 2).await
     }
 
-    /// Get all cluster node leader IPs
-    pub async fn update_leader_sockets(leader_tracker: Arc<LeaderTracker>) -> Result<()> {
-        let rpc_client = RpcClient::new(RPC_URL.to_string());
+    /// Same as [`get_leaders`](Self::get_leaders), resolved from the slot selected by `target`
+    pub async fn get_leaders_for(&self, target: TargetSlot) -> Vec<(String, String, u64)> {
+        self.get_future_leaders_for(target, 0, 2).await
+    }
+
+    /// Returns the leaders of the next `slots_ahead` slots, starting at the
+    /// current one, with the contiguous slot range each of them leads.
+    ///
+    /// Unlike [`get_future_leaders`](Self::get_future_leaders) this does not
+    /// deduplicate: a leader appears once per run of consecutive slots. The
+    /// socket is `None` if the leader's TPU address is unknown. Only the current
+    /// epoch is covered.
+    pub async fn upcoming_leader_schedule(
+        &self,
+        slots_ahead: u64,
+    ) -> Vec<(Pubkey, RangeInclusive<Slot>, Option<SocketAddr>)> {
+        // Acquire all locks together for consistent view
+        let slot_tracker = self.slots_tracker.read().await;
+        let schedule_tracker = self.schedule_tracker.read().await;
+        let leader_sockets = self.leader_sockets.read().await;
+
+        let curr_slot = slot_tracker.current_slot();
+        if curr_slot == 0 {
+            return vec![];
+        }
+
+        let end_slot = curr_slot
+            .saturating_add(slots_ahead)
+            .min(schedule_tracker.next_epoch_slot_start());
+
+        let mut schedule: Vec<(Pubkey, RangeInclusive<Slot>, Option<SocketAddr>)> = Vec::new();
+
+        for slot in curr_slot..end_slot {
+            let Some(leader) = schedule_tracker
+                .slot_to_index(slot)
+                .and_then(|idx| schedule_tracker.get_leader_for_slot_index(idx))
+            else {
+                continue;
+            };
+
+            let pubkey = match Pubkey::from_str(leader) {
+                Ok(pubkey) => pubkey,
+                Err(_) => {
+                    warn!("Invalid leader identity in schedule: {}", leader);
+                    continue;
+                }
+            };
+
+            // Extend the previous run if the same leader continues it
+            if let Some((last, range, _)) = schedule.last_mut()
+                && *last == pubkey
+                && *range.end() + 1 == slot
+            {
+                *range = *range.start()..=slot;
+                continue;
+            }
+
+            let socket = leader_sockets.get(leader).copied();
+            schedule.push((pubkey, slot..=slot, socket));
+        }
+
+        schedule
+    }
 
-        let nodes = rpc_client
-            .get_cluster_nodes()
+    /// Get all cluster node leader IPs
+    ///
+    /// Only entries that differ from the previous refresh are touched, and the
+    /// differences are returned so callers can invalidate connections to
+    /// sockets that moved.
+    pub async fn update_leader_sockets(leader_tracker: Arc<Self>) -> Result<SocketChanges> {
+        let nodes = leader_tracker
+            .source
+            .cluster_nodes()
             .await
             .context("Failed to fetch cluster nodes")?;
 
         let mut new_sockets = HashMap::new();
+        let mut udp_sockets = HashMap::new();
 
         for node in nodes {
-            if let (Some(tpu_quic), Some(gossip)) = (node.tpu_quic, node.gossip) {
-                new_sockets.insert(
-                    node.pubkey.to_string(),
-                    format!("{}:{}", gossip.ip(), tpu_quic.port()),
-                );
+            if let Some(socket) = tpu_socket(&node) {
+                new_sockets.insert(node.pubkey.to_string(), socket);
+            }
+            if let Some(socket) = tpu_udp_socket(&node) {
+                udp_sockets.insert(node.pubkey.to_string(), socket);
             }
         }
 
         info!("Updated sockets for {} validators", new_sockets.len());
+        *leader_tracker.udp_sockets.write().await = udp_sockets;
 
-        let mut sockets = leader_tracker.leader_sockets.write().await;
-        *sockets = new_sockets; // Move instead of clone
+        Ok(leader_tracker.apply_leader_sockets(new_sockets).await)
+    }
 
-        Ok(())
+    /// Brings the known sockets in line with `new_sockets`, updating only the
+    /// entries that differ, and returns those differences.
+    pub(crate) async fn apply_leader_sockets(
+        &self,
+        new_sockets: HashMap<String, SocketAddr>,
+    ) -> SocketChanges {
+        let mut sockets = self.leader_sockets.write().await;
+        let changes = diff_sockets(&sockets, &new_sockets);
+
+        for (identity, socket) in &changes.added {
+            debug!("Leader {} added at {}", identity, socket);
+            sockets.insert(identity.clone(), new_sockets[identity]);
+        }
+        for (identity, old, new) in &changes.changed {
+            info!("Leader {} moved from {} to {}", identity, old, new);
+            sockets.insert(identity.clone(), new_sockets[identity]);
+        }
+        for (identity, socket) in &changes.removed {
+            debug!("Leader {} at {} removed", identity, socket);
+            sockets.remove(identity);
+        }
+
+        info!(
+            "Leader sockets: {} added, {} changed, {} removed",
+            changes.added.len(),
+            changes.changed.len(),
+            changes.removed.len()
+        );
+
+        changes
     }
 
-    /// Run the slot updates listener
-    pub async fn run(leader_tracker: Arc<LeaderTracker>) -> Result<()> {
-        let ws_client = PubsubClient::new(WS_RPC_URL)
-            .await
-            .context("Failed to connect to WebSocket")?;
+    /// Runs the slot updates listener forever, reconnecting with exponential
+    /// backoff whenever the subscription fails or ends.
+    ///
+    /// Each attempt goes through [`run`](Self::run), so the WebSocket client and
+    /// subscription are recreated on every retry.
+    pub async fn run_supervised(leader_tracker: Arc<Self>) {
+        loop {
+            let started = Instant::now();
+
+            match Self::run(leader_tracker.clone()).await {
+                Ok(()) => warn!("Slot updates stream ended"),
+                Err(e) => error!("Slot updates listener failed: {:#}", e),
+            }
+
+            let (delay, attempt) = {
+                let mut backoff = leader_tracker.reconnect.lock().unwrap();
+                let delay = backoff.on_disconnect(started.elapsed());
+                (delay, backoff.attempts())
+            };
+
+            info!(
+                "Reconnecting slot updates in {:?} (attempt {})",
+                delay, attempt
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Returns the legacy UDP TPU address `identity` advertises, if any.
+    pub async fn udp_socket(&self, identity: &str) -> Option<SocketAddr> {
+        self.udp_sockets.read().await.get(identity).copied()
+    }
+
+    /// Replaces the known UDP TPU addresses, for tests that run without RPC.
+    #[cfg(test)]
+    pub(crate) async fn set_udp_sockets(&self, sockets: HashMap<String, SocketAddr>) {
+        *self.udp_sockets.write().await = sockets;
+    }
+
+    /// Replaces the known leader sockets, for tests that run without RPC.
+    #[cfg(test)]
+    pub(crate) async fn set_leader_sockets(&self, sockets: HashMap<String, String>) {
+        *self.leader_sockets.write().await = sockets
+            .into_iter()
+            .map(|(identity, socket)| (identity, socket.parse().unwrap()))
+            .collect();
+    }
 
-        let (mut slot_notifications, _unsubscribe) = ws_client
-            .slot_updates_subscribe()
+    /// Run the slot updates listener
+    ///
+    /// Ends when the source's slot stream does. Dropping the returned future
+    /// drops the stream, which ends the subscription.
+    pub async fn run(leader_tracker: Arc<Self>) -> Result<()> {
+        let mut slot_notifications = leader_tracker
+            .source
+            .slot_stream()
             .await
             .context("Failed to subscribe to slot updates")?;
 
@@ -176,8 +697,8 @@ impl LeaderTracker {
     }
 
     /// Handles a single slot update event.
-    async fn handle_slot_event(
-        leader_tracker: &Arc<LeaderTracker>,
+    pub(crate) async fn handle_slot_event(
+        leader_tracker: &Arc<Self>,
         slot_event: solana_client::rpc_response::SlotUpdate,
     ) -> Result<()> {
         // Record the slot event and get updated slot number
@@ -189,7 +710,12 @@ impl LeaderTracker {
             }
         };
 
-        // Check if we need to rotate to next epoch
+        leader_tracker
+            .ready
+            .send_if_modified(|ready| !std::mem::replace(ready, true));
+
+        // Cheap check under the read lock, repeated by the rotation under the
+        // write lock
         let needs_rotation = {
             let schedule_tracker = leader_tracker.schedule_tracker.read().await;
             curr_slot >= schedule_tracker.next_epoch_slot_start()
@@ -202,49 +728,684 @@ impl LeaderTracker {
         Ok(())
     }
 
-    /// Rotates the schedule to the next epoch and fetches the new next_schedule.
-    async fn rotate_epoch(leader_tracker: &Arc<LeaderTracker>, curr_slot: u64) -> Result<()> {
-        let rpc_client = RpcClient::new(RPC_URL.to_string());
-
+    /// Rotates the schedule to the next epoch and spawns the fetch of the new
+    /// next_schedule, so slot updates keep flowing while the RPC call runs.
+    ///
+    /// Does nothing if `curr_slot` is no longer past the current epoch, i.e.
+    /// a concurrent event already rotated it.
+    async fn rotate_epoch(leader_tracker: &Arc<Self>, curr_slot: u64) -> Result<()> {
         let mut schedule_tracker = leader_tracker.schedule_tracker.write().await;
 
-        
+        let previous_epoch_slot_start = schedule_tracker.current_epoch_slot_start();
+        if !schedule_tracker.rotate(curr_slot) {
+            debug!(
+                "Epoch at slot {} was already rotated to",
+                schedule_tracker.current_epoch_slot_start()
+            );
+            return Ok(());
+        }
         info!(
-            "Rotating epoch: {} -> {}",
-            schedule_tracker.current_epoch_slot_start(),
-            schedule_tracker.next_epoch_slot_start()
+            "Rotated epoch: {} -> {}",
+            previous_epoch_slot_start,
+            schedule_tracker.current_epoch_slot_start()
         );
 
-        // Use the built-in rotation method
-        match schedule_tracker.maybe_rotate(curr_slot, &rpc_client).await {
-            Ok(true) => {
-                info!("Successfully rotated to next epoch");
-            }
-            Ok(false) => {
-                // Shouldn't happen since we checked needs_rotation, but handle it
-                warn!("Rotation not needed despite check");
+        tokio::spawn(Self::fetch_next_schedule(
+            leader_tracker.clone(),
+            schedule_tracker.next_epoch_slot_start(),
+        ));
+
+        leader_tracker.notify_epoch_rotation(EpochRotation {
+            new_epoch_start: schedule_tracker.current_epoch_slot_start(),
+            slots_in_epoch: schedule_tracker.slots_in_epoch(),
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the schedule cache, if enabled, with the current schedules,
+    /// which invalidates the previous epoch's.
+    async fn save_schedule_cache(&self) {
+        let Some(path) = &self.schedule_cache else {
+            return;
+        };
+
+        let cache = self.schedule_tracker.read().await.to_cache();
+        let saved = match cache {
+            Ok(bytes) => schedule_tracking::write_cache(path, bytes).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            warn!("Failed to write leader schedule cache: {:#}", e);
+        }
+    }
+
+    /// Fetches the schedule of the epoch starting at `epoch_slot_start`,
+    /// retrying with backoff until it succeeds or that epoch has begun.
+    async fn fetch_next_schedule(leader_tracker: Arc<Self>, epoch_slot_start: u64) {
+        let mut backoff = ReconnectBackoff::default();
+
+        loop {
+            match ScheduleTracker::fetch_schedule(&leader_tracker.source, epoch_slot_start).await {
+                Ok(schedule) => {
+                    let mut schedule_tracker = leader_tracker.schedule_tracker.write().await;
+                    if schedule_tracker.set_next_schedule(epoch_slot_start, schedule) {
+                        info!(
+                            "Fetched leader schedule for epoch at slot {}",
+                            epoch_slot_start
+                        );
+                        drop(schedule_tracker);
+                        leader_tracker.save_schedule_cache().await;
+                    }
+                    return;
+                }
+                Err(e) => error!(
+                    "Failed to fetch next epoch schedule at slot {}: {:#}",
+                    epoch_slot_start, e
+                ),
             }
-            Err(e) => {
-                error!("Failed to rotate epoch: {}", e);
-                return Err(e).context("Epoch rotation failed");
+
+            let delay = backoff.on_disconnect(Duration::ZERO);
+            tokio::time::sleep(delay).await;
+
+            let schedule_tracker = leader_tracker.schedule_tracker.read().await;
+            if schedule_tracker.next_epoch_slot_start() != epoch_slot_start {
+                warn!(
+                    "Epoch at slot {} began before its schedule was fetched",
+                    epoch_slot_start
+                );
+                return;
             }
         }
-
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tpu_client::tracker::mock_source::MockLeaderSource;
+    use crate::tpu_client::tracker::schedule_tracking::ScheduleTracker;
+    use solana_client::rpc_response::SlotUpdate;
     use std::time::Duration;
     use tokio::time::sleep;
 
+    fn contact_info(gossip: &str, tpu_quic: &str) -> RpcContactInfo {
+        RpcContactInfo {
+            pubkey: Pubkey::new_from_array([1; 32]).to_string(),
+            gossip: Some(gossip.parse().unwrap()),
+            tvu: None,
+            tpu: None,
+            tpu_quic: Some(tpu_quic.parse().unwrap()),
+            tpu_forwards: None,
+            tpu_forwards_quic: None,
+            tpu_vote: None,
+            serve_repair: None,
+            rpc: None,
+            pubsub: None,
+            version: None,
+            feature_set: None,
+            shred_version: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_leader_sockets_reports_changes() {
+        let tracker = LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 32, HashMap::new(), HashMap::new()),
+        );
+        let sockets = |entries: &[(&str, &str)]| -> HashMap<String, SocketAddr> {
+            entries
+                .iter()
+                .map(|(identity, socket)| (identity.to_string(), socket.parse().unwrap()))
+                .collect()
+        };
+
+        tracker
+            .apply_leader_sockets(sockets(&[
+                ("a", "10.0.0.1:8009"),
+                ("b", "10.0.0.2:8009"),
+                ("c", "10.0.0.3:8009"),
+            ]))
+            .await;
+
+        let changes = tracker
+            .apply_leader_sockets(sockets(&[
+                ("a", "10.0.0.1:8009"),
+                ("b", "10.0.0.22:8009"),
+                ("d", "10.0.0.4:8009"),
+            ]))
+            .await;
+
+        assert_eq!(
+            changes.changed,
+            vec![(
+                "b".to_string(),
+                "10.0.0.2:8009".to_string(),
+                "10.0.0.22:8009".to_string()
+            )]
+        );
+        assert_eq!(
+            changes.added,
+            vec![("d".to_string(), "10.0.0.4:8009".to_string())]
+        );
+        assert_eq!(
+            changes.removed,
+            vec![("c".to_string(), "10.0.0.3:8009".to_string())]
+        );
+        assert_eq!(
+            changes.stale_sockets().collect::<Vec<_>>(),
+            ["10.0.0.2:8009"]
+        );
+
+        assert_eq!(
+            *tracker.leader_sockets.read().await,
+            sockets(&[
+                ("a", "10.0.0.1:8009"),
+                ("b", "10.0.0.22:8009"),
+                ("d", "10.0.0.4:8009"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tpu_socket_uses_tpu_ip() {
+        let node = contact_info("10.0.0.1:8001", "203.0.113.5:8009");
+        assert_eq!(tpu_socket(&node), Some("203.0.113.5:8009".parse().unwrap()));
+
+        // Unspecified TPU IP falls back to gossip
+        let node = contact_info("10.0.0.1:8001", "0.0.0.0:8009");
+        assert_eq!(tpu_socket(&node), Some("10.0.0.1:8009".parse().unwrap()));
+
+        // IPv6 nodes, including ones falling back to their gossip IP
+        let node = contact_info("[2001:db8::1]:8001", "[2001:db8::5]:8009");
+        assert_eq!(
+            tpu_socket(&node),
+            Some("[2001:db8::5]:8009".parse().unwrap())
+        );
+        let node = contact_info("[2001:db8::1]:8001", "[::]:8009");
+        assert_eq!(
+            tpu_socket(&node),
+            Some("[2001:db8::1]:8009".parse().unwrap())
+        );
+
+        let node = RpcContactInfo {
+            tpu_quic: None,
+            ..contact_info("10.0.0.1:8001", "0.0.0.0:8009")
+        };
+        assert_eq!(tpu_socket(&node), None);
+
+        // The UDP port is read the same way, and only if advertised
+        assert_eq!(tpu_udp_socket(&node), None);
+        let node = RpcContactInfo {
+            tpu: Some("0.0.0.0:8003".parse().unwrap()),
+            ..node
+        };
+        assert_eq!(
+            tpu_udp_socket(&node),
+            Some("10.0.0.1:8003".parse().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_target_slot_resolves_different_leaders() {
+        // One leader per 4-slot window: slots 100-103 -> leader-25, 104-107 -> leader-26
+        let schedule = (0..200).map(|i| (i, format!("leader-{}", i / 4))).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 200, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(
+                (0..50)
+                    .map(|i| (format!("leader-{}", i), format!("10.0.0.{}:8009", i)))
+                    .collect(),
+            )
+            .await;
+
+        for slot in 100..=105 {
+            let event = SlotUpdate::FirstShredReceived { slot, timestamp: 0 };
+            LeaderTracker::handle_slot_event(&tracker, event)
+                .await
+                .unwrap();
+        }
+        let event = SlotUpdate::Completed {
+            slot: 100,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let leading = tracker.get_leaders_for(TargetSlot::LeadingEdge).await;
+        let completed = tracker.get_leaders_for(TargetSlot::Completed).await;
+
+        assert_eq!(leading[0].0, "leader-26");
+        assert_eq!(leading[0].2, 105);
+        assert_eq!(completed[0].0, "leader-25");
+        assert_eq!(completed[0].2, 101);
+    }
+
+    #[tokio::test]
+    async fn test_next_leaders_looks_ahead_until_amount() {
+        // One leader per 4-slot window, only every other one with a known socket
+        let schedule = (0..200).map(|i| (i, format!("leader-{}", i / 4))).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 200, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(
+                (0..50)
+                    .step_by(2)
+                    .map(|i| (format!("leader-{}", i), format!("10.0.0.{}:8009", i)))
+                    .collect(),
+            )
+            .await;
+
+        assert!(tracker.get_next_leaders(3).await.is_empty());
+
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 100,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let identities: Vec<String> = tracker
+            .get_next_leaders(3)
+            .await
+            .into_iter()
+            .map(|(identity, _, _)| identity)
+            .collect();
+        assert_eq!(identities, vec!["leader-26", "leader-28", "leader-30"]);
+
+        // Never past the end of the known schedules
+        assert_eq!(tracker.get_next_leaders(100).await.len(), 12);
+    }
+
+    #[tokio::test]
+    async fn test_future_leaders_keep_their_soonest_slot() {
+        // leader-a leads the windows on either side of leader-b
+        let schedule = (0..200)
+            .map(|i| {
+                (
+                    i,
+                    if i / 4 % 2 == 0 {
+                        "leader-a"
+                    } else {
+                        "leader-b"
+                    }
+                    .to_string(),
+                )
+            })
+            .collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 200, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(HashMap::from([
+                ("leader-a".to_string(), "10.0.0.1:8009".to_string()),
+                ("leader-b".to_string(), "10.0.0.2:8009".to_string()),
+            ]))
+            .await;
+
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 10,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        // Slots 10-21: leader-a at 10-11, leader-b at 12-15, leader-a again
+        let leaders = tracker.get_future_leaders(0, 12).await;
+        assert_eq!(
+            leaders,
+            vec![
+                ("leader-a".to_string(), "10.0.0.1:8009".to_string(), 10),
+                ("leader-b".to_string(), "10.0.0.2:8009".to_string(), 12),
+            ]
+        );
+        assert_eq!(tracker.get_future_leaders(2, 12).await[0].2, 12);
+    }
+
+    #[tokio::test]
+    async fn test_future_leaders_span_epoch_boundary() {
+        let curr = (0..200).map(|i| (i, format!("leader-{}", i / 4))).collect();
+        let next = (0..200).map(|i| (i, format!("next-{}", i / 4))).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 200, curr, next),
+        ));
+        tracker
+            .set_leader_sockets(
+                ["leader-49", "next-0", "next-1"]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, identity)| (identity.to_string(), format!("10.0.0.{}:8009", i)))
+                    .collect(),
+            )
+            .await;
+
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 198,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        // Slots 198-205 cover the last leader of this epoch and the first two
+        // of the next
+        let leaders: Vec<(String, u64)> = tracker
+            .get_future_leaders(0, 8)
+            .await
+            .into_iter()
+            .map(|(identity, _, slot)| (identity, slot))
+            .collect();
+        assert_eq!(
+            leaders,
+            vec![
+                ("leader-49".to_string(), 198),
+                ("next-0".to_string(), 200),
+                ("next-1".to_string(), 204),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_epoch_rotation_does_not_wait_for_rpc() {
+        let config = LeaderTrackerConfig {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            ..LeaderTrackerConfig::default()
+        };
+        let next = (0..200).map(|i| (i, "leader-next".to_string())).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            config,
+            ScheduleTracker::from_schedules(0, 200, HashMap::new(), next),
+        ));
+
+        // The next epoch's schedule can't be fetched, but the rotation itself
+        // completes right away
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 200,
+            timestamp: 0,
+        };
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            LeaderTracker::handle_slot_event(&tracker, event),
+        )
+        .await
+        .expect("rotation blocked on the schedule fetch")
+        .unwrap();
+
+        let status = tracker.status().await;
+        assert_eq!(status.curr_epoch_slot_start, 200);
+        assert_eq!(status.next_epoch_slot_start, 400);
+    }
+
+    #[tokio::test]
+    async fn test_epoch_rotation_notifies_subscriber() {
+        let config = LeaderTrackerConfig {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            ..LeaderTrackerConfig::default()
+        };
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            config,
+            ScheduleTracker::from_schedules(0, 200, HashMap::new(), HashMap::new()),
+        ));
+        let (sender, mut rotations) = mpsc::channel(1);
+        tracker.on_epoch_rotation(sender);
+
+        let rotate = |slot| {
+            let event = SlotUpdate::FirstShredReceived { slot, timestamp: 0 };
+            LeaderTracker::handle_slot_event(&tracker, event)
+        };
+        // Slots advance one at a time, so none is dropped as an outlier
+        rotate(199).await.unwrap();
+        assert!(rotations.try_recv().is_err());
+
+        rotate(200).await.unwrap();
+        assert_eq!(
+            rotations.try_recv().unwrap(),
+            EpochRotation {
+                new_epoch_start: 200,
+                slots_in_epoch: 200,
+            }
+        );
+
+        // A subscriber that went away doesn't stop the tracker from rotating
+        drop(rotations);
+        for slot in 201..=400 {
+            rotate(slot).await.unwrap();
+        }
+        assert_eq!(tracker.status().await.curr_epoch_slot_start, 400);
+        assert!(tracker.epoch_rotations.lock().unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_events_rotate_epoch_once() {
+        let config = LeaderTrackerConfig {
+            rpc_url: "http://127.0.0.1:1".to_string(),
+            ..LeaderTrackerConfig::default()
+        };
+        let next = (0..200).map(|i| (i, "leader-next".to_string())).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            config,
+            ScheduleTracker::from_schedules(0, 200, HashMap::new(), next),
+        ));
+
+        // Both events see the boundary crossed under the read lock, but only
+        // the first to take the write lock rotates
+        let rotations = [200, 201].map(|slot| {
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                let event = SlotUpdate::FirstShredReceived { slot, timestamp: 0 };
+                LeaderTracker::handle_slot_event(&tracker, event).await
+            })
+        });
+        for rotation in rotations {
+            rotation.await.unwrap().unwrap();
+        }
+
+        let status = tracker.status().await;
+        assert_eq!(status.curr_epoch_slot_start, 200);
+        assert_eq!(status.next_epoch_slot_start, 400);
+        assert_eq!(
+            tracker.leader_at_slot(201).await.as_deref(),
+            Some("leader-next")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_reflects_slot_and_sockets() {
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 432_000, HashMap::new(), HashMap::new()),
+        ));
+
+        let status = tracker.status().await;
+        assert_eq!(status.current_slot, 0);
+        assert_eq!(status.known_sockets, 0);
+
+        tracker
+            .set_leader_sockets(HashMap::from([(
+                "leader-1".to_string(),
+                "10.0.0.1:8009".to_string(),
+            )]))
+            .await;
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 5,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let status = tracker.status().await;
+        assert_eq!(
+            status,
+            TrackerStatus {
+                current_slot: 5,
+                curr_epoch_slot_start: 0,
+                next_epoch_slot_start: 432_000,
+                slots_in_epoch: 432_000,
+                known_sockets: 1,
+                schedule_loaded: false,
+                stale: false,
+            }
+        );
+        assert!(!status.is_ready());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tracker_goes_stale_without_slot_updates() {
+        let config = LeaderTrackerConfig {
+            stale_after: Some(Duration::from_secs(5)),
+            ..LeaderTrackerConfig::default()
+        };
+        let schedule = (0..100).map(|i| (i, "leader-1".to_string())).collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            config,
+            ScheduleTracker::from_schedules(0, 100, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(HashMap::from([(
+                "leader-1".to_string(),
+                "10.0.0.1:8009".to_string(),
+            )]))
+            .await;
+        let advance_to = |slot| {
+            let event = SlotUpdate::FirstShredReceived { slot, timestamp: 0 };
+            LeaderTracker::handle_slot_event(&tracker, event)
+        };
+
+        advance_to(5).await.unwrap();
+        assert!(tracker.status().await.is_ready());
+        assert_eq!(tracker.staleness().await, None);
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        let status = tracker.status().await;
+        assert!(status.stale);
+        assert!(!status.is_ready());
+        assert!(tracker.staleness().await.unwrap() >= Duration::from_secs(6));
+
+        // Repeating the same slot doesn't count as progress
+        advance_to(5).await.unwrap();
+        assert!(tracker.staleness().await.is_some());
+
+        advance_to(6).await.unwrap();
+        assert!(tracker.status().await.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_upcoming_leader_schedule_ranges() {
+        let leaders: Vec<Pubkey> = (0..3u8).map(|i| Pubkey::new_from_array([i; 32])).collect();
+
+        // Slots 0-7 -> leaders[0], 8-11 -> leaders[1], 12-15 -> leaders[2], then repeating
+        let schedule = (0..100)
+            .map(|i: usize| {
+                let leader = match i % 16 {
+                    0..=7 => leaders[0],
+                    8..=11 => leaders[1],
+                    _ => leaders[2],
+                };
+                (i, leader.to_string())
+            })
+            .collect();
+        let tracker = Arc::new(LeaderTracker::with_schedule(
+            LeaderTrackerConfig::default(),
+            ScheduleTracker::from_schedules(0, 100, schedule, HashMap::new()),
+        ));
+        tracker
+            .set_leader_sockets(HashMap::from([(
+                leaders[1].to_string(),
+                "10.0.0.1:8009".to_string(),
+            )]))
+            .await;
+
+        let event = SlotUpdate::FirstShredReceived {
+            slot: 5,
+            timestamp: 0,
+        };
+        LeaderTracker::handle_slot_event(&tracker, event)
+            .await
+            .unwrap();
+
+        let upcoming = tracker.upcoming_leader_schedule(20).await;
+
+        assert_eq!(
+            upcoming,
+            vec![
+                (leaders[0], 5..=7, None),
+                (leaders[1], 8..=11, Some("10.0.0.1:8009".parse().unwrap())),
+                (leaders[2], 12..=15, None),
+                (leaders[0], 16..=23, None),
+                (leaders[1], 24..=24, Some("10.0.0.1:8009".parse().unwrap())),
+            ]
+        );
+
+        // Ranges tile the requested window without gaps
+        for pair in upcoming.windows(2) {
+            assert_eq!(*pair[0].1.end() + 1, *pair[1].1.start());
+        }
+
+        // Clamped to the end of the epoch
+        let upcoming = tracker.upcoming_leader_schedule(1_000).await;
+        assert_eq!(*upcoming.last().unwrap().1.end(), 99);
+
+        assert_eq!(
+            tracker.leader_at_slot(24).await,
+            Some(leaders[1].to_string())
+        );
+        // The next epoch's schedule isn't known
+        assert_eq!(tracker.leader_at_slot(100).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_tracks_leaders_from_source() {
+        let source = MockLeaderSource::new(8, 64, 20).with_slot_updates(vec![
+            SlotUpdate::FirstShredReceived {
+                slot: 21,
+                timestamp: 0,
+            },
+        ]);
+        let expected: Vec<(String, String)> = [5, 6, 7]
+            .into_iter()
+            .map(|i| (source.leader(i), source.socket(i)))
+            .collect();
+        assert_eq!(source.leader_of(21), expected[0].0);
+
+        let tracker = Arc::new(LeaderTracker::with_source(source).await.unwrap());
+        let changes = LeaderTracker::update_leader_sockets(tracker.clone())
+            .await
+            .unwrap();
+        assert_eq!(changes.added.len(), 8);
+        // No slot seen yet
+        assert!(!tracker.status().await.is_ready());
+
+        // Ends with the mock's slot stream
+        LeaderTracker::run(tracker.clone()).await.unwrap();
+
+        let leaders = tracker.get_future_leaders(0, 8).await;
+        let slots: Vec<u64> = leaders.iter().map(|(_, _, slot)| *slot).collect();
+        assert_eq!(slots, vec![21, 24, 28]);
+        let leaders: Vec<(String, String)> = leaders
+            .into_iter()
+            .map(|(identity, socket, _)| (identity, socket))
+            .collect();
+        assert_eq!(leaders, expected);
+        assert!(tracker.status().await.is_ready());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_get_rpc_leader_schedule() {
         let leader_tracker = Arc::new(
-            LeaderTracker::new()
+            LeaderTracker::new(LeaderTrackerConfig::default())
                 .await
                 .expect("Failed to initialize LeaderTracker"),
         );
@@ -0,0 +1,111 @@
+//! Deterministic stand-in for the cluster, so leader tracking can be tested
+//! without network access.
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use solana_client::rpc_response::{RpcContactInfo, RpcLeaderSchedule, SlotUpdate};
+use solana_sdk::epoch_info::EpochInfo;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::Slot;
+use crate::tpu_client::tracker::source::LeaderSource;
+
+/// Slots each leader leads in a row, as on mainnet.
+const SLOTS_PER_LEADER: usize = 4;
+
+/// Cluster of `leaders` validators that take turns in schedule order, each for
+/// [`SLOTS_PER_LEADER`] slots, in epochs of `slots_in_epoch` slots.
+#[derive(Debug)]
+pub(crate) struct MockLeaderSource {
+    leaders: Vec<Pubkey>,
+    slots_in_epoch: u64,
+    current_slot: Slot,
+    /// Slot updates handed out by the next `slot_stream`
+    slot_updates: Mutex<Vec<SlotUpdate>>,
+}
+
+impl MockLeaderSource {
+    pub(crate) fn new(leaders: usize, slots_in_epoch: u64, current_slot: Slot) -> Self {
+        Self {
+            leaders: (0..leaders)
+                .map(|i| Pubkey::new_from_array([i as u8 + 1; 32]))
+                .collect(),
+            slots_in_epoch,
+            current_slot,
+            slot_updates: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queues slot updates for the next `slot_stream`.
+    pub(crate) fn with_slot_updates(self, updates: Vec<SlotUpdate>) -> Self {
+        *self.slot_updates.lock().unwrap() = updates;
+        self
+    }
+
+    /// Identity of the `index`-th leader in schedule order.
+    pub(crate) fn leader(&self, index: usize) -> String {
+        self.leaders[index % self.leaders.len()].to_string()
+    }
+
+    /// TPU socket the `index`-th leader advertises.
+    pub(crate) fn socket(&self, index: usize) -> String {
+        format!("10.0.0.{}:8009", index % self.leaders.len() + 1)
+    }
+
+    /// Identity of the leader of `slot`.
+    pub(crate) fn leader_of(&self, slot: Slot) -> String {
+        let slot_index = (slot % self.slots_in_epoch) as usize;
+        self.leader(slot_index / SLOTS_PER_LEADER)
+    }
+}
+
+impl LeaderSource for MockLeaderSource {
+    async fn epoch_info(&self) -> Result<EpochInfo> {
+        Ok(EpochInfo {
+            epoch: self.current_slot / self.slots_in_epoch,
+            slot_index: self.current_slot % self.slots_in_epoch,
+            slots_in_epoch: self.slots_in_epoch,
+            absolute_slot: self.current_slot,
+            block_height: self.current_slot,
+            transaction_count: None,
+        })
+    }
+
+    async fn leader_schedule(&self, _slot: Slot) -> Result<Option<RpcLeaderSchedule>> {
+        // Every epoch has the same schedule
+        let mut schedule = RpcLeaderSchedule::new();
+        for slot_index in 0..self.slots_in_epoch as usize {
+            let leader = self.leader(slot_index / SLOTS_PER_LEADER);
+            schedule.entry(leader).or_default().push(slot_index);
+        }
+        Ok(Some(schedule))
+    }
+
+    async fn cluster_nodes(&self) -> Result<Vec<RpcContactInfo>> {
+        Ok((0..self.leaders.len())
+            .map(|i| RpcContactInfo {
+                pubkey: self.leader(i),
+                gossip: None,
+                tvu: None,
+                tpu: None,
+                tpu_quic: self.socket(i).parse().ok(),
+                tpu_forwards: None,
+                tpu_forwards_quic: None,
+                tpu_vote: None,
+                serve_repair: None,
+                rpc: None,
+                pubsub: None,
+                version: None,
+                feature_set: None,
+                shred_version: None,
+            })
+            .collect())
+    }
+
+    async fn slot_stream(&self) -> Result<BoxStream<'static, SlotUpdate>> {
+        let updates = std::mem::take(&mut *self.slot_updates.lock().unwrap());
+        Ok(stream::iter(updates).boxed())
+    }
+}
@@ -1,3 +1,10 @@
+pub mod backoff;
+pub mod failover_rpc;
+#[cfg(feature = "grpc")]
+mod grpc_slots;
 pub mod leader_tracker;
+#[cfg(test)]
+pub(crate) mod mock_source;
 pub mod schedule_tracking;
 pub mod slots_tracker;
+pub mod source;
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use anyhow::{Context, Result, ensure};
-use solana_client::nonblocking::rpc_client::RpcClient;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 
+use crate::tpu_client::tracker::source::LeaderSource;
 
 #[derive(Debug)]
 pub struct ScheduleTracker {
@@ -20,14 +23,23 @@ impl ScheduleTracker {
     /// # Errors
     ///
     /// Returns an error if:
-    /// - RPC connection fails
+    /// - The source can't be reached
     /// - Epoch info is invalid
     /// - Leader schedule fetch fails
-    pub async fn new(rpc_client: &RpcClient) -> Result<Self> {
-        let epoch_info = rpc_client
-            .get_epoch_info()
+    pub async fn new(source: &impl LeaderSource) -> Result<Self> {
+        Self::load_or_fetch(source, None).await
+    }
+
+    /// Same as [`new`](Self::new), but reads the schedules from the cache file
+    /// at `cache` if it holds the current epoch's, and writes them there
+    /// after fetching them otherwise. Only the epoch info is fetched on a hit.
+    ///
+    /// An unreadable or stale cache falls back to fetching.
+    pub async fn load_or_fetch(source: &impl LeaderSource, cache: Option<&Path>) -> Result<Self> {
+        let epoch_info = source
+            .epoch_info()
             .await
-            .context("Failed to fetch epoch info from RPC")?;
+            .context("Failed to fetch epoch info")?;
 
         // Validate epoch info
         ensure!(
@@ -47,53 +59,134 @@ impl ScheduleTracker {
         let curr_epoch_slot_start = epoch_info.absolute_slot - epoch_info.slot_index;
         let next_epoch_slot_start = curr_epoch_slot_start + epoch_info.slots_in_epoch;
 
+        if let Some(path) = cache {
+            match Self::load_cache(path, curr_epoch_slot_start, epoch_info.slots_in_epoch).await {
+                Ok(Some(tracker)) => {
+                    info!("Loaded leader schedules from {}", path.display());
+                    return Ok(tracker);
+                }
+                Ok(None) => info!("Leader schedule cache {} is stale", path.display()),
+                Err(e) => warn!("Ignoring leader schedule cache: {:#}", e),
+            }
+        }
+
         // Fetch both schedules
-        let curr_schedule = Self::fetch_schedule(rpc_client, curr_epoch_slot_start)
+        let curr_schedule = Self::fetch_schedule(source, curr_epoch_slot_start)
             .await
             .context("Failed to fetch current epoch schedule")?;
 
-        let next_schedule = Self::fetch_schedule(rpc_client, next_epoch_slot_start)
+        let next_schedule = Self::fetch_schedule(source, next_epoch_slot_start)
             .await
             .context("Failed to fetch next epoch schedule")?;
 
-        Ok(Self {
+        let tracker = Self {
             curr_epoch_slot_start,
             next_epoch_slot_start,
             curr_schedule,
             next_schedule,
             slots_in_epoch: epoch_info.slots_in_epoch,
-        })
+        };
+
+        if let Some(path) = cache
+            && let Err(e) = tracker.save(path).await
+        {
+            warn!("Failed to write leader schedule cache: {:#}", e);
+        }
+
+        Ok(tracker)
+    }
+
+    /// Reads the cache file at `path`, returning `None` if it holds another
+    /// epoch than the one starting at `curr_epoch_slot_start`.
+    async fn load_cache(
+        path: &Path,
+        curr_epoch_slot_start: u64,
+        slots_in_epoch: u64,
+    ) -> Result<Option<Self>> {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+        let cache: ScheduleCache =
+            bincode::deserialize(&bytes).context(format!("Failed to parse {}", path.display()))?;
+
+        if cache.curr_epoch_slot_start != curr_epoch_slot_start
+            || cache.slots_in_epoch != slots_in_epoch
+            || cache.curr_schedule.is_empty()
+            || cache.next_schedule.is_empty()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::from_schedules(
+            curr_epoch_slot_start,
+            slots_in_epoch,
+            by_slot_index(cache.curr_schedule),
+            by_slot_index(cache.next_schedule),
+        )))
+    }
+
+    /// Writes the epoch boundaries and both schedules to the cache file at
+    /// `path`.
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        write_cache(path, self.to_cache()?).await
+    }
+
+    /// Serializes the epoch boundaries and both schedules for the cache file.
+    pub fn to_cache(&self) -> Result<Vec<u8>> {
+        let cache = ScheduleCache {
+            curr_epoch_slot_start: self.curr_epoch_slot_start,
+            slots_in_epoch: self.slots_in_epoch,
+            curr_schedule: by_leader(&self.curr_schedule),
+            next_schedule: by_leader(&self.next_schedule),
+        };
+        bincode::serialize(&cache).context("Failed to serialize leader schedules")
+    }
+
+    /// Creates a ScheduleTracker from already-known schedules.
+    ///
+    /// # Arguments
+    ///
+    /// * `curr_epoch_slot_start` - First slot of the current epoch
+    /// * `slots_in_epoch` - Number of slots per epoch
+    /// * `curr_schedule` - Slot index -> leader for the current epoch
+    /// * `next_schedule` - Slot index -> leader for the next epoch
+    pub fn from_schedules(
+        curr_epoch_slot_start: u64,
+        slots_in_epoch: u64,
+        curr_schedule: HashMap<usize, String>,
+        next_schedule: HashMap<usize, String>,
+    ) -> Self {
+        Self {
+            curr_epoch_slot_start,
+            next_epoch_slot_start: curr_epoch_slot_start + slots_in_epoch,
+            curr_schedule,
+            next_schedule,
+            slots_in_epoch,
+        }
     }
 
     /// Fetches the leader schedule for a given epoch.
     ///
     /// # Arguments
     ///
-    /// * `rpc_client` - The RPC client to use
+    /// * `source` - Where to read the schedule from
     /// * `slot` - The first slot of the epoch
     ///
     /// # Returns
     ///
     /// A HashMap mapping slot indices to validator pubkeys
     pub async fn fetch_schedule(
-        rpc_client: &RpcClient,
+        source: &impl LeaderSource,
         slot: u64,
     ) -> Result<HashMap<usize, String>> {
-        let leader_schedule = rpc_client
-            .get_leader_schedule(Some(slot))
-            .await
-            .context("RPC call to get_leader_schedule failed")?
+        let leader_schedule = source
+            .leader_schedule(slot)
+            .await?
             .context(format!("No leader schedule available for slot {}", slot))?;
 
-        // Convert from RPC format: {pubkey: [slot_indices]}
-        // to our format: {slot_index: pubkey}
-        let mut schedule = HashMap::with_capacity(leader_schedule.len() * 4);
-
-        for (pubkey, slot_indices) in leader_schedule {
-            for &slot_index in &slot_indices {
-                schedule.insert(slot_index, pubkey.clone());
-            }
-        }
+        let schedule = by_slot_index(leader_schedule);
 
         ensure!(
             !schedule.is_empty(),
@@ -108,6 +201,32 @@ impl ScheduleTracker {
         self.curr_schedule.get(&slot_index).map(|s| s.as_str())
     }
 
+    /// Returns the leader of the absolute `slot`, looking it up in the next
+    /// epoch's schedule if it falls past the current epoch.
+    ///
+    /// Returns `None` before the current epoch, past the next one, or while
+    /// the next epoch's schedule isn't fetched yet.
+    pub fn leader_at_slot(&self, slot: u64) -> Option<&str> {
+        if slot < self.next_epoch_slot_start {
+            let index = self.slot_to_index(slot)?;
+            return self.get_leader_for_slot_index(index);
+        }
+
+        let index = slot - self.next_epoch_slot_start;
+        if index >= self.slots_in_epoch {
+            return None;
+        }
+        self.next_schedule
+            .get(&(index as usize))
+            .map(|s| s.as_str())
+    }
+
+    /// First slot past the epochs whose schedule is known, i.e. the end of
+    /// the next epoch.
+    pub fn known_slot_end(&self) -> u64 {
+        self.next_epoch_slot_start + self.slots_in_epoch
+    }
+
     pub fn current_epoch_slot_start(&self) -> u64 {
         self.curr_epoch_slot_start
     }
@@ -128,23 +247,50 @@ impl ScheduleTracker {
     pub async fn maybe_rotate(
         &mut self,
         current_slot: u64,
-        rpc_client: &RpcClient,
+        source: &impl LeaderSource,
     ) -> Result<bool> {
+        if !self.rotate(current_slot) {
+            return Ok(false);
+        }
+
+        // Fetch new next epoch schedule
+        self.next_schedule = Self::fetch_schedule(source, self.next_epoch_slot_start)
+            .await
+            .context("Failed to fetch next epoch schedule after rotation")?;
+
+        Ok(true)
+    }
+
+    /// Rotates to the next epoch without fetching anything, leaving the next
+    /// schedule empty until [`set_next_schedule`](Self::set_next_schedule).
+    ///
+    /// Returns `false` if `current_slot` is still in the current epoch.
+    pub fn rotate(&mut self, current_slot: u64) -> bool {
         if current_slot < self.next_epoch_slot_start {
-            return Ok(false); // Still in current epoch
+            return false; // Still in current epoch
         }
 
-        // Rotate to next epoch
         self.curr_epoch_slot_start = self.next_epoch_slot_start;
         self.next_epoch_slot_start += self.slots_in_epoch;
         self.curr_schedule = std::mem::take(&mut self.next_schedule);
 
-        // Fetch new next epoch schedule
-        self.next_schedule = Self::fetch_schedule(rpc_client, self.next_epoch_slot_start)
-            .await
-            .context("Failed to fetch next epoch schedule after rotation")?;
+        true
+    }
 
-        Ok(true)
+    /// Installs the schedule of the epoch starting at `epoch_slot_start`, if
+    /// that is still the next epoch. Returns `false` if the tracker rotated
+    /// past it in the meantime.
+    pub fn set_next_schedule(
+        &mut self,
+        epoch_slot_start: u64,
+        schedule: HashMap<usize, String>,
+    ) -> bool {
+        if epoch_slot_start != self.next_epoch_slot_start {
+            return false;
+        }
+
+        self.next_schedule = schedule;
+        true
     }
 
     /// Converts an absolute slot number to a slot index within the current epoch.
@@ -175,6 +321,56 @@ impl ScheduleTracker {
     }
 }
 
+/// On-disk form of a [`ScheduleTracker`], keyed by the epoch it was fetched in.
+#[derive(Serialize, Deserialize)]
+struct ScheduleCache {
+    curr_epoch_slot_start: u64,
+    slots_in_epoch: u64,
+    /// Schedules in the RPC format, which is far smaller than ours
+    curr_schedule: HashMap<String, Vec<usize>>,
+    next_schedule: HashMap<String, Vec<usize>>,
+}
+
+/// Writes a cache file, replacing the previous one only once the new one is
+/// complete.
+pub async fn write_cache(path: &Path, bytes: Vec<u8>) -> Result<()> {
+    let partial = path.with_extension("partial");
+    tokio::fs::write(&partial, bytes)
+        .await
+        .context(format!("Failed to write {}", partial.display()))?;
+    tokio::fs::rename(&partial, path)
+        .await
+        .context(format!("Failed to replace {}", path.display()))
+}
+
+/// Converts from the RPC format, {pubkey: [slot_indices]}, to ours,
+/// {slot_index: pubkey}.
+fn by_slot_index(leader_schedule: HashMap<String, Vec<usize>>) -> HashMap<usize, String> {
+    let mut schedule = HashMap::with_capacity(leader_schedule.len() * 4);
+
+    for (pubkey, slot_indices) in leader_schedule {
+        for &slot_index in &slot_indices {
+            schedule.insert(slot_index, pubkey.clone());
+        }
+    }
+
+    schedule
+}
+
+/// Converts from our format back to the RPC one.
+fn by_leader(schedule: &HashMap<usize, String>) -> HashMap<String, Vec<usize>> {
+    let mut leader_schedule: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for (&slot_index, pubkey) in schedule {
+        leader_schedule
+            .entry(pubkey.clone())
+            .or_default()
+            .push(slot_index);
+    }
+
+    leader_schedule
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,4 +391,82 @@ mod tests {
         assert_eq!(tracker.slot_to_index(999), None); // Before epoch
         assert_eq!(tracker.slot_to_index(1432), None); // After epoch
     }
+
+    #[test]
+    fn test_rotate_then_set_next_schedule() {
+        let next = HashMap::from([(0, "leader-2".to_string())]);
+        let mut tracker = ScheduleTracker::from_schedules(
+            1000,
+            432,
+            HashMap::from([(0, "leader-1".to_string())]),
+            next,
+        );
+
+        assert!(!tracker.rotate(1431));
+        assert!(tracker.rotate(1432));
+        assert_eq!(tracker.current_epoch_slot_start(), 1432);
+        assert_eq!(tracker.next_epoch_slot_start(), 1864);
+        assert_eq!(tracker.get_leader_for_slot_index(0), Some("leader-2"));
+
+        // A schedule for an epoch that is no longer next is dropped
+        assert!(!tracker.set_next_schedule(1432, HashMap::new()));
+        let schedule = HashMap::from([(0, "leader-3".to_string())]);
+        assert!(tracker.set_next_schedule(1864, schedule));
+
+        assert!(tracker.rotate(1864));
+        assert_eq!(tracker.get_leader_for_slot_index(0), Some("leader-3"));
+    }
+
+    #[test]
+    fn test_leader_for_slot_spans_next_epoch() {
+        let tracker = ScheduleTracker::from_schedules(
+            1000,
+            432,
+            HashMap::from([(431, "leader-1".to_string())]),
+            HashMap::from([(0, "leader-2".to_string()), (431, "leader-3".to_string())]),
+        );
+
+        assert_eq!(tracker.leader_at_slot(999), None);
+        assert_eq!(tracker.leader_at_slot(1431), Some("leader-1"));
+        assert_eq!(tracker.leader_at_slot(1432), Some("leader-2"));
+        assert_eq!(tracker.leader_at_slot(1863), Some("leader-3"));
+        assert_eq!(tracker.leader_at_slot(1864), None);
+        assert_eq!(tracker.known_slot_end(), 1864);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_cache_is_keyed_by_epoch() {
+        let path =
+            std::env::temp_dir().join(format!("bifrost-schedule-cache-{}.bin", std::process::id()));
+        let schedule = |leader: &str| (0..432).map(|i| (i, leader.to_string())).collect();
+        let tracker =
+            ScheduleTracker::from_schedules(1000, 432, schedule("leader-1"), schedule("leader-2"));
+        write_cache(&path, tracker.to_cache().unwrap())
+            .await
+            .unwrap();
+
+        let loaded = ScheduleTracker::load_cache(&path, 1000, 432)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.curr_schedule, tracker.curr_schedule);
+        assert_eq!(loaded.next_schedule, tracker.next_schedule);
+        assert_eq!(loaded.next_epoch_slot_start(), 1432);
+
+        // The epoch advanced since the cache was written
+        assert!(
+            ScheduleTracker::load_cache(&path, 1432, 432)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            ScheduleTracker::load_cache(&path, 1000, 432)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
 }
@@ -1,6 +1,8 @@
 use crate::Slot;
 use solana_client::rpc_response::SlotUpdate;
 use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
 
 const MAX_SLOT_SKIP_DISTANCE: u64 = 48;
 const RECENT_LEADER_SLOTS_CAPACITY: usize = 48;
@@ -23,10 +25,24 @@ impl SlotEvent {
     }
 }
 
+/// Which slot to resolve leaders from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TargetSlot {
+    /// The estimated current slot. Lowest latency, but the estimate may run
+    /// ahead of what the cluster has actually completed.
+    #[default]
+    LeadingEdge,
+    /// The slot right after the most recently completed one.
+    Completed,
+}
+
 #[derive(Debug)]
 pub struct SlotsTracker {
     recent_events: VecDeque<SlotEvent>,
     current_slot: Slot,
+    last_completed_slot: Slot,
+    /// When the current slot estimate last moved forward
+    current_slot_since: Option<Instant>,
 }
 
 impl SlotsTracker {
@@ -34,6 +50,8 @@ impl SlotsTracker {
         Self {
             recent_events: VecDeque::with_capacity(RECENT_LEADER_SLOTS_CAPACITY),
             current_slot: 0,
+            last_completed_slot: 0,
+            current_slot_since: None,
         }
     }
 
@@ -41,6 +59,21 @@ impl SlotsTracker {
         self.current_slot
     }
 
+    /// Time since the current slot estimate was first observed, or `None`
+    /// before any slot is known.
+    pub fn time_in_current_slot(&self) -> Option<Duration> {
+        self.current_slot_since.map(|since| since.elapsed())
+    }
+
+    /// Returns the slot to resolve leaders from under `target`, or 0 if unknown.
+    pub fn target_slot(&self, target: TargetSlot) -> Slot {
+        match target {
+            TargetSlot::LeadingEdge => self.current_slot,
+            TargetSlot::Completed if self.last_completed_slot == 0 => self.current_slot,
+            TargetSlot::Completed => self.last_completed_slot + 1,
+        }
+    }
+
     /// Records a slot update and returns the new current slot estimate if processed
     pub fn record(&mut self, slot_event: SlotUpdate) -> Option<Slot> {
         let event = match slot_event {
@@ -57,43 +90,72 @@ impl SlotsTracker {
             self.recent_events.drain(..excess);
         }
 
-        self.current_slot = self.estimate_current_slot();
+        let estimate = self.estimate_current_slot();
+        if estimate > self.current_slot {
+            self.current_slot_since = Some(Instant::now());
+        }
+        self.current_slot = estimate;
+
+        // Only trust completions that don't run ahead of the estimate (outliers)
+        if let SlotEvent::End(slot) = event
+            && slot < self.current_slot
+        {
+            self.last_completed_slot = self.last_completed_slot.max(slot);
+        }
+
         Some(self.current_slot)
     }
 
+    /// Estimates the current slot from the recent events.
+    ///
+    /// Start and End events are considered separately, so a burst of one kind
+    /// can't skew the other: a Start means the slot is underway, an End means
+    /// the next one is. Each kind yields its latest slot that is not an
+    /// outlier, see [`latest_reasonable`], and the estimate is the greater of
+    /// the two. A Start estimate more than `MAX_SLOT_SKIP_DISTANCE` ahead of
+    /// the End estimate is ignored, since shreds can be seen well before the
+    /// cluster reaches their slot.
     fn estimate_current_slot(&self) -> Slot {
-        if self.recent_events.is_empty() {
-            return self.current_slot;
+        let latest = |start: bool| {
+            latest_reasonable(
+                self.recent_events
+                    .iter()
+                    .filter(|e| e.is_start() == start)
+                    .map(SlotEvent::slot)
+                    .collect(),
+            )
+        };
+        let started = latest(true);
+        let completed = latest(false).map(|slot| slot.saturating_add(1));
+
+        match (started, completed) {
+            (Some(started), Some(completed))
+                if started > completed.saturating_add(MAX_SLOT_SKIP_DISTANCE) =>
+            {
+                completed
+            }
+            (Some(started), Some(completed)) => started.max(completed),
+            (started, completed) => started.or(completed).unwrap_or(self.current_slot),
         }
+    }
+}
 
-        let mut sorted_events: Vec<SlotEvent> = self.recent_events.iter().copied().collect();
+/// Returns the latest of `slots` that is not an outlier, or `None` if there
+/// are none.
+///
+/// Uses the median to filter out outliers (validators broadcasting far-future
+/// slots): a slot counts if it is at most `MAX_SLOT_SKIP_DISTANCE` past where
+/// the median would put the latest slot.
+fn latest_reasonable(mut slots: Vec<Slot>) -> Option<Slot> {
+    slots.sort_unstable();
 
-        sorted_events.sort_unstable_by(|a, b| {
-            a.slot()
-                .cmp(&b.slot())
-                .then_with(|| b.is_start().cmp(&a.is_start()))
-        });
+    let max_idx = slots.len().checked_sub(1)?;
+    let median_idx = max_idx / 2;
+    let expected_latest = slots[median_idx] + (max_idx - median_idx) as u64;
+    let max_reasonable = expected_latest + MAX_SLOT_SKIP_DISTANCE;
 
-        // Use median to filter out outliers (validators broadcasting far-future slots)
-        let max_idx = sorted_events.len() - 1;
-        let median_idx = max_idx / 2;
-        let median_slot = sorted_events[median_idx].slot();
-        let expected_current = median_slot + (max_idx - median_idx) as u64;
-        let max_reasonable = expected_current + MAX_SLOT_SKIP_DISTANCE;
-
-        // Find the most recent reasonable slot
-        let idx = sorted_events
-            .iter()
-            .rposition(|e| e.slot() <= max_reasonable)
-            .unwrap_or(median_idx); // Fallback to median if all slots unreasonable
-
-        let slot_event = &sorted_events[idx];
-        if slot_event.is_start() {
-            slot_event.slot()
-        } else {
-            slot_event.slot().saturating_add(1)
-        }
-    }
+    // The median itself always qualifies
+    slots.into_iter().rev().find(|slot| *slot <= max_reasonable)
 }
 
 impl Default for SlotsTracker {
@@ -153,6 +215,64 @@ mod tests {
         assert_eq!(tracker.current_slot(), 14);
     }
 
+    #[test]
+    fn test_target_slot() {
+        let mut tracker = SlotsTracker::new();
+        for slot in 100..=104 {
+            tracker.record(SlotUpdate::FirstShredReceived { slot, timestamp: 0 });
+        }
+        tracker.record(SlotUpdate::Completed {
+            slot: 101,
+            timestamp: 0,
+        });
+
+        assert_eq!(tracker.target_slot(TargetSlot::LeadingEdge), 104);
+        assert_eq!(tracker.target_slot(TargetSlot::Completed), 102);
+    }
+
+    #[test]
+    fn test_estimate_from_a_single_event_kind() {
+        let record_all = |events: Vec<SlotUpdate>| {
+            let mut tracker = SlotsTracker::new();
+            for event in events {
+                tracker.record(event);
+            }
+            tracker.current_slot()
+        };
+
+        // Only shreds: the latest started slot is underway
+        let starts = (100..=111)
+            .map(|slot| SlotUpdate::FirstShredReceived { slot, timestamp: 0 })
+            .collect();
+        assert_eq!(record_all(starts), 111);
+
+        // Only completions: the slot after the latest completed one is underway
+        let ends = (100..=111)
+            .map(|slot| SlotUpdate::Completed { slot, timestamp: 0 })
+            .collect();
+        assert_eq!(record_all(ends), 112);
+    }
+
+    #[test]
+    fn test_estimate_takes_the_further_kind() {
+        let mut tracker = SlotsTracker::new();
+        for slot in 100..=105 {
+            tracker.recent_events.push_back(SlotEvent::Start(slot));
+        }
+        for slot in 100..=120 {
+            tracker.recent_events.push_back(SlotEvent::End(slot));
+        }
+        // Lagging shreds don't hold back the completions
+        assert_eq!(tracker.estimate_current_slot(), 121);
+
+        // A burst of shreds far past the completions is not trusted
+        let mut tracker = tracker_from_slots((100..=110).collect());
+        for slot in 200..=215 {
+            tracker.recent_events.push_back(SlotEvent::Start(slot));
+        }
+        assert_eq!(tracker.estimate_current_slot(), 111);
+    }
+
     #[test]
     fn test_outlier_rejection() {
         // Slot 100 is way beyond MAX_SLOT_SKIP_DISTANCE from slot 1
@@ -0,0 +1,236 @@
+//! Where the leader tracker gets epochs, schedules, cluster nodes and slot
+//! updates from.
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use futures_util::FutureExt;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use log::{debug, warn};
+use solana_client::nonblocking::pubsub_client::{PubsubClient, UnsubscribeFn};
+use solana_client::rpc_response::{RpcContactInfo, RpcLeaderSchedule, SlotUpdate};
+use solana_sdk::epoch_info::EpochInfo;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::Slot;
+use crate::tpu_client::tracker::failover_rpc::FailoverRpcClient;
+use crate::tpu_client::tracker::leader_tracker::{LeaderTrackerConfig, SlotSource};
+
+/// Time the RPC node gets to acknowledge an unsubscribe or a shutdown.
+const UNSUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Slot updates buffered between the WebSocket and the tracker.
+const SLOT_STREAM_BUFFER: usize = 1024;
+
+/// Cluster data the [`LeaderTracker`](super::leader_tracker::LeaderTracker)
+/// is built from.
+///
+/// [`RpcLeaderSource`] reads it from a validator's JSON-RPC and WebSocket
+/// endpoints. Implement this to back the tracker with another feed, such as a
+/// Geyser plugin.
+pub trait LeaderSource: Send + Sync + 'static {
+    /// Current epoch and the position of the current slot in it.
+    fn epoch_info(&self) -> impl Future<Output = Result<EpochInfo>> + Send;
+
+    /// Leader schedule of the epoch containing `slot`, as identity -> slot
+    /// indices within the epoch. `None` if it isn't known yet.
+    fn leader_schedule(
+        &self,
+        slot: Slot,
+    ) -> impl Future<Output = Result<Option<RpcLeaderSchedule>>> + Send;
+
+    /// Nodes currently in the cluster, with the sockets they advertise.
+    fn cluster_nodes(&self) -> impl Future<Output = Result<Vec<RpcContactInfo>>> + Send;
+
+    /// Subscribes to slot updates. The stream ends when the subscription is
+    /// lost, and the subscription ends when the stream is dropped.
+    fn slot_stream(&self) -> impl Future<Output = Result<BoxStream<'static, SlotUpdate>>> + Send;
+}
+
+/// Reads cluster data from a validator's JSON-RPC and WebSocket endpoints, or
+/// slot updates from gRPC if [`SlotSource::Grpc`] is configured. JSON-RPC
+/// calls fail over between `rpc_url` and the fallback endpoints.
+pub struct RpcLeaderSource {
+    rpc_client: FailoverRpcClient,
+    ws_url: String,
+    slot_source: SlotSource,
+}
+
+impl RpcLeaderSource {
+    pub fn new(config: &LeaderTrackerConfig) -> Self {
+        Self {
+            rpc_client: FailoverRpcClient::new(
+                std::iter::once(config.rpc_url.clone())
+                    .chain(config.fallback_rpc_urls.iter().cloned()),
+            ),
+            ws_url: config.ws_url.clone(),
+            slot_source: config.slot_source.clone(),
+        }
+    }
+
+    /// The subscription runs in its own task, which owns the WebSocket. When
+    /// the subscription ends or the stream is dropped, the task unsubscribes
+    /// and closes the WebSocket, so reconnects don't pile up subscriptions on
+    /// the RPC node.
+    async fn ws_slot_stream(&self) -> Result<BoxStream<'static, SlotUpdate>> {
+        let ws_client = PubsubClient::new(&self.ws_url)
+            .await
+            .context("Failed to connect to WebSocket")?;
+
+        let (subscribed, subscribe_result) = oneshot::channel();
+        let (updates, receiver) = mpsc::channel(SLOT_STREAM_BUFFER);
+        tokio::spawn(async move {
+            let (mut notifications, unsubscribe) = match ws_client.slot_updates_subscribe().await {
+                Ok(subscription) => {
+                    let _ = subscribed.send(Ok(()));
+                    subscription
+                }
+                Err(e) => {
+                    let _ = subscribed.send(Err(e));
+                    return;
+                }
+            };
+            let subscription = Subscription(Some(unsubscribe));
+
+            loop {
+                tokio::select! {
+                    update = notifications.next() => match update {
+                        Some(update) if updates.send(update).await.is_ok() => {}
+                        _ => break,
+                    },
+                    _ = updates.closed() => break,
+                }
+            }
+
+            // The stream borrows the client, which has to outlive the unsubscribe
+            drop(notifications);
+            subscription.unsubscribe().await;
+            match tokio::time::timeout(UNSUBSCRIBE_TIMEOUT, ws_client.shutdown()).await {
+                Ok(Ok(())) => debug!("Closed slot updates WebSocket"),
+                Ok(Err(e)) => warn!("Failed to close slot updates WebSocket: {}", e),
+                Err(_) => warn!("Timed out closing slot updates WebSocket"),
+            }
+        });
+
+        subscribe_result
+            .await
+            .map_err(|_| anyhow!("Slot updates subscription task ended"))?
+            .context("Failed to subscribe to slot updates")?;
+
+        let updates = stream::unfold(receiver, |mut receiver| async move {
+            let update = receiver.recv().await?;
+            Some((update, receiver))
+        });
+        Ok(updates.boxed())
+    }
+}
+
+impl fmt::Debug for RpcLeaderSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcLeaderSource")
+            .field("rpc_urls", &self.rpc_client.urls())
+            .field("ws_url", &self.ws_url)
+            .field("slot_source", &self.slot_source)
+            .finish()
+    }
+}
+
+impl LeaderSource for RpcLeaderSource {
+    async fn epoch_info(&self) -> Result<EpochInfo> {
+        self.rpc_client
+            .call("get_epoch_info", |client| client.get_epoch_info().boxed())
+            .await
+    }
+
+    async fn leader_schedule(&self, slot: Slot) -> Result<Option<RpcLeaderSchedule>> {
+        self.rpc_client
+            .call("get_leader_schedule", |client| {
+                client.get_leader_schedule(Some(slot)).boxed()
+            })
+            .await
+    }
+
+    async fn cluster_nodes(&self) -> Result<Vec<RpcContactInfo>> {
+        self.rpc_client
+            .call("get_cluster_nodes", |client| {
+                client.get_cluster_nodes().boxed()
+            })
+            .await
+    }
+
+    async fn slot_stream(&self) -> Result<BoxStream<'static, SlotUpdate>> {
+        match &self.slot_source {
+            SlotSource::Rpc => self.ws_slot_stream().await,
+            #[cfg(feature = "grpc")]
+            SlotSource::Grpc { endpoint, x_token } => {
+                super::grpc_slots::slot_stream(endpoint, x_token.as_deref()).await
+            }
+            #[cfg(not(feature = "grpc"))]
+            SlotSource::Grpc { .. } => anyhow::bail!("gRPC slot updates require the grpc feature"),
+        }
+    }
+}
+
+/// Slot updates subscription that is unsubscribed from when dropped, so a
+/// cancelled listener doesn't leave it behind on the RPC node.
+struct Subscription(Option<UnsubscribeFn>);
+
+impl Subscription {
+    /// Unsubscribes and waits for the RPC node to acknowledge it.
+    async fn unsubscribe(mut self) {
+        if let Some(unsubscribe) = self.0.take()
+            && tokio::time::timeout(UNSUBSCRIBE_TIMEOUT, unsubscribe())
+                .await
+                .is_err()
+        {
+            warn!("Timed out unsubscribing from slot updates");
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // Dropped without `unsubscribe`, i.e. the task was cancelled. The
+        // WebSocket may already be gone, in which case the node drops the
+        // subscription with the connection.
+        if let Some(unsubscribe) = self.0.take()
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            runtime.spawn(unsubscribe());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_subscription_unsubscribes_once() {
+        let subscription = |calls: Arc<AtomicUsize>| {
+            let unsubscribe: UnsubscribeFn = Box::new(move || {
+                async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                }
+                .boxed()
+            });
+            Subscription(Some(unsubscribe))
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        subscription(calls.clone()).unsubscribe().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Dropping without unsubscribing, as a cancelled task would
+        let calls = Arc::new(AtomicUsize::new(0));
+        drop(subscription(calls.clone()));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,678 @@
+//! Forwarding metrics, a periodic StatsD / InfluxDB line-protocol pusher and
+//! an HTTP endpoint for Prometheus scrapes and status queries.
+
+use anyhow::{Context, Result};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CONTENT_TYPE, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use log::{debug, info, warn};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::task::JoinSet;
+
+/// Number of recent latency samples percentiles are computed over.
+const LATENCY_WINDOW: usize = 1024;
+/// Upper bounds, in seconds, of the forward latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+/// Path the HTTP endpoint serves Prometheus metrics on.
+pub const PROMETHEUS_PATH: &str = "/metrics";
+/// Path the HTTP endpoint serves the status JSON on.
+pub const STATUS_PATH: &str = "/status";
+/// Path of the liveness probe.
+pub const LIVENESS_PATH: &str = "/healthz";
+/// Path of the readiness probe.
+pub const READINESS_PATH: &str = "/readyz";
+/// Time a scraper gets to send its request.
+const SCRAPE_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most HTTP connections served at once. Further connections are closed
+/// until one finishes.
+const MAX_HTTP_CONNECTIONS: usize = 64;
+/// Most leaders deliveries are counted for. Past it, the leader sent to
+/// least recently is forgotten, as leaders come and go with the schedule.
+const MAX_TRACKED_LEADERS: usize = 2048;
+
+/// Deliveries to a single leader.
+#[derive(Debug, Clone, Copy)]
+struct LeaderCounts {
+    delivered: u64,
+    failed: u64,
+    last_sent: Instant,
+}
+
+/// Counters updated on the session and forwarding paths.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    sessions: AtomicU64,
+    received: AtomicU64,
+    forwarded: AtomicU64,
+    failed: AtomicU64,
+    udp_fallbacks: AtomicU64,
+    latencies_us: Mutex<VecDeque<u64>>,
+    /// Forwarded transactions per latency bucket, plus one for overflow
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    latency_sum_us: AtomicU64,
+    /// Keyed by (leader identity, socket)
+    leaders: Mutex<BTreeMap<(String, String), LeaderCounts>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an accepted WebTransport session.
+    pub fn record_session(&self) {
+        self.sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records `count` transactions read from clients.
+    pub fn record_received(&self, count: usize) {
+        self.received.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Records the outcome of sending a transaction to one leader.
+    pub fn record_leader_delivery(&self, identity: &str, socket: &str, delivered: bool) {
+        let key = (identity.to_string(), socket.to_string());
+        let now = Instant::now();
+
+        let mut leaders = self.leaders.lock().unwrap();
+        if leaders.len() >= MAX_TRACKED_LEADERS
+            && !leaders.contains_key(&key)
+            && let Some(stale) = leaders
+                .iter()
+                .min_by_key(|(_, counts)| counts.last_sent)
+                .map(|(key, _)| key.clone())
+        {
+            leaders.remove(&stale);
+        }
+        let counts = leaders.entry(key).or_insert(LeaderCounts {
+            delivered: 0,
+            failed: 0,
+            last_sent: now,
+        });
+        counts.last_sent = now;
+        if delivered {
+            counts.delivered += 1;
+        } else {
+            counts.failed += 1;
+        }
+    }
+
+    /// Records a transaction that reached at least one leader.
+    pub fn record_forwarded(&self, latency: Duration) {
+        self.forwarded.fetch_add(1, Ordering::Relaxed);
+
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|le| latency.as_secs_f64() <= *le)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+
+        let mut latencies = self.latencies_us.lock().unwrap();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency.as_micros() as u64);
+    }
+
+    /// Records a transaction that could not be forwarded.
+    pub fn record_failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a send to a leader's UDP TPU port after its QUIC connection
+    /// failed.
+    pub fn record_udp_fallback(&self) {
+        self.udp_fallbacks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the forwarding counters. Pool and slot gauges are left at zero
+    /// for the caller to fill in.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut latencies: Vec<u64> = self.latencies_us.lock().unwrap().iter().copied().collect();
+        latencies.sort_unstable();
+
+        MetricsSnapshot {
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            connection_count: 0,
+            current_slot: 0,
+            latency_p50_ms: percentile_ms(&latencies, 50),
+            latency_p99_ms: percentile_ms(&latencies, 99),
+        }
+    }
+}
+
+impl Metrics {
+    /// Renders all metrics in the Prometheus text exposition format, with
+    /// the pool and slot gauges given by the caller.
+    pub fn render_prometheus(
+        &self,
+        connection_count: u64,
+        open_streams: u64,
+        current_slot: u64,
+    ) -> String {
+        let mut out = String::new();
+        let counter = |out: &mut String, name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {value}");
+        };
+        let gauge = |out: &mut String, name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            let _ = writeln!(out, "{name} {value}");
+        };
+
+        counter(
+            &mut out,
+            "bifrost_sessions_accepted_total",
+            "WebTransport sessions accepted.",
+            self.sessions.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "bifrost_transactions_received_total",
+            "Transactions read from clients.",
+            self.received.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "bifrost_transactions_forwarded_total",
+            "Transactions delivered to at least one leader.",
+            self.forwarded.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "bifrost_transactions_failed_total",
+            "Transactions that reached no leader.",
+            self.failed.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "bifrost_udp_fallbacks_total",
+            "Sends to a leader over UDP after its QUIC connection failed.",
+            self.udp_fallbacks.load(Ordering::Relaxed),
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP bifrost_leader_deliveries_total Sends to each leader, by result."
+        );
+        let _ = writeln!(out, "# TYPE bifrost_leader_deliveries_total counter");
+        for ((identity, socket), counts) in self.leaders.lock().unwrap().iter() {
+            for (result, value) in [("delivered", counts.delivered), ("failed", counts.failed)] {
+                let _ = writeln!(
+                    out,
+                    "bifrost_leader_deliveries_total{{leader=\"{identity}\",socket=\"{socket}\",result=\"{result}\"}} {value}"
+                );
+            }
+        }
+
+        let name = "bifrost_forward_latency_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Time from submission until the first leader accepted a transaction."
+        );
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (le, bucket) in LATENCY_BUCKETS.iter().zip(&self.latency_buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        cumulative += self.latency_buckets[LATENCY_BUCKETS.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {cumulative}");
+        let sum = self.latency_sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {cumulative}");
+
+        gauge(
+            &mut out,
+            "bifrost_connections",
+            "Connections in the TPU connection pool.",
+            connection_count,
+        );
+        gauge(
+            &mut out,
+            "bifrost_connection_streams",
+            "Streams open on pooled TPU connections.",
+            open_streams,
+        );
+        gauge(
+            &mut out,
+            "bifrost_current_slot",
+            "Current slot as seen by the leader tracker.",
+            current_slot,
+        );
+
+        out
+    }
+}
+
+fn percentile_ms(sorted_us: &[u64], pct: usize) -> f64 {
+    if sorted_us.is_empty() {
+        return 0.0;
+    }
+    let idx = (sorted_us.len() - 1) * pct / 100;
+    sorted_us[idx] as f64 / 1000.0
+}
+
+/// Point-in-time values of the key metrics.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub forwarded: u64,
+    pub failed: u64,
+    pub connection_count: u64,
+    pub current_slot: u64,
+    pub latency_p50_ms: f64,
+    pub latency_p99_ms: f64,
+}
+
+impl MetricsSnapshot {
+    /// Fraction of transactions forwarded successfully, 1.0 if none were seen.
+    pub fn success_rate(&self) -> f64 {
+        let total = self.forwarded + self.failed;
+        if total == 0 {
+            return 1.0;
+        }
+        self.forwarded as f64 / total as f64
+    }
+
+    /// Renders the snapshot as lines in the given wire format.
+    pub fn to_lines(&self, format: PushFormat) -> Vec<String> {
+        match format {
+            // Totals are sent as gauges so a lost packet doesn't skew counts
+            PushFormat::StatsD => vec![
+                format!("bifrost.forwarded:{}|g", self.forwarded),
+                format!("bifrost.failed:{}|g", self.failed),
+                format!("bifrost.success_rate:{}|g", self.success_rate()),
+                format!("bifrost.connections:{}|g", self.connection_count),
+                format!("bifrost.current_slot:{}|g", self.current_slot),
+                format!("bifrost.latency_p50_ms:{}|g", self.latency_p50_ms),
+                format!("bifrost.latency_p99_ms:{}|g", self.latency_p99_ms),
+            ],
+            PushFormat::Influx => vec![format!(
+                "bifrost forwarded={}i,failed={}i,success_rate={},connections={}i,current_slot={}i,latency_p50_ms={},latency_p99_ms={}",
+                self.forwarded,
+                self.failed,
+                self.success_rate(),
+                self.connection_count,
+                self.current_slot,
+                self.latency_p50_ms,
+                self.latency_p99_ms
+            )],
+        }
+    }
+}
+
+/// Wire format used by the metrics pusher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushFormat {
+    StatsD,
+    Influx,
+}
+
+/// Where and how often to push metrics.
+#[derive(Debug, Clone)]
+pub struct MetricsPushConfig {
+    pub target: SocketAddr,
+    pub interval: Duration,
+    pub format: PushFormat,
+}
+
+/// Sends `snapshot` to `target` as a single UDP datagram.
+pub async fn push_once(
+    socket: &UdpSocket,
+    target: SocketAddr,
+    snapshot: &MetricsSnapshot,
+    format: PushFormat,
+) -> Result<()> {
+    let payload = snapshot.to_lines(format).join("\n");
+    socket
+        .send_to(payload.as_bytes(), target)
+        .await
+        .context(format!("Failed to push metrics to {}", target))?;
+    Ok(())
+}
+
+/// Pushes a fresh snapshot from `sample` every `config.interval`, forever.
+///
+/// # Errors
+///
+/// Returns an error only if the local UDP socket cannot be bound. Send failures
+/// are logged and retried on the next tick.
+pub async fn run_pusher<F, Fut>(config: MetricsPushConfig, sample: F) -> Result<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = MetricsSnapshot>,
+{
+    let bind_addr: SocketAddr = if config.target.is_ipv4() {
+        "0.0.0.0:0".parse()?
+    } else {
+        "[::]:0".parse()?
+    };
+    let socket = UdpSocket::bind(bind_addr)
+        .await
+        .context("Failed to bind metrics socket")?;
+
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        interval.tick().await;
+
+        let snapshot = sample().await;
+        match push_once(&socket, config.target, &snapshot, config.format).await {
+            Ok(()) => debug!("Pushed metrics to {}", config.target),
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+/// Documents served by the HTTP endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpRoute {
+    /// Prometheus metrics, on `GET /metrics`
+    Metrics,
+    /// Leader tracker status as JSON, on `GET /status`
+    Status,
+    /// Liveness probe, on `GET /healthz`
+    Liveness,
+    /// Readiness probe, on `GET /readyz`
+    Readiness,
+}
+
+impl HttpRoute {
+    fn from_path(path: &str) -> Option<Self> {
+        match path {
+            PROMETHEUS_PATH => Some(HttpRoute::Metrics),
+            STATUS_PATH => Some(HttpRoute::Status),
+            LIVENESS_PATH => Some(HttpRoute::Liveness),
+            READINESS_PATH => Some(HttpRoute::Readiness),
+            _ => None,
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            HttpRoute::Metrics => "text/plain; version=0.0.4",
+            HttpRoute::Status | HttpRoute::Readiness => "application/json",
+            HttpRoute::Liveness => "text/plain",
+        }
+    }
+}
+
+/// Document served for an [`HttpRoute`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Answered with 200 OK if set, 503 Service Unavailable otherwise, which
+    /// is what probes go by
+    pub available: bool,
+    pub body: String,
+}
+
+impl From<String> for HttpResponse {
+    fn from(body: String) -> Self {
+        Self {
+            available: true,
+            body,
+        }
+    }
+}
+
+/// Serves the response `render` returns for each [`HttpRoute`] on `addr`,
+/// forever.
+///
+/// Each connection is served on its own task, one request per connection, so
+/// a slow client can't hold up other scrapes.
+///
+/// # Errors
+///
+/// Returns an error only if `addr` cannot be bound. Failed requests are logged.
+pub async fn serve_http<F, Fut>(addr: SocketAddr, render: F) -> Result<()>
+where
+    F: Fn(HttpRoute) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr)
+        .await
+        .context(format!("Failed to bind HTTP endpoint to {}", addr))?;
+    info!(
+        "Serving {}, {}, {} and {} on http://{}",
+        PROMETHEUS_PATH, STATUS_PATH, LIVENESS_PATH, READINESS_PATH, addr
+    );
+
+    serve_scrapes(listener, render).await
+}
+
+async fn serve_scrapes<F, Fut>(listener: TcpListener, render: F) -> Result<()>
+where
+    F: Fn(HttpRoute) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    let render = Arc::new(render);
+    // Aborted along with the endpoint
+    let mut connections = JoinSet::new();
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Failed to accept metrics connection: {}", e);
+                continue;
+            }
+        };
+
+        while connections.try_join_next().is_some() {}
+        if connections.len() >= MAX_HTTP_CONNECTIONS {
+            debug!("Closing metrics connection from {}, too many open", peer);
+            continue;
+        }
+
+        let render = render.clone();
+        connections.spawn(async move {
+            let service = service_fn(move |request| {
+                let render = render.clone();
+                async move { Ok::<_, Infallible>(answer_scrape(request, &*render).await) }
+            });
+            let served = http1::Builder::new()
+                .timer(TokioTimer::new())
+                .header_read_timeout(SCRAPE_REQUEST_TIMEOUT)
+                .keep_alive(false)
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+            if let Err(e) = served {
+                debug!("Metrics scrape from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn answer_scrape<F, Fut>(request: Request<Incoming>, render: &F) -> Response<Full<Bytes>>
+where
+    F: Fn(HttpRoute) -> Fut,
+    Fut: Future<Output = HttpResponse>,
+{
+    let route = match *request.method() {
+        Method::GET => HttpRoute::from_path(request.uri().path()),
+        _ => None,
+    };
+    let (status, content_type, body) = match route {
+        Some(route) => {
+            let response = render(route).await;
+            let status = match response.available {
+                true => StatusCode::OK,
+                false => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            (status, route.content_type(), response.body)
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "Not Found\n".to_string(),
+        ),
+    };
+
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[test]
+    fn test_snapshot_counts_and_percentiles() {
+        let metrics = Metrics::new();
+        for ms in 1..=100 {
+            metrics.record_forwarded(Duration::from_millis(ms));
+        }
+        metrics.record_failed();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.forwarded, 100);
+        assert_eq!(snapshot.failed, 1);
+        assert_eq!(snapshot.latency_p50_ms, 50.0);
+        assert_eq!(snapshot.latency_p99_ms, 99.0);
+    }
+
+    #[tokio::test]
+    async fn test_push_emits_lines() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = receiver.local_addr().unwrap();
+
+        let snapshot = MetricsSnapshot {
+            forwarded: 3,
+            failed: 1,
+            connection_count: 2,
+            current_slot: 42,
+            latency_p50_ms: 1.5,
+            latency_p99_ms: 4.0,
+        };
+
+        let mut buf = vec![0u8; 2048];
+
+        push_once(&sender, target, &snapshot, PushFormat::StatsD)
+            .await
+            .unwrap();
+        let len = receiver.recv(&mut buf).await.unwrap();
+        let lines = String::from_utf8_lossy(&buf[..len]).to_string();
+        assert!(lines.contains("bifrost.forwarded:3|g"));
+        assert!(lines.contains("bifrost.success_rate:0.75|g"));
+        assert!(lines.contains("bifrost.connections:2|g"));
+        assert!(lines.contains("bifrost.current_slot:42|g"));
+        assert!(lines.contains("bifrost.latency_p99_ms:4|g"));
+
+        push_once(&sender, target, &snapshot, PushFormat::Influx)
+            .await
+            .unwrap();
+        let len = receiver.recv(&mut buf).await.unwrap();
+        let line = String::from_utf8_lossy(&buf[..len]).to_string();
+        assert!(line.starts_with("bifrost forwarded=3i,failed=1i,success_rate=0.75"));
+        assert!(line.contains("current_slot=42i"));
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_scrape() {
+        let metrics = std::sync::Arc::new(Metrics::new());
+        metrics.record_session();
+        metrics.record_received(3);
+        metrics.record_forwarded(Duration::from_millis(3));
+        metrics.record_forwarded(Duration::from_secs(10));
+        metrics.record_failed();
+        metrics.record_leader_delivery("leader-1", "10.0.0.1:8009", true);
+        metrics.record_leader_delivery("leader-1", "10.0.0.1:8009", false);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = metrics.clone();
+        tokio::spawn(serve_scrapes(listener, move |route| {
+            let metrics = served.clone();
+            async move {
+                match route {
+                    HttpRoute::Metrics => metrics.render_prometheus(2, 5, 42).into(),
+                    HttpRoute::Status => "{\"current_slot\":42}".to_string().into(),
+                    HttpRoute::Liveness => "ok\n".to_string().into(),
+                    HttpRoute::Readiness => HttpResponse {
+                        available: false,
+                        body: "{\"current_slot\":0}".to_string(),
+                    },
+                }
+            }
+        }));
+
+        // A client that never sends its request doesn't hold up others
+        let _idle = TcpStream::connect(addr).await.unwrap();
+
+        let scrape = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let response = scrape(PROMETHEUS_PATH).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        for line in [
+            "bifrost_sessions_accepted_total 1",
+            "bifrost_transactions_received_total 3",
+            "bifrost_transactions_forwarded_total 2",
+            "bifrost_transactions_failed_total 1",
+            "bifrost_leader_deliveries_total{leader=\"leader-1\",socket=\"10.0.0.1:8009\",result=\"failed\"} 1",
+            "bifrost_forward_latency_seconds_bucket{le=\"0.005\"} 1",
+            "bifrost_forward_latency_seconds_bucket{le=\"+Inf\"} 2",
+            "bifrost_forward_latency_seconds_count 2",
+            "bifrost_connections 2",
+            "bifrost_connection_streams 5",
+            "bifrost_current_slot 42",
+        ] {
+            assert!(response.lines().any(|l| l == line), "missing {line}");
+        }
+
+        let response = scrape(STATUS_PATH).await;
+        assert!(response.contains("content-type: application/json"));
+        assert!(response.ends_with("{\"current_slot\":42}"));
+
+        assert!(scrape(LIVENESS_PATH).await.starts_with("HTTP/1.1 200 OK"));
+        let response = scrape(READINESS_PATH).await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(response.ends_with("{\"current_slot\":0}"));
+
+        assert!(scrape("/").await.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn test_leader_counts_are_capped() {
+        let metrics = Metrics::new();
+        for i in 0..MAX_TRACKED_LEADERS + 1 {
+            metrics.record_leader_delivery(&format!("leader-{}", i), "10.0.0.1:8009", true);
+        }
+
+        // The leader sent to least recently made room
+        let leaders = metrics.leaders.lock().unwrap();
+        assert_eq!(leaders.len(), MAX_TRACKED_LEADERS);
+        let first = ("leader-0".to_string(), "10.0.0.1:8009".to_string());
+        assert!(!leaders.contains_key(&first));
+    }
+}
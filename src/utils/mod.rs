@@ -0,0 +1,26 @@
+pub mod metrics;
+
+use rand::Rng;
+use std::time::Duration;
+
+/// Random duration of at most `max`, to keep periodic work of many tasks or
+/// instances from running in lockstep.
+pub(crate) fn jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    rand::thread_rng().gen_range(Duration::ZERO..=max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_bound() {
+        assert_eq!(jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_millis(5)) <= Duration::from_millis(5));
+        }
+    }
+}
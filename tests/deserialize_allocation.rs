@@ -0,0 +1,119 @@
+//! Fuzzes transaction deserialization with arbitrary bytes and checks that no
+//! payload makes it allocate far more than its own size.
+//!
+//! This is its own test binary because it replaces the global allocator.
+
+use bifrost::constants::MAX_TRANSACTION_SIZE;
+use bifrost::server::deserialize_transaction;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::{VersionedMessage, v0};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+
+/// Largest single allocation made on the current thread while armed, so
+/// deserialization can be checked for allocation amplification.
+mod largest_allocation {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        static ARMED: Cell<bool> = const { Cell::new(false) };
+        static LARGEST: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct Tracking;
+
+    unsafe impl GlobalAlloc for Tracking {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ARMED.try_with(|armed| {
+                if armed.get() {
+                    LARGEST.with(|largest| largest.set(largest.get().max(layout.size())));
+                }
+            });
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Tracking = Tracking;
+
+    /// Runs `f` and returns the size of the largest allocation it made.
+    pub(super) fn during<T>(f: impl FnOnce() -> T) -> usize {
+        LARGEST.with(|largest| largest.set(0));
+        ARMED.with(|armed| armed.set(true));
+        let _ = f();
+        ARMED.with(|armed| armed.set(false));
+        LARGEST.with(Cell::get)
+    }
+}
+
+fn v0_transaction(payer: &Keypair) -> VersionedTransaction {
+    let message = v0::Message::try_compile(&payer.pubkey(), &[], &[], Hash::default()).unwrap();
+    VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer]).unwrap()
+}
+
+#[test]
+fn test_deserialization_survives_arbitrary_bytes() {
+    // Compiled instructions are the largest elements per wire byte
+    let max_allocation = MAX_TRANSACTION_SIZE * 32;
+    let check = |tx_data: &[u8]| {
+        let largest = largest_allocation::during(|| deserialize_transaction(tx_data));
+        assert!(
+            largest <= max_allocation,
+            "allocated {} bytes for {:02x?}",
+            largest,
+            tx_data
+        );
+    };
+
+    let payer = Keypair::new();
+    let legacy = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![AccountMeta::new(payer.pubkey(), true)],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    let valid = [
+        bincode::serialize(&legacy).unwrap(),
+        bincode::serialize(&v0_transaction(&payer)).unwrap(),
+    ];
+
+    // A huge length prefix at every offset of valid transactions
+    for tx_data in &valid {
+        for offset in 0..tx_data.len() {
+            let mut mutated = tx_data.clone();
+            let end = (offset + 3).min(mutated.len());
+            mutated[offset..end].copy_from_slice(&[0xff, 0xff, 0x03][..end - offset]);
+            check(&mutated);
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(0xb1f);
+    for _ in 0..10_000 {
+        // Random bytes, or a valid transaction with a few bytes flipped
+        let tx_data = if rng.gen_bool(0.5) {
+            let len = rng.gen_range(0..=MAX_TRANSACTION_SIZE);
+            (0..len).map(|_| rng.r#gen()).collect::<Vec<u8>>()
+        } else {
+            let mut mutated = valid[rng.gen_range(0..valid.len())].clone();
+            for _ in 0..rng.gen_range(1..=4) {
+                let offset = rng.gen_range(0..mutated.len());
+                mutated[offset] = rng.r#gen();
+            }
+            mutated
+        };
+        check(&tx_data);
+    }
+}